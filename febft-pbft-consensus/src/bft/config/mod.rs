@@ -1,11 +1,18 @@
+use std::sync::Arc;
 use std::time::Duration;
 use serde::Deserialize;
 
+use crate::bft::proposer::backpressure::ExecutionBacklog;
+
 #[derive(Debug, Deserialize)]
 pub struct PBFTConfig {
     pub timeout_dur: Duration,
     pub proposer_config: ProposerConfig,
     pub watermark: u32,
+    #[serde(default)]
+    pub sync_config: SynchronizerConfig,
+    #[serde(default)]
+    pub view_change_timeout: ViewChangeTimeoutConfig,
 }
 
 impl PBFTConfig {
@@ -15,6 +22,8 @@ impl PBFTConfig {
             timeout_dur,
             proposer_config,
             watermark,
+            sync_config: SynchronizerConfig::default(),
+            view_change_timeout: ViewChangeTimeoutConfig::default(),
         }
     }
 }
@@ -24,10 +33,229 @@ pub struct ProposerConfig {
     pub target_batch_size: u64,
     pub max_batch_size: u64,
     pub batch_timeout: u64,
+    /// The maximum time (in micros) a request can wait in a batch that is being
+    /// assembled before the batch is proposed, regardless of its size.
+    /// Unlike the batch timeout, this is counted from the first request in the batch
+    #[serde(default)]
+    pub max_assembly_latency: Option<u64>,
+    /// Adapt the target batch size to the load, instead of always using `target_batch_size`
+    #[serde(default)]
+    pub adaptive_batch_size: Option<AdaptiveBatchConfig>,
+    /// How many of the requests we have most recently proposed we remember,
+    /// so that we don't propose them again (e.g. when a client retransmits them)
+    #[serde(default = "default_recently_proposed_capacity")]
+    pub recently_proposed_capacity: usize,
+    /// The backlog of decided batches waiting for execution, shared with the execution layer
+    /// which reports the batches it executes. The proposer stops proposing new batches once it
+    /// reaches its high watermark, until the execution layer catches up. `None` disables the backpressure
+    #[serde(skip)]
+    pub execution_backlog: Option<Arc<ExecutionBacklog>>,
+}
+
+fn default_recently_proposed_capacity() -> usize {
+    16384
+}
+
+/// Bounds for adapting the target batch size to the load.
+/// The target grows while batches fill up before the batch timeout
+/// and shrinks when assembling a batch takes longer than `target_latency`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdaptiveBatchConfig {
+    pub min_batch_size: u64,
+    pub max_batch_size: u64,
+    /// The assembly latency (in micros) above which the target batch size shrinks
+    pub target_latency: u64,
+}
+
+impl AdaptiveBatchConfig {
+    pub fn new(min_batch_size: u64, max_batch_size: u64, target_latency: u64) -> Self {
+        Self { min_batch_size, max_batch_size, target_latency }
+    }
 }
 
 impl ProposerConfig {
     pub fn new(target_batch_size: u64, max_batch_size: u64, batch_timeout: u64) -> Self {
-        Self { target_batch_size, max_batch_size, batch_timeout }
+        Self {
+            target_batch_size,
+            max_batch_size,
+            batch_timeout,
+            max_assembly_latency: None,
+            adaptive_batch_size: None,
+            recently_proposed_capacity: default_recently_proposed_capacity(),
+            execution_backlog: None,
+        }
+    }
+
+    pub fn with_max_assembly_latency(mut self, max_assembly_latency: u64) -> Self {
+        self.max_assembly_latency = Some(max_assembly_latency);
+
+        self
+    }
+
+    pub fn with_adaptive_batch_size(mut self, adaptive_batch_size: AdaptiveBatchConfig) -> Self {
+        self.adaptive_batch_size = Some(adaptive_batch_size);
+
+        self
+    }
+
+    pub fn with_execution_backlog(mut self, execution_backlog: Arc<ExecutionBacklog>) -> Self {
+        self.execution_backlog = Some(execution_backlog);
+
+        self
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SynchronizerConfig {
+    /// How many of the latest view changes are kept in memory
+    pub view_change_history: usize,
+    /// How many views ahead of the next view we accept view change messages for.
+    /// Messages for views further ahead are dropped
+    pub max_view_look_ahead: usize,
+    /// How long we can be unable to reach a quorum before alerting
+    pub quorum_alert_after: Duration,
+    /// The maximum amount of timed out requests forwarded in a single message
+    pub forward_batch_size: usize,
+    /// The minimum interval between forwarding timed out requests
+    pub forward_interval: Duration,
+    /// The interval of the timer we register when we send our STOP (or STOP-QUORUM-JOIN) message.
+    /// Whenever it fires while the view change has not advanced past the stopping phase, we re-send it
+    pub stop_retransmit_interval: Duration,
+    /// How many times we re-send our STOP message for a single view change
+    pub max_stop_retransmits: usize,
+    /// What we do when the collects of a view change are not sound
+    pub unsound_view_change_policy: UnsoundViewChangePolicy,
+    /// How long we wait for the quorum to integrate us, when attempting to join it,
+    /// before giving up on the attempt
+    pub join_timeout: Duration,
+    /// Whether membership changes are validated to preserve the intersection
+    /// between the quorums of consecutive views
+    pub check_quorum_intersection: bool,
+}
+
+impl SynchronizerConfig {
+    pub fn with_view_change_history(mut self, view_change_history: usize) -> Self {
+        self.view_change_history = view_change_history;
+
+        self
+    }
+
+    pub fn with_max_view_look_ahead(mut self, max_view_look_ahead: usize) -> Self {
+        self.max_view_look_ahead = max_view_look_ahead;
+
+        self
+    }
+
+    pub fn with_quorum_alert_after(mut self, quorum_alert_after: Duration) -> Self {
+        self.quorum_alert_after = quorum_alert_after;
+
+        self
+    }
+
+    pub fn with_forward_batch_size(mut self, forward_batch_size: usize) -> Self {
+        self.forward_batch_size = forward_batch_size;
+
+        self
+    }
+
+    pub fn with_forward_interval(mut self, forward_interval: Duration) -> Self {
+        self.forward_interval = forward_interval;
+
+        self
+    }
+
+    pub fn with_stop_retransmit_interval(mut self, stop_retransmit_interval: Duration) -> Self {
+        self.stop_retransmit_interval = stop_retransmit_interval;
+
+        self
+    }
+
+    pub fn with_max_stop_retransmits(mut self, max_stop_retransmits: usize) -> Self {
+        self.max_stop_retransmits = max_stop_retransmits;
+
+        self
+    }
+
+    pub fn with_unsound_view_change_policy(mut self, unsound_view_change_policy: UnsoundViewChangePolicy) -> Self {
+        self.unsound_view_change_policy = unsound_view_change_policy;
+
+        self
+    }
+
+    pub fn with_join_timeout(mut self, join_timeout: Duration) -> Self {
+        self.join_timeout = join_timeout;
+
+        self
+    }
+
+    pub fn with_quorum_intersection_check(mut self, check_quorum_intersection: bool) -> Self {
+        self.check_quorum_intersection = check_quorum_intersection;
+
+        self
+    }
+}
+
+impl Default for SynchronizerConfig {
+    fn default() -> Self {
+        Self {
+            view_change_history: 16,
+            max_view_look_ahead: 32,
+            quorum_alert_after: Duration::from_secs(30),
+            forward_batch_size: 1024,
+            forward_interval: Duration::from_millis(100),
+            stop_retransmit_interval: Duration::from_secs(1),
+            max_stop_retransmits: 5,
+            unsound_view_change_policy: UnsoundViewChangePolicy::default(),
+            join_timeout: Duration::from_secs(10),
+            check_quorum_intersection: true,
+        }
+    }
+}
+
+/// What a replica does when the collects of a view change are not sound,
+/// meaning it can't safely determine the value to carry over to the new view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum UnsoundViewChangePolicy {
+    /// Log the failure and install the new view regardless.
+    /// This matches BFT-SMaRt, which does nothing when the view change is not sound
+    #[default]
+    Proceed,
+    /// Abort the view change, back off the view change timeout and
+    /// start changing to the view that follows
+    AbortAndRetry,
+}
+
+/// How the request timeout grows with the number of consecutive view changes
+/// that have failed, so that we don't keep timing out at the same rate the network is failing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum TimeoutGrowth {
+    /// The timeout never changes
+    Fixed,
+    /// The timeout grows by the base timeout with each failed view change
+    Linear,
+    /// The timeout doubles with each failed view change
+    Exponential,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ViewChangeTimeoutConfig {
+    pub growth: TimeoutGrowth,
+    /// The maximum the timeout can grow to
+    pub max_timeout: Duration,
+}
+
+impl ViewChangeTimeoutConfig {
+    pub fn new(growth: TimeoutGrowth, max_timeout: Duration) -> Self {
+        Self { growth, max_timeout }
+    }
+}
+
+impl Default for ViewChangeTimeoutConfig {
+    fn default() -> Self {
+        Self {
+            growth: TimeoutGrowth::Fixed,
+            max_timeout: Duration::from_secs(60),
+        }
     }
 }
@@ -848,6 +848,9 @@ pub struct ProposerConsensusGuard {
     /// We must store them due to the way the request pre processor
     /// sends requests to the proposer
     last_view_change: Mutex<Option<BTreeMap<NodeId, BTreeMap<SeqNo, SeqNo>>>>,
+    /// The requests which have been decided since the proposer last checked,
+    /// so it can stop tracking them as in flight
+    decided_requests: Mutex<Vec<ClientRqInfo>>,
 }
 
 impl ProposerConsensusGuard {
@@ -860,6 +863,7 @@ impl ProposerConsensusGuard {
             seq_no_queue: Mutex::new((BinaryHeap::with_capacity(watermark as usize), view)),
             has_pending_view_change_reqs: AtomicBool::new(false),
             last_view_change: Mutex::new(None),
+            decided_requests: Mutex::new(Vec::new()),
         })
     }
 
@@ -972,6 +976,16 @@ impl ProposerConsensusGuard {
         self.has_pending_view_change_reqs.store(false, Ordering::Relaxed);
     }
 
+    /// Register the requests of a batch that has been decided
+    pub(crate) fn batch_decided(&self, requests: &[ClientRqInfo]) {
+        self.decided_requests.lock().unwrap().extend_from_slice(requests);
+    }
+
+    /// Take the requests that have been decided since the last time this was called
+    pub fn take_decided_requests(&self) -> Vec<ClientRqInfo> {
+        std::mem::take(&mut *self.decided_requests.lock().unwrap())
+    }
+
     /// Clear all of the pending decisions waiting for a propose from this consensus guard
     fn clear(&self) {
         self.seq_no_queue.lock().unwrap().0.clear();
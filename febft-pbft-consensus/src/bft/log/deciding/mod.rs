@@ -252,20 +252,17 @@ impl<O> WorkingDecisionLog<O> where O: Clone {
     /// Calculate the instance of a completed consensus pre prepare phase with
     /// all the batches received
     fn calculate_instance_digest(&self) -> Option<(Digest, Vec<Digest>)> {
-        let mut ctx = Context::new();
-
         let mut batch_ordered_digests = Vec::with_capacity(self.pre_prepare_digests.len());
 
         for order_digest in &self.pre_prepare_digests {
             if let Some(digest) = order_digest.clone() {
-                ctx.update(digest.as_ref());
                 batch_ordered_digests.push(digest);
             } else {
                 return None;
             }
         }
 
-        Some((ctx.finish(), batch_ordered_digests))
+        Some((digest_of_ordering(&batch_ordered_digests), batch_ordered_digests))
     }
 
     /// Get the current decision
@@ -344,6 +341,18 @@ impl<O> WorkingDecisionLog<O> where O: Clone {
     }
 }
 
+/// Calculate the digest of an entire batch, given the ordered digests
+/// of the pre prepare messages that compose it
+pub(super) fn digest_of_ordering(pre_prepare_ordering: &[Digest]) -> Digest {
+    let mut ctx = Context::new();
+
+    for digest in pre_prepare_ordering {
+        ctx.update(digest.as_ref());
+    }
+
+    ctx.finish()
+}
+
 impl<O> Orderable for CompletedBatch<O> {
     fn sequence_number(&self) -> SeqNo {
         self.seq
@@ -368,6 +377,11 @@ impl<O> CompletedBatch<O> {
     pub fn request_count(&self) -> usize {
         self.client_requests.len()
     }
+
+    /// The information of the client requests contained in this batch
+    pub fn client_request_info(&self) -> &Vec<ClientRqInfo> {
+        &self.client_request_info
+    }
 }
 
 impl<O> Orderable for WorkingDecisionLog<O> {
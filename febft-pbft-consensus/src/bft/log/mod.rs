@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
+
 use either::Either;
+use intmap::IntMap;
 use thiserror::Error;
 use atlas_common::Err;
+use atlas_common::crypto::hash::Digest;
 
 use atlas_common::error::*;
 use atlas_common::node_id::NodeId;
@@ -12,7 +16,7 @@ use atlas_smr_application::app::UpdateBatch;
 use atlas_smr_application::serialize::ApplicationData;
 
 use crate::bft::log::decided::DecisionLog;
-use crate::bft::log::deciding::{CompletedBatch, FinishedMessageLog};
+use crate::bft::log::deciding::{CompletedBatch, digest_of_ordering, FinishedMessageLog};
 use crate::bft::log::decisions::{Proof, ProofMetadata};
 use crate::bft::message::ConsensusMessageKind;
 use crate::bft::OPDecision;
@@ -23,6 +27,9 @@ pub mod decisions;
 
 pub struct Log<D> where D: ApplicationData {
     decided: DecisionLog<D::Request>,
+    // The requests that have already been delivered to the executor,
+    // so we never deliver the same request twice (even across view changes)
+    executed: ExecutedRequests,
 }
 
 impl<D> Log<D> where D: ApplicationData {
@@ -55,9 +62,14 @@ impl<D> Log<D> where D: ApplicationData {
             }
         }
 
-        let batch_info = ProtocolConsensusDecision::from(&proof);
+        let executed = &mut self.executed;
+
         let sequence = proof.sequence_number();
 
+        let batch_info = decision_from_proof(&proof, |header, message| {
+            executed.should_execute(header.from(), message.session_id(), message.sequence_number(), sequence)
+        });
+
         let (metadata, messages) = proof.into_parts();
 
         Ok(Decision::full_decision_info(sequence, metadata, messages, batch_info))
@@ -73,15 +85,30 @@ impl<D> Log<D> where D: ApplicationData {
             batch_meta
         } = completed;
 
-        let metadata = ProofMetadata::new(seq, digest.clone(), pre_prepare_ordering,
-                                          client_requests.len());
-
         let FinishedMessageLog {
             pre_prepares,
             prepares,
             commits
         } = contained_messages;
 
+        // Make sure the batch we are about to deliver to the executor actually corresponds
+        // to the pre prepares sent by the proposers and to the digest the quorum committed to
+        let proposed: Vec<_> = pre_prepares.iter()
+            .map(|pre_prepare| pre_prepare.header().digest().clone())
+            .collect();
+
+        let committed: Vec<_> = commits.iter()
+            .filter_map(|commit| match commit.message().consensus().kind() {
+                ConsensusMessageKind::Commit(digest) => Some(digest.clone()),
+                _ => None,
+            })
+            .collect();
+
+        verify_batch_digest(seq, &digest, &pre_prepare_ordering, &proposed, &committed)?;
+
+        let metadata = ProofMetadata::new(seq, digest.clone(), pre_prepare_ordering,
+                                          client_requests.len());
+
         let proof = Proof::new(
             metadata,
             pre_prepares,
@@ -96,16 +123,167 @@ impl<D> Log<D> where D: ApplicationData {
         for cli_rq in client_requests {
             let (header, rq) = cli_rq.into_inner();
 
+            if !self.executed.should_execute(header.from(), rq.session_id(), rq.sequence_number(), seq) {
+                // This request has already been delivered to the executor in a previous decision
+                continue;
+            }
+
             batch.add(header.from(), rq.session_id(), rq.sequence_number(), rq.into_inner_operation());
         }
 
         Ok(ProtocolConsensusDecision::new(seq, batch, client_request_info, digest))
     }
+
+    /// A state covering every decision before `next_seq` was installed through the state
+    /// transfer protocol, so the requests we have executed no longer tell us exactly what
+    /// the installed state contains (it can be either ahead or behind of our own execution).
+    ///
+    /// The requests we executed in decisions covered by the installed state are kept as
+    /// executed, while the ones we executed in later decisions are forgotten, as those decisions
+    /// will be delivered again on top of the installed state. The requests of the last decision
+    /// in our decision log are also registered, when the installed state covers it.
+    /// Requests decided while we were behind, which we only know about through the installed
+    /// state, are not known to have been executed.
+    pub fn state_installed(&mut self, next_seq: SeqNo) {
+        self.executed.state_installed(next_seq);
+
+        if let Some(proof) = self.decided.last_decision() {
+            if proof.sequence_number() < next_seq {
+                self.executed.register_proof(&proof);
+            }
+        }
+    }
+}
+
+/// Verify that a batch matches what was decided: its pre prepare ordering must be the one
+/// sent by the proposers (the digests of the pre prepare messages, in order), its digest must
+/// be the digest of that ordering, and it must be the digest the quorum committed to.
+fn verify_batch_digest(seq: SeqNo, batch_digest: &Digest, pre_prepare_ordering: &[Digest],
+                       proposed: &[Digest], committed: &[Digest]) -> Result<()> {
+    if pre_prepare_ordering != proposed {
+        return Err!(LogError::BatchOrderingMismatch {
+            seq,
+            ordering: pre_prepare_ordering.to_vec(),
+            proposed: proposed.to_vec(),
+        });
+    }
+
+    let calculated = digest_of_ordering(proposed);
+
+    if calculated != *batch_digest {
+        return Err!(LogError::BatchDigestMismatch {
+            seq,
+            batch_digest: batch_digest.clone(),
+            calculated,
+        });
+    }
+
+    if committed.is_empty() || committed.iter().any(|digest| digest != batch_digest) {
+        return Err!(LogError::CommittedDigestMismatch {
+            seq,
+            batch_digest: batch_digest.clone(),
+            committed: committed.to_vec(),
+        });
+    }
+
+    Ok(())
 }
 
 pub fn initialize_decided_log<D>(node_id: NodeId) -> Log<D> where D: ApplicationData {
     Log {
         decided: DecisionLog::init(None),
+        executed: ExecutedRequests::new(),
+    }
+}
+
+/// How many of the latest executed requests of each client session we keep track of.
+/// Requests older than this window are considered to have been executed
+const EXECUTED_WINDOW: usize = 1024;
+
+/// Keeps track of the requests executed for each client session,
+/// in order to guarantee requests are only delivered to the executor once
+struct ExecutedRequests {
+    sessions: IntMap<SessionWindow>,
+}
+
+/// The requests of a client session that have been executed.
+/// Requests of a session are not necessarily decided in order (a view change can decide
+/// a request after a newer one), so we track each of the latest executed requests,
+/// instead of just the latest one
+#[derive(Default)]
+struct SessionWindow {
+    // Every request up to (and including) this one is considered executed
+    floor: Option<SeqNo>,
+    // The latest decision in which a request which has left the window was executed
+    floor_decision: Option<SeqNo>,
+    // The executed requests, along with the decision they were executed in
+    executed: BTreeMap<SeqNo, SeqNo>,
+}
+
+impl ExecutedRequests {
+    fn new() -> Self {
+        Self {
+            sessions: IntMap::new(),
+        }
+    }
+
+    /// Check whether a given request should be executed, registering it as executed
+    /// if so. Requests which have already been executed (or which are older than
+    /// the window of requests we keep track of) are rejected.
+    fn should_execute(&mut self, from: NodeId, session: SeqNo, rq_seq: SeqNo, decision: SeqNo) -> bool {
+        let key = operation_key_raw(from, session);
+
+        if !self.sessions.contains_key(key) {
+            self.sessions.insert(key, SessionWindow::default());
+        }
+
+        let window = self.sessions.get_mut(key).unwrap();
+
+        if window.floor.map_or(false, |floor| rq_seq <= floor) || window.executed.contains_key(&rq_seq) {
+            return false;
+        }
+
+        window.executed.insert(rq_seq, decision);
+
+        while window.executed.len() > EXECUTED_WINDOW {
+            if let Some((oldest, oldest_decision)) = window.executed.pop_first() {
+                window.floor = Some(oldest);
+                window.floor_decision = window.floor_decision.max(Some(oldest_decision));
+            }
+        }
+
+        true
+    }
+
+    /// A state covering every decision before `next_seq` was installed.
+    /// Forget the requests executed in the decisions that follow it, as they are going to be
+    /// executed again on top of the installed state
+    fn state_installed(&mut self, next_seq: SeqNo) {
+        for window in self.sessions.values_mut() {
+            window.executed.retain(|_, decision| *decision < next_seq);
+
+            // We can't tell which of the requests that left the window were executed in the
+            // following decisions, so we can no longer consider them all executed
+            if window.floor_decision.map_or(false, |decision| decision >= next_seq) {
+                window.floor = None;
+                window.floor_decision = None;
+            }
+        }
+    }
+
+    /// Register the requests contained in the given proof as executed
+    fn register_proof<O>(&mut self, proof: &Proof<O>) {
+        let decision = proof.sequence_number();
+
+        for pre_prepare in proof.pre_prepares() {
+            if let ConsensusMessageKind::PrePrepare(reqs) = pre_prepare.message().consensus().kind() {
+                for request in reqs {
+                    let message = request.message();
+
+                    self.should_execute(request.header().from(), message.session_id(), message.sequence_number(), decision);
+                }
+            }
+        }
     }
 }
 
@@ -126,41 +304,53 @@ pub fn operation_key_raw(from: NodeId, session: SeqNo) -> u64 {
 
 impl<O> From<&Proof<O>> for ProtocolConsensusDecision<O> where O: Clone {
     fn from(value: &Proof<O>) -> Self {
-        let mut update_batch = UpdateBatch::new_with_cap(value.seq_no(), value.metadata().contained_client_rqs());
-        let mut client_rqs = Vec::with_capacity(value.metadata().contained_client_rqs());
-
-        if !value.are_pre_prepares_ordered().unwrap() {
-            //The batch should be provided to this already ordered.
-            todo!()
-        }
+        decision_from_proof(value, |_, _| true)
+    }
+}
 
-        for pre_prepare in value.pre_prepares() {
-            let consensus_msg = (*pre_prepare.message()).clone();
+/// Build the decision contained in a given proof, only delivering the requests
+/// accepted by `should_execute` to the executor
+fn decision_from_proof<O, F>(value: &Proof<O>, mut should_execute: F) -> ProtocolConsensusDecision<O>
+    where O: Clone,
+          F: FnMut(&Header, &RequestMessage<O>) -> bool {
+    let mut update_batch = UpdateBatch::new_with_cap(value.seq_no(), value.metadata().contained_client_rqs());
+    let mut client_rqs = Vec::with_capacity(value.metadata().contained_client_rqs());
+
+    if !value.are_pre_prepares_ordered().unwrap() {
+        //The batch should be provided to this already ordered.
+        todo!()
+    }
 
-            let reqs = match consensus_msg.into_consensus().into_kind() {
-                ConsensusMessageKind::PrePrepare(reqs) => { reqs }
-                _ => {
-                    unreachable!()
-                }
-            };
+    for pre_prepare in value.pre_prepares() {
+        let consensus_msg = (*pre_prepare.message()).clone();
 
-            for request in reqs {
-                client_rqs.push(ClientRqInfo::from(&request));
+        let reqs = match consensus_msg.into_consensus().into_kind() {
+            ConsensusMessageKind::PrePrepare(reqs) => { reqs }
+            _ => {
+                unreachable!()
+            }
+        };
 
-                let (header, message) = request.into_inner();
+        for request in reqs {
+            client_rqs.push(ClientRqInfo::from(&request));
 
-                update_batch.add(header.from(),
-                                 message.session_id(),
-                                 message.sequence_number(),
-                                 message.into_inner_operation());
+            if !should_execute(request.header(), request.message()) {
+                continue;
             }
-        }
 
-        ProtocolConsensusDecision::new(value.seq_no(),
-                                       update_batch,
-                                       client_rqs,
-                                       value.metadata().batch_digest())
+            let (header, message) = request.into_inner();
+
+            update_batch.add(header.from(),
+                             message.session_id(),
+                             message.sequence_number(),
+                             message.into_inner_operation());
+        }
     }
+
+    ProtocolConsensusDecision::new(value.seq_no(),
+                                   update_batch,
+                                   client_rqs,
+                                   value.metadata().batch_digest())
 }
 
 #[derive(Error, Debug)]
@@ -175,4 +365,173 @@ pub enum LogError {
         install_attempt: SeqNo,
         currently_installed: SeqNo,
     },
-}
\ No newline at end of file
+    #[error("The digest of batch {seq:?} ({batch_digest:?}) does not match the digest of its contents ({calculated:?})")]
+    BatchDigestMismatch {
+        seq: SeqNo,
+        batch_digest: Digest,
+        calculated: Digest,
+    },
+    #[error("The pre prepare ordering of batch {seq:?} ({ordering:?}) does not match the pre prepares sent by the proposers ({proposed:?})")]
+    BatchOrderingMismatch {
+        seq: SeqNo,
+        ordering: Vec<Digest>,
+        proposed: Vec<Digest>,
+    },
+    #[error("The digest of batch {seq:?} ({batch_digest:?}) does not match the digests committed by the quorum ({committed:?})")]
+    CommittedDigestMismatch {
+        seq: SeqNo,
+        batch_digest: Digest,
+        committed: Vec<Digest>,
+    },
+}
+
+#[cfg(test)]
+mod log_tests {
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use super::{EXECUTED_WINDOW, ExecutedRequests, operation_key_raw, verify_batch_digest};
+    use crate::bft::log::deciding::digest_of_ordering;
+
+    #[test]
+    fn test_requests_are_only_executed_once() {
+        let mut executed = ExecutedRequests::new();
+
+        let (client, other_client) = (NodeId(1000), NodeId(1001));
+        let session = SeqNo::ZERO;
+        let rq_seq = SeqNo::ZERO.next();
+        let decision = SeqNo::ZERO;
+
+        // Executed right before the view change
+        assert!(executed.should_execute(client, session, rq_seq, decision));
+
+        // The new view re-proposes the same request, which must not be executed again
+        assert!(!executed.should_execute(client, session, rq_seq, decision.next()));
+
+        // Other requests are not affected
+        assert!(executed.should_execute(client, session, rq_seq.next(), decision.next()));
+        assert!(executed.should_execute(other_client, session, rq_seq, decision.next()));
+        assert!(executed.should_execute(client, session.next(), rq_seq, decision.next()));
+    }
+
+    #[test]
+    fn test_view_change_executes_requests_decided_out_of_order() {
+        let mut executed = ExecutedRequests::new();
+
+        let client = NodeId(1000);
+        let session = SeqNo::ZERO;
+
+        let rq = |seq: u32| SeqNo::from(seq);
+
+        // View 0 decides requests 0 and 2 of the client, while request 1
+        // is stuck in a batch of a leader that has failed
+        for decided in [rq(0), rq(2)] {
+            assert!(executed.should_execute(client, session, decided, SeqNo::ZERO));
+        }
+
+        // The view change forges a pre prepare with the requests collected from the
+        // STOP messages, which contains both the stuck request and an already executed one
+        let forged_batch = [rq(1), rq(2)];
+
+        let delivered: Vec<_> = forged_batch.iter()
+            .filter(|seq| executed.should_execute(client, session, **seq, SeqNo::ZERO.next()))
+            .cloned()
+            .collect();
+
+        // The stuck request is executed even though it is older than the latest executed one
+        assert_eq!(delivered, vec![rq(1)]);
+
+        // Once a request leaves the window we keep track of, it is considered executed
+        for seq in 3..(3 + EXECUTED_WINDOW as u32) {
+            assert!(executed.should_execute(client, session, rq(seq), SeqNo::ZERO.next()));
+        }
+
+        assert!(!executed.should_execute(client, session, rq(1), SeqNo::ZERO.next()));
+        assert!(executed.sessions.get(operation_key_raw(client, session)).unwrap().executed.len() <= EXECUTED_WINDOW);
+    }
+
+    #[test]
+    fn test_installed_state_keeps_the_requests_it_covers() {
+        let mut executed = ExecutedRequests::new();
+
+        let client = NodeId(1000);
+        let session = SeqNo::ZERO;
+
+        let rq = |seq: u32| SeqNo::from(seq);
+        let decision = |seq: u32| SeqNo::from(seq);
+
+        // Requests 0 and 1 are executed in decisions 0 and 1, and request 2 in decision 3
+        assert!(executed.should_execute(client, session, rq(0), decision(0)));
+        assert!(executed.should_execute(client, session, rq(1), decision(1)));
+        assert!(executed.should_execute(client, session, rq(2), decision(3)));
+
+        // We install a state covering decisions 0 to 2
+        executed.state_installed(decision(3));
+
+        // The requests executed in the decisions it covers are not executed again
+        assert!(!executed.should_execute(client, session, rq(0), decision(3)));
+        assert!(!executed.should_execute(client, session, rq(1), decision(3)));
+
+        // But decision 3 is delivered again on top of the installed state, so its request is executed
+        assert!(executed.should_execute(client, session, rq(2), decision(3)));
+        assert!(!executed.should_execute(client, session, rq(2), decision(4)));
+
+        // Requests that left the window in decisions covered by the state are still considered executed
+        let mut executed = ExecutedRequests::new();
+
+        for seq in 0..=(EXECUTED_WINDOW as u32) {
+            assert!(executed.should_execute(client, session, rq(seq), decision(0)));
+        }
+
+        executed.state_installed(decision(1));
+
+        assert!(!executed.should_execute(client, session, rq(0), decision(1)));
+
+        // When some of them were executed in the decisions that follow the state, we no longer
+        // know which of them were, so none of them is considered executed (not even the ones
+        // executed in decisions covered by the state)
+        let mut executed = ExecutedRequests::new();
+
+        for seq in 0..=(EXECUTED_WINDOW as u32 + 1) {
+            assert!(executed.should_execute(client, session, rq(seq), decision(seq)));
+        }
+
+        executed.state_installed(decision(1));
+
+        assert!(executed.should_execute(client, session, rq(1), decision(1)));
+        assert!(executed.should_execute(client, session, rq(0), decision(1)));
+    }
+
+    #[test]
+    fn test_mismatched_batch_digest_is_rejected() {
+        let ordering = vec![Digest::from_bytes(&[1; Digest::LENGTH]).unwrap(),
+                            Digest::from_bytes(&[2; Digest::LENGTH]).unwrap()];
+
+        let batch_digest = digest_of_ordering(&ordering);
+        let committed = vec![batch_digest.clone(); 3];
+
+        assert!(verify_batch_digest(SeqNo::ZERO, &batch_digest, &ordering, &ordering, &committed).is_ok());
+
+        let forged_digest = Digest::from_bytes(&[3; Digest::LENGTH]).unwrap();
+
+        // The batch digest does not match the pre prepares it is made of
+        assert!(verify_batch_digest(SeqNo::ZERO, &forged_digest, &ordering, &ordering, &committed).is_err());
+
+        // The ordering of the batch differs from the pre prepares sent by the proposers
+        let swapped: Vec<_> = ordering.iter().rev().cloned().collect();
+
+        assert!(verify_batch_digest(SeqNo::ZERO, &batch_digest, &swapped, &ordering, &committed).is_err());
+
+        // A proposer sent a different pre prepare than the one in the batch
+        let proposed = vec![ordering[0].clone(), forged_digest.clone()];
+
+        assert!(verify_batch_digest(SeqNo::ZERO, &batch_digest, &ordering, &proposed, &committed).is_err());
+
+        // The quorum committed to a different batch than the one we are about to execute
+        let diverging_commits = vec![batch_digest.clone(), batch_digest.clone(), forged_digest];
+
+        assert!(verify_batch_digest(SeqNo::ZERO, &batch_digest, &ordering, &ordering, &diverging_commits).is_err());
+        assert!(verify_batch_digest(SeqNo::ZERO, &batch_digest, &ordering, &ordering, &[]).is_err());
+    }
+}
@@ -32,6 +32,10 @@ pub const PROPOSER_PROPOSE_TIME_ID: usize = 107;
 
 pub const PROPOSER_REQUEST_TIME_ITERATIONS: &str = "PROPOSER_REQUEST_TIME_ITERATIONS";
 pub const PROPOSER_REQUEST_TIME_ITERATIONS_ID: usize = 108;
+
+/// How long a batch took to assemble, from its first request until it was proposed
+pub const PROPOSER_BATCH_ASSEMBLY_LATENCY: &str = "PROPOSER_BATCH_ASSEMBLY_LATENCY";
+pub const PROPOSER_BATCH_ASSEMBLY_LATENCY_ID: usize = 109;
 /// 110-119: Consensus
 
 pub const PROPOSE_LATENCY: &str = "PROPOSE_LATENCY";
@@ -83,6 +87,22 @@ pub const SYNC_FORWARDED_REQUESTS_ID: usize = 124;
 pub const SYNC_FORWARDED_COUNT : &str = "SYNC_FORWARDED_COUNT";
 pub const SYNC_FORWARDED_COUNT_ID: usize = 125;
 
+pub const SYNC_FUTURE_VIEW_MSGS_DROPPED : &str = "SYNC_FUTURE_VIEW_MSGS_DROPPED";
+pub const SYNC_FUTURE_VIEW_MSGS_DROPPED_ID: usize = 126;
+
+pub const NON_MEMBER_MSGS_DROPPED : &str = "NON_MEMBER_MSGS_DROPPED";
+pub const NON_MEMBER_MSGS_DROPPED_ID: usize = 127;
+
+pub const SYNC_EXCESS_VIEW_MSGS_DROPPED : &str = "SYNC_EXCESS_VIEW_MSGS_DROPPED";
+pub const SYNC_EXCESS_VIEW_MSGS_DROPPED_ID: usize = 128;
+
+/// 130-139: Proposer (continued)
+pub const PROPOSER_PROPOSED_BATCH_SIZE: &str = "PROPOSER_PROPOSED_BATCH_SIZE";
+pub const PROPOSER_PROPOSED_BATCH_SIZE_ID: usize = 130;
+
+pub const PROPOSER_TARGET_BATCH_SIZE: &str = "PROPOSER_TARGET_BATCH_SIZE";
+pub const PROPOSER_TARGET_BATCH_SIZE_ID: usize = 131;
+
 pub fn metrics() -> Vec<MetricRegistry> {
     
     vec![
@@ -93,6 +113,9 @@ pub fn metrics() -> Vec<MetricRegistry> {
         (PROPOSER_FWD_REQUESTS_ID, PROPOSER_FWD_REQUESTS.to_string(), MetricKind::Duration).into(),
         (PROPOSER_PROPOSE_TIME_ID, PROPOSER_PROPOSE_TIME.to_string(), MetricKind::Duration).into(),
         (PROPOSER_REQUEST_TIME_ITERATIONS_ID, PROPOSER_REQUEST_TIME_ITERATIONS.to_string(), MetricKind::Counter).into(),
+        (PROPOSER_BATCH_ASSEMBLY_LATENCY_ID, PROPOSER_BATCH_ASSEMBLY_LATENCY.to_string(), MetricKind::Duration).into(),
+        (PROPOSER_PROPOSED_BATCH_SIZE_ID, PROPOSER_PROPOSED_BATCH_SIZE.to_string(), MetricKind::Count).into(),
+        (PROPOSER_TARGET_BATCH_SIZE_ID, PROPOSER_TARGET_BATCH_SIZE.to_string(), MetricKind::Count).into(),
         (CLIENT_POOL_BATCH_SIZE_ID, CLIENT_POOL_BATCH_SIZE.to_string(), MetricKind::Count).into(),
         (CONSENSUS_PRE_PREPARE_LATENCY_ID, CONSENSUS_PRE_PREPARE_LATENCY.to_string(), MetricKind::Duration).into(),
         (PROPOSER_LATENCY_ID, PROPOSER_LATENCY.to_string(), MetricKind::Duration).into(),
@@ -112,6 +135,9 @@ pub fn metrics() -> Vec<MetricRegistry> {
         (SYNC_STOPPED_COUNT_ID, SYNC_STOPPED_COUNT.to_string(), MetricKind::Counter).into(),
         (SYNC_FORWARDED_REQUESTS_ID, SYNC_FORWARDED_REQUESTS.to_string(), MetricKind::Duration).into(),
         (SYNC_FORWARDED_COUNT_ID, SYNC_FORWARDED_COUNT.to_string(), MetricKind::Counter).into(),
+        (SYNC_FUTURE_VIEW_MSGS_DROPPED_ID, SYNC_FUTURE_VIEW_MSGS_DROPPED.to_string(), MetricKind::Counter).into(),
+        (NON_MEMBER_MSGS_DROPPED_ID, NON_MEMBER_MSGS_DROPPED.to_string(), MetricKind::Counter).into(),
+        (SYNC_EXCESS_VIEW_MSGS_DROPPED_ID, SYNC_EXCESS_VIEW_MSGS_DROPPED.to_string(), MetricKind::Counter).into(),
     ]
     
 }
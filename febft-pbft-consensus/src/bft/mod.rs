@@ -28,6 +28,7 @@ use atlas_core::request_pre_processing::RequestPreProcessor;
 use atlas_core::serialize::ReconfigurationProtocolMessage;
 use atlas_core::smr::smr_decision_log::{ShareableConsensusMessage, ShareableMessage};
 use atlas_core::timeouts::{RqTimeout, Timeouts};
+use atlas_metrics::metrics::metric_increment;
 use atlas_smr_application::ExecutorHandle;
 use atlas_smr_application::serialize::ApplicationData;
 
@@ -38,6 +39,8 @@ use crate::bft::log::decided::DecisionLog;
 use crate::bft::log::decisions::{Proof, ProofMetadata};
 use crate::bft::message::{ConsensusMessageKind, ObserveEventKind, PBFTMessage};
 use crate::bft::message::serialize::PBFTConsensus;
+use crate::bft::metric::NON_MEMBER_MSGS_DROPPED_ID;
+use crate::bft::proposer::backpressure::ExecutionBacklog;
 use crate::bft::proposer::Proposer;
 use crate::bft::sync::{AbstractSynchronizer, Synchronizer, SynchronizerPollStatus, SynchronizerStatus, SyncReconfigurationResult};
 use crate::bft::sync::view::ViewInfo;
@@ -104,6 +107,8 @@ pub struct PBFTOrderProtocol<D, NT, >
     message_log: Log<D>,
     // The proposer of this replica
     proposer: Arc<Proposer<D, NT>>,
+    // The decided batches still waiting for execution, shared with the proposer
+    execution_backlog: Option<Arc<ExecutionBacklog>>,
     // The networking layer for a Node in the network (either Client or Replica)
     node: Arc<NT>,
     // The handle to the executor, currently not utilized
@@ -150,6 +155,10 @@ impl<D, NT> OrderingProtocol<D, NT> for PBFTOrderProtocol<D, NT>
 
 
     fn handle_off_ctx_message(&mut self, message: ShareableMessage<PBFTMessage<D::Request>>) {
+        if !self.is_sender_accepted(&message) {
+            return;
+        }
+
         match message.message() {
             PBFTMessage::Consensus(consensus) => {
                 debug!("{:?} // Received off context consensus message {:?}", self.node.id(), consensus);
@@ -185,6 +194,8 @@ impl<D, NT> OrderingProtocol<D, NT> for PBFTOrderProtocol<D, NT>
     fn poll(&mut self) -> Result<OPPollResult<ProofMetadata, PBFTMessage<D::Request>, D::Request>> {
         trace!("{:?} // Polling {:?}", self.node.id(), self.phase);
 
+        self.synchronizer.watch_quorum_connectivity();
+
         match self.phase {
             ConsensusPhase::NormalPhase => {
                 self.poll_normal_phase()
@@ -196,6 +207,10 @@ impl<D, NT> OrderingProtocol<D, NT> for PBFTOrderProtocol<D, NT>
     }
 
     fn process_message(&mut self, message: ShareableMessage<PBFTMessage<D::Request>>) -> Result<OPExecResult<ProofMetadata, PBFTMessage<D::Request>, D::Request>> {
+        if !self.is_sender_accepted(&message) {
+            return Ok(OPExecResult::MessageDropped);
+        }
+
         match self.phase {
             ConsensusPhase::NormalPhase => {
                 self.update_normal_phase(message)
@@ -209,17 +224,30 @@ impl<D, NT> OrderingProtocol<D, NT> for PBFTOrderProtocol<D, NT>
     fn install_seq_no(&mut self, seq_no: SeqNo) -> Result<()> {
         self.consensus.install_sequence_number(seq_no, &self.synchronizer.view());
 
+        self.message_log.state_installed(seq_no);
+
+        if let Some(backlog) = &self.execution_backlog {
+            // The execution layer has installed a state covering every batch decided so far
+            backlog.execution_caught_up();
+        }
+
         Ok(())
     }
 
     fn handle_timeout(&mut self, timeout: Vec<RqTimeout>) -> Result<OPExecResult<ProofMetadata, PBFTMessage<D::Request>, D::Request>> {
+        if self.synchronizer.expire_join_attempt() {
+            // We were not integrated into the quorum in time, so we are back to our previous view.
+            // The failure is reported when the reconfiguration protocol next checks on our attempt
+            self.switch_phase(ConsensusPhase::NormalPhase);
+        }
+
         if self.consensus.is_catching_up() {
             warn!("{:?} // Ignoring timeouts while catching up", self.node.id());
 
             return Ok(OPExecResult::MessageDropped);
         }
 
-        let status = self.synchronizer.client_requests_timed_out(self.node.id(), &timeout);
+        let status = self.synchronizer.client_requests_timed_out(&*self.node, &self.timeouts, &timeout);
 
         match status {
             SynchronizerStatus::RequestsTimedOut { forwarded, stopped } => {
@@ -286,14 +314,15 @@ impl<D, NT> PBFTOrderProtocol<D, NT>
                            initial_state: Option<DecisionLog<D::Request>>) -> Result<Self> {
         let PBFTConfig {
             timeout_dur,
-            proposer_config, watermark
+            proposer_config, watermark,
+            sync_config, view_change_timeout
         } = config;
 
         let OrderingProtocolArgs(node_id, executor, timeouts,
                                  pre_processor, batch_input,
                                  node, quorum) = args;
 
-        let sync = Synchronizer::initialize_with_quorum(node_id, SeqNo::ZERO, quorum.clone(), timeout_dur)?;
+        let sync = Synchronizer::initialize_with_quorum(node_id, SeqNo::ZERO, quorum.clone(), timeout_dur, view_change_timeout, sync_config)?;
 
         let consensus_guard = ProposerConsensusGuard::new(sync.view(), watermark);
 
@@ -307,6 +336,8 @@ impl<D, NT> PBFTOrderProtocol<D, NT>
                                               executor.clone(), consensus_guard.clone(),
                                               proposer_config);
 
+        let execution_backlog = proposer.execution_backlog().cloned();
+
         let replica = Self {
             phase: ConsensusPhase::NormalPhase,
             consensus,
@@ -318,6 +349,7 @@ impl<D, NT> PBFTOrderProtocol<D, NT>
             executor,
             message_log: dec_log,
             proposer,
+            execution_backlog,
             node,
         };
 
@@ -341,7 +373,6 @@ impl<D, NT> PBFTOrderProtocol<D, NT>
     }
 
     fn poll_sync_phase(&mut self) -> Result<OPPollResult<ProofMetadata, PBFTMessage<D::Request>, D::Request>> {
-
         // retrieve a view change message to be processed
         let poll_result = self.synchronizer.poll();
 
@@ -443,6 +474,9 @@ impl<D, NT> PBFTOrderProtocol<D, NT>
             }
         }
 
+        // forward any timed out requests that were held back by the forwarding rate limit
+        self.synchronizer.flush_forwarded_requests(&*self.node);
+
         // retrieve the next message to be processed.
         //
         // the order of the next consensus message is guaranteed by
@@ -460,6 +494,27 @@ impl<D, NT> PBFTOrderProtocol<D, NT>
         })
     }
 
+    /// Check whether we should process a message, according to the membership of its sender.
+    /// Messages from nodes that are not members of the current view (or of the view we are changing to)
+    /// are dropped, except for the messages which are part of the reconfiguration of the quorum
+    /// and the messages of nodes we know are joining the quorum for the views that will include them.
+    fn is_sender_accepted(&self, message: &ShareableMessage<PBFTMessage<D::Request>>) -> bool {
+        let from = message.header().from();
+
+        let membership = self.synchronizer.sender_membership(from, message.message());
+
+        if !membership.is_accepted() {
+            // A non member can send us any amount of messages, so we don't log each one at warn level.
+            // The metric below is what should be monitored
+            debug!("{:?} // Dropping message from {:?} as it is not a member of the current view",
+                self.node.id(), from);
+
+            metric_increment(NON_MEMBER_MSGS_DROPPED_ID, Some(1));
+        }
+
+        membership.is_accepted()
+    }
+
     fn update_sync_phase(&mut self, message: ShareableMessage<PBFTMessage<D::Request>>) -> Result<OPExecResult<ProofMetadata, PBFTMessage<D::Request>, D::Request>> {
         match message.message() {
             PBFTMessage::ViewChange(view_change) => {
@@ -590,12 +645,19 @@ impl<D, NT> PBFTOrderProtocol<D, NT>
             // This will automatically move the consensus machine to the next consensus instance
             let completed_batch = self.consensus.finalize(&view)?.unwrap();
 
+            // The decided requests are no longer in flight, so the proposer can stop tracking them
+            self.consensus_guard.batch_decided(completed_batch.client_request_info());
+
             //Should the execution be scheduled here or will it be scheduled by the persistent log?
             let exec_info = self.message_log.finalize_batch(completed_batch)?;
 
             finalized_decisions.push(exec_info);
         }
 
+        if let Some(backlog) = &self.execution_backlog {
+            backlog.batches_decided(finalized_decisions.len());
+        }
+
         Ok(finalized_decisions)
     }
 
@@ -904,7 +966,10 @@ impl<D, NT, RP> ReconfigurableOrderProtocol<RP> for PBFTOrderProtocol<D, NT>
 
         match &result {
             ReconfigurationAttemptResult::Failed => {
-                warn!("Failed to join quorum")
+                warn!("Failed to join quorum");
+
+                // Our attempt might have expired, in which case we are back to our previous view
+                self.switch_phase(ConsensusPhase::NormalPhase);
             }
             ReconfigurationAttemptResult::AlreadyPartOfQuorum => {}
             ReconfigurationAttemptResult::InProgress => {
@@ -0,0 +1,278 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use event_listener::Event;
+use log::debug;
+
+/// Keeps track of the batches that have been decided but not yet executed,
+/// so the proposer can stop proposing when the execution layer falls behind.
+///
+/// The proposer is paused once the backlog reaches the high watermark and is only
+/// resumed once it drains down to the low watermark, so it does not flap around a
+/// single threshold under sustained overload.
+///
+/// The backlog is a counter shared between the ordering protocol and the execution layer:
+/// it is handed to the proposer through [crate::bft::config::ProposerConfig::with_execution_backlog],
+/// the ordering protocol registers the batches it decides and the executor reports the
+/// batches it has executed with [ExecutionBacklog::batches_executed].
+#[derive(Debug)]
+pub struct ExecutionBacklog {
+    high_watermark: usize,
+    low_watermark: usize,
+    // The amount of decided batches which have not yet been executed
+    pending: AtomicUsize,
+    paused: AtomicBool,
+    // Wakes up the proposer once the backlog has drained
+    drained: Event,
+}
+
+impl ExecutionBacklog {
+    pub fn new(high_watermark: usize) -> Self {
+        let high_watermark = high_watermark.max(1);
+
+        Self {
+            high_watermark,
+            low_watermark: high_watermark / 2,
+            pending: AtomicUsize::new(0),
+            paused: AtomicBool::new(false),
+            drained: Event::new(),
+        }
+    }
+
+    /// Register that a batch has been decided and will be delivered to the execution layer
+    pub fn batches_decided(&self, count: usize) {
+        let pending = self.pending.fetch_add(count, Ordering::Relaxed) + count;
+
+        if should_pause(self.paused.load(Ordering::Relaxed), pending, self.high_watermark, self.low_watermark) {
+            self.paused.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Register that the execution layer has executed the given amount of batches
+    pub fn batches_executed(&self, count: usize) {
+        let pending = self.pending.fetch_update(Ordering::Relaxed, Ordering::Relaxed,
+                                                |pending| Some(pending.saturating_sub(count)))
+            .unwrap_or(0)
+            .saturating_sub(count);
+
+        if self.paused.load(Ordering::Relaxed)
+            && !should_pause(true, pending, self.high_watermark, self.low_watermark) {
+            debug!("Execution backlog drained to {} batches, resuming the proposer", pending);
+
+            self.resume();
+        }
+    }
+
+    /// Register that the execution layer has caught up with every decided batch,
+    /// for example because it has installed a state received from other replicas
+    pub fn execution_caught_up(&self) {
+        self.pending.store(0, Ordering::Relaxed);
+
+        if self.paused.load(Ordering::Relaxed) {
+            debug!("Execution caught up with every decided batch, resuming the proposer");
+
+            self.resume();
+        }
+    }
+
+    fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+
+        self.drained.notify(usize::MAX);
+    }
+
+    /// Wake up any thread waiting for the backlog to drain, so it can check whether it was cancelled
+    pub fn wake(&self) {
+        self.drained.notify(usize::MAX);
+    }
+
+    /// The amount of decided batches which have not yet been executed
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    /// Whether the proposer should hold off on proposing new batches
+    pub fn is_overloaded(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Block the proposer while the backlog is overloaded.
+    /// Returns false if the proposer was cancelled instead, in which case it must not propose
+    pub fn wait_until_proposable(&self, cancelled: &AtomicBool) -> bool {
+        if self.is_overloaded() {
+            self.block_until_drained(cancelled);
+        }
+
+        !cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Block until the backlog has drained, if it is overloaded, or until `cancelled` is set.
+    /// Cancellation is also checked every [DRAIN_CHECK_INTERVAL], in case the wake up is missed
+    pub fn block_until_drained(&self, cancelled: &AtomicBool) {
+        while !cancelled.load(Ordering::Relaxed) {
+            let listener = self.drained.listen();
+
+            // Check again after we have started listening, so we don't miss the notification
+            if !self.is_overloaded() || cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+
+            listener.wait_timeout(DRAIN_CHECK_INTERVAL);
+        }
+    }
+}
+
+/// How often a proposer waiting for the backlog to drain checks whether it has been cancelled
+const DRAIN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether the proposer should be paused with `pending` batches waiting for execution,
+/// given whether it is `paused` at the moment
+fn should_pause(paused: bool, pending: usize, high_watermark: usize, low_watermark: usize) -> bool {
+    if paused {
+        pending > low_watermark
+    } else {
+        pending >= high_watermark
+    }
+}
+
+#[cfg(test)]
+mod backpressure_tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::ExecutionBacklog;
+
+    /// A backlog whose execution layer has fallen behind
+    fn overloaded_backlog(high_watermark: usize) -> Arc<ExecutionBacklog> {
+        let backlog = Arc::new(ExecutionBacklog::new(high_watermark));
+
+        backlog.batches_decided(high_watermark);
+
+        assert!(backlog.is_overloaded());
+
+        backlog
+    }
+
+    #[test]
+    fn test_proposer_pauses_until_backlog_drains() {
+        let backlog = ExecutionBacklog::new(8);
+
+        backlog.batches_decided(7);
+
+        assert!(!backlog.is_overloaded());
+
+        // The execution falls behind, so we stop proposing
+        backlog.batches_decided(1);
+
+        assert!(backlog.is_overloaded());
+
+        // A little progress is not enough to resume
+        backlog.batches_executed(3);
+
+        assert_eq!(backlog.pending(), 5);
+        assert!(backlog.is_overloaded());
+
+        // Only once the backlog drains to the low watermark
+        backlog.batches_executed(1);
+
+        assert!(!backlog.is_overloaded());
+
+        // Reports of more executions than decisions do not underflow
+        backlog.batches_executed(100);
+
+        assert_eq!(backlog.pending(), 0);
+    }
+
+    #[test]
+    fn test_proposer_stops_until_executor_drains_backlog() {
+        const HIGH_WATERMARK: usize = 8;
+        const BATCHES: usize = 32;
+
+        let backlog = Arc::new(ExecutionBacklog::new(HIGH_WATERMARK));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let proposed = Arc::new(AtomicUsize::new(0));
+
+        let (decided_tx, decided_rx) = mpsc::channel();
+
+        // A proposer whose batches are decided as soon as they are proposed
+        let proposer = {
+            let backlog = backlog.clone();
+            let cancelled = cancelled.clone();
+            let proposed = proposed.clone();
+
+            thread::spawn(move || {
+                for batch in 0..BATCHES {
+                    if !backlog.wait_until_proposable(&cancelled) {
+                        return;
+                    }
+
+                    proposed.fetch_add(1, Ordering::Relaxed);
+
+                    backlog.batches_decided(1);
+                    decided_tx.send(batch).unwrap();
+                }
+            })
+        };
+
+        while !backlog.is_overloaded() {
+            thread::yield_now();
+        }
+
+        // The executor has not executed anything yet, so the proposer stops at the high watermark
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(proposed.load(Ordering::Relaxed), HIGH_WATERMARK);
+        assert_eq!(backlog.pending(), HIGH_WATERMARK);
+
+        // Once the executor starts draining the backlog, the proposer resumes
+        let executor = {
+            let backlog = backlog.clone();
+
+            thread::spawn(move || {
+                for _batch in decided_rx.iter() {
+                    backlog.batches_executed(1);
+                }
+            })
+        };
+
+        proposer.join().unwrap();
+        executor.join().unwrap();
+
+        assert_eq!(proposed.load(Ordering::Relaxed), BATCHES);
+        assert_eq!(backlog.pending(), 0);
+        assert!(!backlog.is_overloaded());
+    }
+
+    #[test]
+    fn test_blocked_proposer_resumes_once_caught_up() {
+        let backlog = overloaded_backlog(8);
+
+        backlog.execution_caught_up();
+
+        assert_eq!(backlog.pending(), 0);
+        assert!(!backlog.is_overloaded());
+        assert!(backlog.wait_until_proposable(&AtomicBool::new(false)));
+    }
+
+    #[test]
+    fn test_cancelled_proposer_stops_waiting() {
+        let backlog = overloaded_backlog(8);
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let waiter = {
+            let backlog = backlog.clone();
+            let cancelled = cancelled.clone();
+
+            thread::spawn(move || backlog.wait_until_proposable(&cancelled))
+        };
+
+        cancelled.store(true, Ordering::Relaxed);
+        backlog.wake();
+
+        assert!(!waiter.join().unwrap());
+        assert!(backlog.is_overloaded());
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::sync::{Arc, MutexGuard};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::JoinHandle;
@@ -7,6 +7,7 @@ use std::time::{Duration, Instant};
 use log::{debug, error, info, warn};
 
 use atlas_common::channel::TryRecvError;
+use atlas_common::crypto::hash::Digest;
 use atlas_common::node_id::NodeId;
 use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_common::threadpool;
@@ -19,16 +20,18 @@ use atlas_smr_application::ExecutorHandle;
 use atlas_smr_application::serialize::ApplicationData;
 use atlas_metrics::metrics::{metric_duration, metric_increment, metric_store_count};
 
-use crate::bft::config::ProposerConfig;
+use crate::bft::config::{AdaptiveBatchConfig, ProposerConfig};
 use crate::bft::consensus::ProposerConsensusGuard;
+use crate::bft::proposer::backpressure::ExecutionBacklog;
 use crate::bft::message::{ConsensusMessage, ConsensusMessageKind, PBFTMessage};
-use crate::bft::metric::{CLIENT_POOL_BATCH_SIZE_ID, PROPOSER_BATCHES_MADE_ID, PROPOSER_LATENCY_ID, PROPOSER_PROPOSE_TIME_ID, PROPOSER_REQUEST_PROCESSING_TIME_ID, PROPOSER_REQUEST_TIME_ITERATIONS_ID, PROPOSER_REQUESTS_COLLECTED_ID};
+use crate::bft::metric::{CLIENT_POOL_BATCH_SIZE_ID, PROPOSER_BATCH_ASSEMBLY_LATENCY_ID, PROPOSER_BATCHES_MADE_ID, PROPOSER_LATENCY_ID, PROPOSER_PROPOSED_BATCH_SIZE_ID, PROPOSER_PROPOSE_TIME_ID, PROPOSER_REQUEST_PROCESSING_TIME_ID, PROPOSER_REQUEST_TIME_ITERATIONS_ID, PROPOSER_REQUESTS_COLLECTED_ID, PROPOSER_TARGET_BATCH_SIZE_ID};
 use crate::bft::PBFT;
 use crate::bft::sync::view::{is_request_in_hash_space, ViewInfo};
 
 use super::sync::{AbstractSynchronizer, Synchronizer};
 
 //pub mod follower_proposer;
+pub mod backpressure;
 
 pub type BatchType<R> = Vec<StoredRequestMessage<R>>;
 
@@ -51,7 +54,15 @@ pub struct Proposer<D, NT>
     target_global_batch_size: usize,
     //Time limit for generating a batch with target_global_batch_size size
     global_batch_time_limit: u128,
+    //Hard limit on the time a request can wait in a batch that is being assembled
+    max_assembly_latency: Option<u128>,
     max_batch_size: usize,
+    //The bounds for adapting the target batch size to the load, if enabled
+    adaptive_batch_size: Option<AdaptiveBatchConfig>,
+    //How many of the most recently proposed requests we remember
+    recently_proposed_capacity: usize,
+    // The decided batches which are still waiting to be executed, when backpressure is enabled
+    execution_backlog: Option<Arc<ExecutionBacklog>>,
 
     //For unordered request execution
     executor_handle: ExecutorHandle<D>,
@@ -64,11 +75,236 @@ const PRINT_INTERVAL: usize = 10000;
 struct ProposeBuilder<D> where D: ApplicationData {
     currently_accumulated: Vec<StoredRequestMessage<D::Request>>,
     last_proposal: Instant,
+    assembly: AssemblyTimer,
+    batch_size: BatchSizeController,
 }
 
 impl<D> ProposeBuilder<D> where D: ApplicationData {
-    pub fn new(target_size: usize) -> Self {
-        Self { currently_accumulated: Vec::with_capacity(target_size), last_proposal: Instant::now() }
+    pub fn new(batch_size: BatchSizeController) -> Self {
+        Self {
+            currently_accumulated: Vec::with_capacity(batch_size.current()),
+            last_proposal: Instant::now(),
+            assembly: AssemblyTimer::new(),
+            batch_size,
+        }
+    }
+
+    fn push(&mut self, request: StoredRequestMessage<D::Request>) {
+        self.assembly.request_received();
+
+        self.currently_accumulated.push(request);
+    }
+
+    fn append(&mut self, requests: &mut Vec<StoredRequestMessage<D::Request>>) {
+        if !requests.is_empty() {
+            self.assembly.request_received();
+        }
+
+        self.currently_accumulated.append(requests);
+    }
+}
+
+/// Keeps track of when the first request of the batch currently being assembled arrived
+struct AssemblyTimer {
+    first_request: Option<Instant>,
+}
+
+impl AssemblyTimer {
+    fn new() -> Self {
+        Self { first_request: None }
+    }
+
+    /// Register that a request was added to the batch.
+    /// Only the first request of a batch starts the timer
+    fn request_received(&mut self) {
+        if self.first_request.is_none() {
+            self.first_request = Some(Instant::now());
+        }
+    }
+
+    /// How long the current batch has been assembling for, if it has any requests
+    fn elapsed(&self) -> Option<Duration> {
+        self.first_request.map(|first| first.elapsed())
+    }
+
+    /// The batch has been proposed, return its assembly latency and reset the timer
+    fn batch_proposed(&mut self) -> Option<Duration> {
+        self.first_request.take().map(|first| first.elapsed())
+    }
+}
+
+/// Controls the target size of the batches we propose.
+///
+/// When adaptive, the target grows while batches fill up before the batch timeout
+/// (so the pending requests keep up with it) and shrinks when assembling a batch takes
+/// longer than the target latency, always within the configured bounds
+struct BatchSizeController {
+    min: usize,
+    max: usize,
+    target_latency: Option<Duration>,
+    current: usize,
+}
+
+impl BatchSizeController {
+    /// A controller that always keeps the same target batch size
+    fn fixed(target_size: usize) -> Self {
+        Self { min: target_size, max: target_size, target_latency: None, current: target_size }
+    }
+
+    /// A controller that adapts the target batch size to the load, within the configured bounds.
+    /// The target never exceeds `max_batch_size`, the hard limit on the size of the batches we propose
+    fn adaptive(initial_size: usize, config: &AdaptiveBatchConfig, max_batch_size: usize) -> Self {
+        let max_batch_size = max_batch_size.max(1);
+
+        let min = (config.min_batch_size as usize).clamp(1, max_batch_size);
+        let max = (config.max_batch_size as usize).clamp(min, max_batch_size);
+
+        Self {
+            min,
+            max,
+            target_latency: Some(Duration::from_micros(config.target_latency)),
+            current: initial_size.clamp(min, max),
+        }
+    }
+
+    fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Update the target batch size after proposing a batch of `batch_size` requests,
+    /// which took `assembly_latency` to assemble
+    fn batch_proposed(&mut self, batch_size: usize, assembly_latency: Option<Duration>) {
+        let latency_exceeded = match (assembly_latency, self.target_latency) {
+            (Some(latency), Some(target_latency)) => latency > target_latency,
+            _ => false
+        };
+
+        if latency_exceeded {
+            self.current = (self.current / 2).max(self.min);
+        } else if batch_size >= self.current {
+            self.current = (self.current + (self.current / 4).max(1)).min(self.max);
+        }
+    }
+}
+
+/// Identifies a client request by its sender, session and operation sequence number.
+/// Unlike the digest of the request, this is the same for all of its retransmissions
+type RequestKey = (NodeId, SeqNo, SeqNo);
+
+fn request_key<R>(request: &StoredRequestMessage<R>) -> RequestKey {
+    (request.header().from(), request.message().session_id(), request.message().sequence_number())
+}
+
+/// The requests we have most recently proposed, so that a request
+/// already in flight (e.g. retransmitted by its client) is not proposed again.
+///
+/// The requests are forgotten once the batch they were proposed in is decided.
+/// At most `capacity` requests are remembered, the oldest being forgotten
+/// when it is reached, so the capacity should cover the requests that can be in flight at once.
+/// The requests are also forgotten when a new view is installed
+struct RecentlyProposed {
+    capacity: usize,
+    view: Option<SeqNo>,
+    order: VecDeque<RequestKey>,
+    requests: HashSet<RequestKey>,
+}
+
+impl RecentlyProposed {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            view: None,
+            order: VecDeque::with_capacity(capacity),
+            requests: HashSet::new(),
+        }
+    }
+
+    /// Forget the proposed requests if we are now proposing in a different view
+    fn view_installed(&mut self, view: SeqNo) {
+        if self.view != Some(view) {
+            self.view = Some(view);
+
+            self.order.clear();
+            self.requests.clear();
+        }
+    }
+
+    /// Whether the request was recently proposed
+    fn contains(&self, request: &RequestKey) -> bool {
+        self.requests.contains(request)
+    }
+
+    /// Register a request we are going to propose.
+    /// Returns false if it was recently proposed, in which case it should not be proposed again
+    fn insert(&mut self, request: RequestKey) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+
+        if self.requests.contains(&request) {
+            return false;
+        }
+
+        while self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.requests.remove(&oldest);
+            }
+        }
+
+        self.order.push_back(request);
+        self.requests.insert(request);
+
+        true
+    }
+
+    /// Forget the given requests, as the batch they were proposed in has been decided
+    fn decided(&mut self, decided: impl IntoIterator<Item=RequestKey>) {
+        let mut forgotten = false;
+
+        for request in decided {
+            forgotten |= self.requests.remove(&request);
+        }
+
+        if forgotten {
+            let requests = &self.requests;
+
+            self.order.retain(|request| requests.contains(request));
+        }
+    }
+}
+
+/// Stop tracking the requests which have been decided since we last checked as in flight
+fn forget_decided_requests(consensus_guard: &ProposerConsensusGuard, recently_proposed: &mut RecentlyProposed) {
+    let decided = consensus_guard.take_decided_requests();
+
+    recently_proposed.decided(decided.iter().map(|request| (request.sender, request.session, request.seq_no)));
+}
+
+/// Whether we, as a leader of a view with multiple leaders, should propose the request with the given digest.
+/// We only propose those in our slice of the hash space, leaving the rest to the other leaders,
+/// as replicas reject batches with requests outside of the proposing leader's slice
+fn is_ours_to_propose(digest: &Digest, our_slice: Option<&(Vec<u8>, Vec<u8>)>) -> bool {
+    our_slice.map_or(false, |slice| is_request_in_hash_space(digest, slice))
+}
+
+/// Decide whether the batch currently being assembled should be proposed.
+/// A batch is proposed when it reaches the target size, when the batch timeout (counted
+/// from the last proposal) has elapsed or when the oldest request in it has waited for longer
+/// than the max assembly latency
+fn should_propose(current_batch_size: usize, target_batch_size: usize,
+                  micros_since_last_batch: u128, batch_time_limit: u128,
+                  assembly_latency: Option<Duration>, max_assembly_latency: Option<u128>) -> bool {
+    if current_batch_size >= target_batch_size {
+        return true;
+    }
+
+    if micros_since_last_batch > batch_time_limit {
+        return true;
+    }
+
+    match (assembly_latency, max_assembly_latency) {
+        (Some(latency), Some(max_latency)) => latency.as_micros() >= max_latency,
+        _ => false
     }
 }
 
@@ -87,7 +323,8 @@ impl<D, NT> Proposer<D, NT>
         proposer_config: ProposerConfig,
     ) -> Arc<Self> {
         let ProposerConfig {
-            target_batch_size, max_batch_size, batch_timeout
+            target_batch_size, max_batch_size, batch_timeout, max_assembly_latency,
+            adaptive_batch_size, recently_proposed_capacity, execution_backlog
         } = proposer_config;
 
         Arc::new(Self {
@@ -99,11 +336,20 @@ impl<D, NT> Proposer<D, NT>
             consensus_guard,
             target_global_batch_size: target_batch_size as usize,
             global_batch_time_limit: batch_timeout as u128,
+            max_assembly_latency: max_assembly_latency.map(|latency| latency as u128),
             executor_handle,
             max_batch_size: max_batch_size as usize,
+            adaptive_batch_size,
+            recently_proposed_capacity,
+            execution_backlog,
         })
     }
 
+    /// The backlog of decided batches waiting for execution, if backpressure is enabled
+    pub fn execution_backlog(&self) -> Option<&Arc<ExecutionBacklog>> {
+        self.execution_backlog.as_ref()
+    }
+
     ///Start this work
     pub fn start(self: Arc<Self>) -> JoinHandle<()>
         where NT: OrderProtocolSendNode<D, PBFT<D>> + 'static {
@@ -112,9 +358,16 @@ impl<D, NT> Proposer<D, NT>
             .spawn(move || {
 
                 //The currently accumulated requests, accumulated while we wait for the next batch to propose
-                let mut ordered_propose = ProposeBuilder::new(self.target_global_batch_size);
+                let ordered_batch_size = match &self.adaptive_batch_size {
+                    Some(config) => BatchSizeController::adaptive(self.target_global_batch_size, config, self.max_batch_size),
+                    None => BatchSizeController::fixed(self.target_global_batch_size),
+                };
 
-                let mut unordered_propose = ProposeBuilder::new(self.target_global_batch_size);
+                let mut ordered_propose = ProposeBuilder::new(ordered_batch_size);
+
+                let mut unordered_propose = ProposeBuilder::new(BatchSizeController::fixed(self.target_global_batch_size));
+
+                let mut recently_proposed = RecentlyProposed::new(self.recently_proposed_capacity);
 
                 loop {
                     if self.cancelled.load(Ordering::Relaxed) {
@@ -135,6 +388,20 @@ impl<D, NT> Proposer<D, NT>
                         info!("{:?} // Resuming proposer as we are now able to propose again.", self.node_ref.id());
                     }
 
+                    if let Some(backlog) = &self.execution_backlog {
+                        // Don't keep ordering requests the execution layer can't keep up with
+                        if backlog.is_overloaded() {
+                            warn!("{:?} // Stopping proposer as there are {} decided batches waiting for execution.",
+                                self.node_ref.id(), backlog.pending());
+
+                            if !backlog.wait_until_proposable(&self.cancelled) {
+                                continue;
+                            }
+
+                            info!("{:?} // Resuming proposer as the execution has caught up.", self.node_ref.id());
+                        }
+                    }
+
                     //We do this as we don't want to get stuck waiting for requests that might never arrive
                     //Or even just waiting for any type of request. We want to minimize the amount of time the
                     //Consensus is waiting for new requests
@@ -183,25 +450,19 @@ impl<D, NT> Proposer<D, NT>
                                 for message in messages {
                                     let digest = message.header().unique_digest();
 
-                                    if is_leader {
-                                        if leader_set_size > 1 {
-                                            if is_request_in_hash_space(&digest, our_slice.as_ref().unwrap()) {
-                                                // we know that these operations will always be proposed since we are a
-                                                // Correct replica. We can therefore just add them to the latest op log
-                                                ordered_propose.currently_accumulated.push(message);
-                                            }
-                                        } else {
-                                            // we know that these operations will always be proposed since we are a
-                                            // Correct replica. We can therefore just add them to the latest op log
-                                            ordered_propose.currently_accumulated.push(message);
-                                        }
+                                    if is_leader && (leader_set_size == 1 || is_ours_to_propose(&digest, our_slice.as_ref())) {
+                                        // we know that these operations will always be proposed since we are a
+                                        // Correct replica. We can therefore just add them to the latest op log
+                                        ordered_propose.push(message);
                                     } else {
+                                        // Another leader is responsible for proposing this request,
+                                        // so we watch it to make sure it does
                                         digest_vec.push(ClientRqInfo::new(digest, message.header().from(), message.message().sequence_number(), message.message().session_id()));
                                     }
                                 }
                             }
                             PreProcessorOutputMessage::DeDupedUnorderedRequests(mut messages) => {
-                                unordered_propose.currently_accumulated.append(&mut messages);
+                                unordered_propose.append(&mut messages);
                             }
                         }
 
@@ -224,7 +485,9 @@ impl<D, NT> Proposer<D, NT>
                     //Lets first deal with unordered requests since it should be much quicker and easier
                     let unordered = self.propose_unordered(&mut unordered_propose);
 
-                    let ordered = self.propose_ordered(is_leader, &mut ordered_propose);
+                    forget_decided_requests(&self.consensus_guard, &mut recently_proposed);
+
+                    let ordered = self.propose_ordered(is_leader, info.sequence_number(), &mut ordered_propose, &mut recently_proposed);
 
                     if unordered || ordered {
                         metric_duration(PROPOSER_PROPOSE_TIME_ID, start.elapsed());
@@ -248,23 +511,15 @@ impl<D, NT> Proposer<D, NT>
         if !propose.currently_accumulated.is_empty() {
             let current_batch_size = propose.currently_accumulated.len();
 
-            let should_exec = if current_batch_size < self.target_global_batch_size {
-                let micros_since_last_batch = propose.last_proposal.elapsed()
-                    .as_micros();
-
-                if micros_since_last_batch <= self.global_batch_time_limit {
-                    //Don't execute yet since we don't have the size and haven't timed
-                    //out
-                    false
-                } else {
-                    //We have timed out, execute the pending requests
-                    true
-                }
-            } else {
-                true
-            };
+            let should_exec = should_propose(current_batch_size, propose.batch_size.current(),
+                                             propose.last_proposal.elapsed().as_micros(),
+                                             self.global_batch_time_limit,
+                                             propose.assembly.elapsed(), self.max_assembly_latency);
 
             if should_exec {
+                if let Some(assembly_latency) = propose.assembly.batch_proposed() {
+                    metric_duration(PROPOSER_BATCH_ASSEMBLY_LATENCY_ID, assembly_latency);
+                }
 
                 // Swap in the latest time at which a batch was executed
                 let last_unordered_batch =
@@ -310,21 +565,43 @@ impl<D, NT> Proposer<D, NT>
 
     /// attempt to propose the ordered requests that we have collected
     /// Returns true if a batch was proposed
-    fn propose_ordered(&self, is_leader: bool,
-                       propose: &mut ProposeBuilder<D>, ) -> bool
+    fn propose_ordered(&self, is_leader: bool, view: SeqNo,
+                       propose: &mut ProposeBuilder<D>,
+                       recently_proposed: &mut RecentlyProposed) -> bool
         where NT: OrderProtocolSendNode<D, PBFT<D>> {
 
         //Now let's deal with ordered requests
         if is_leader {
-            let current_batch_size = propose.currently_accumulated.len();
+            // A view change clears the requests in flight, so they can be proposed again
+            recently_proposed.view_installed(view);
 
-            if current_batch_size < self.target_global_batch_size {
-                let micros_since_last_batch = propose.last_proposal.elapsed().as_micros();
+            // Drop the requests that are already in flight before cutting the batch,
+            // so they don't take up room in it
+            propose.currently_accumulated.retain(|request| {
+                let in_flight = recently_proposed.contains(&request_key(request));
 
-                if micros_since_last_batch <= self.global_batch_time_limit {
-                    //Batch isn't large enough and time hasn't passed, don't even attempt to propose
-                    return false;
+                if in_flight {
+                    debug!("{:?} // Request {:?} was recently proposed, not proposing it again", self.node_ref.id(), ClientRqInfo::from(request));
                 }
+
+                !in_flight
+            });
+
+            if propose.currently_accumulated.is_empty() {
+                // There is nothing left to propose, so there is no batch being assembled
+                propose.assembly.batch_proposed();
+
+                return false;
+            }
+
+            let current_batch_size = propose.currently_accumulated.len();
+
+            if !should_propose(current_batch_size, propose.batch_size.current(),
+                               propose.last_proposal.elapsed().as_micros(),
+                               self.global_batch_time_limit,
+                               propose.assembly.elapsed(), self.max_assembly_latency) {
+                //Batch isn't large enough and neither time limit has passed, don't even attempt to propose
+                return false;
             }
 
             let last_proposed_batch = propose.last_proposal.clone();
@@ -347,8 +624,26 @@ impl<D, NT> Proposer<D, NT>
                         None
                     };
 
-                    let current_batch = std::mem::replace(&mut propose.currently_accumulated,
-                                                          next_batch.unwrap_or_else(|| Vec::new()));
+                    let mut current_batch = std::mem::replace(&mut propose.currently_accumulated,
+                                                              next_batch.unwrap_or_else(|| Vec::new()));
+
+                    // Only the first copy of a request retransmitted while we were assembling the batch is kept
+                    current_batch.retain(|request| recently_proposed.insert(request_key(request)));
+
+                    let assembly_latency = propose.assembly.batch_proposed();
+
+                    if let Some(assembly_latency) = assembly_latency {
+                        metric_duration(PROPOSER_BATCH_ASSEMBLY_LATENCY_ID, assembly_latency);
+                    }
+
+                    propose.batch_size.batch_proposed(current_batch.len(), assembly_latency);
+
+                    metric_store_count(PROPOSER_TARGET_BATCH_SIZE_ID, propose.batch_size.current());
+
+                    if !propose.currently_accumulated.is_empty() {
+                        // The left over requests start assembling the next batch
+                        propose.assembly.request_received();
+                    }
 
                     self.propose(seq, &view, current_batch);
 
@@ -403,7 +698,9 @@ impl<D, NT> Proposer<D, NT>
 
         let targets = view.quorum_members().clone();
 
-        info!("{:?} // Proposing new batch with {} request count {:?} to quorum: {:?}", self.node_ref.id(), currently_accumulated.len(), seq, targets);
+        let batch_size = currently_accumulated.len();
+
+        info!("{:?} // Proposing new batch with {} request count {:?} to quorum: {:?}", self.node_ref.id(), batch_size, seq, targets);
 
         let message = PBFTMessage::Consensus(ConsensusMessage::new(
             seq,
@@ -414,10 +711,15 @@ impl<D, NT> Proposer<D, NT>
         self.node_ref.broadcast_signed(message, targets.into_iter());
 
         metric_increment(PROPOSER_BATCHES_MADE_ID, Some(1));
+        metric_store_count(PROPOSER_PROPOSED_BATCH_SIZE_ID, batch_size);
     }
 
     pub fn cancel(&self) {
         self.cancelled.store(true, Ordering::Relaxed);
+
+        if let Some(backlog) = &self.execution_backlog {
+            backlog.wake();
+        }
     }
 
     /// Check if the given request has already appeared in a view change message
@@ -468,3 +770,202 @@ impl<D, NT> Proposer<D, NT>
         false
     }
 }
+
+#[cfg(test)]
+mod proposer_tests {
+    use std::time::Duration;
+
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+    use atlas_core::messages::ClientRqInfo;
+
+    use crate::bft::sync::view::ViewInfo;
+
+    use crate::bft::config::AdaptiveBatchConfig;
+    use crate::bft::consensus::ProposerConsensusGuard;
+
+    use super::{AssemblyTimer, BatchSizeController, forget_decided_requests, is_ours_to_propose, RecentlyProposed, should_propose};
+
+    #[test]
+    fn test_assembly_deadline_forces_proposal() {
+        const TARGET_BATCH_SIZE: usize = 1024;
+        const BATCH_TIMEOUT: u128 = 1_000_000;
+        const MAX_ASSEMBLY_LATENCY: u128 = 1_000;
+
+        let mut assembly = AssemblyTimer::new();
+
+        assert!(assembly.elapsed().is_none());
+
+        assembly.request_received();
+
+        // The small batch is not proposed before the deadline, as the batch timeout has not elapsed
+        assert!(!should_propose(1, TARGET_BATCH_SIZE, 0, BATCH_TIMEOUT,
+                                Some(Duration::ZERO), Some(MAX_ASSEMBLY_LATENCY)));
+
+        std::thread::sleep(Duration::from_micros(MAX_ASSEMBLY_LATENCY as u64));
+
+        assert!(should_propose(1, TARGET_BATCH_SIZE, 0, BATCH_TIMEOUT,
+                               assembly.elapsed(), Some(MAX_ASSEMBLY_LATENCY)));
+
+        // Without a hard deadline, the batch keeps waiting for the target size or the batch timeout
+        assert!(!should_propose(1, TARGET_BATCH_SIZE, 0, BATCH_TIMEOUT,
+                                assembly.elapsed(), None));
+
+        let latency = assembly.batch_proposed().expect("The assembly latency should have been recorded");
+
+        assert!(latency.as_micros() >= MAX_ASSEMBLY_LATENCY);
+        assert!(assembly.elapsed().is_none());
+    }
+
+    #[test]
+    fn test_leaders_only_propose_their_slice() {
+        let leaders = vec![NodeId(0), NodeId(1)];
+
+        let view = ViewInfo::with_leader_set(SeqNo::ZERO, 4, 1, vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)], leaders.clone()).unwrap();
+
+        let digest = Digest::from_bytes(&[0xAB; Digest::LENGTH]).unwrap();
+
+        let proposers: Vec<_> = leaders.iter()
+            .filter(|leader| is_ours_to_propose(&digest, view.hash_space_division().get(leader)))
+            .collect();
+
+        // Exactly one of the leaders proposes the request
+        assert_eq!(proposers.len(), 1);
+
+        // A replica without a slice does not propose any request
+        assert!(!is_ours_to_propose(&digest, None));
+    }
+
+    #[test]
+    fn test_target_batch_size_adapts_to_load() {
+        let config = AdaptiveBatchConfig::new(64, 1024, 1_000);
+
+        let mut batch_size = BatchSizeController::adaptive(256, &config, 4096);
+
+        // Batches keep filling up before the batch timeout, so the target grows up to the max
+        for _ in 0..32 {
+            let target = batch_size.current();
+
+            batch_size.batch_proposed(target, Some(Duration::from_micros(100)));
+        }
+
+        assert_eq!(batch_size.current(), 1024);
+
+        // Assembling the batches takes too long, so the target shrinks down to the min
+        for _ in 0..32 {
+            batch_size.batch_proposed(10, Some(Duration::from_millis(5)));
+        }
+
+        assert_eq!(batch_size.current(), 64);
+
+        // Small batches proposed within the target latency leave the target as it is
+        batch_size.batch_proposed(10, Some(Duration::from_micros(100)));
+
+        assert_eq!(batch_size.current(), 64);
+
+        // The target never grows past the max batch size of the proposer, even if the adaptive bounds allow it
+        let mut capped = BatchSizeController::adaptive(256, &config, 512);
+
+        for _ in 0..32 {
+            let target = capped.current();
+
+            capped.batch_proposed(target, Some(Duration::from_micros(100)));
+        }
+
+        assert_eq!(capped.current(), 512);
+
+        let below_min = BatchSizeController::adaptive(256, &config, 32);
+
+        assert_eq!(below_min.current(), 32);
+
+        // A fixed target never changes
+        let mut fixed = BatchSizeController::fixed(256);
+
+        fixed.batch_proposed(256, None);
+        fixed.batch_proposed(1, Some(Duration::from_secs(1)));
+
+        assert_eq!(fixed.current(), 256);
+    }
+
+    #[test]
+    fn test_duplicated_request_is_not_proposed_twice() {
+        let client = NodeId(1000);
+        let session = SeqNo::ZERO;
+
+        let request = (client, session, SeqNo::ZERO);
+
+        let mut recently_proposed = RecentlyProposed::new(2);
+
+        recently_proposed.view_installed(SeqNo::ZERO);
+
+        assert!(recently_proposed.insert(request));
+        assert!(recently_proposed.contains(&request));
+
+        // The client retransmits the request while it is still in flight.
+        // The retransmission has a different digest, but the same session and sequence number
+        assert!(!recently_proposed.insert(request));
+
+        // Other operations of the same client are not affected
+        assert!(!recently_proposed.contains(&(client, session, SeqNo::ZERO.next())));
+
+        // Once enough requests have been proposed since, it is forgotten
+        assert!(recently_proposed.insert((client, session, SeqNo::ZERO.next())));
+        assert!(recently_proposed.insert((NodeId(1001), session, SeqNo::ZERO)));
+
+        assert!(!recently_proposed.contains(&request));
+        assert!(recently_proposed.insert(request));
+
+        // A view change clears the requests in flight
+        recently_proposed.view_installed(SeqNo::ZERO.next());
+
+        assert!(!recently_proposed.contains(&request));
+        assert!(recently_proposed.insert((NodeId(1001), session, SeqNo::ZERO)));
+    }
+
+    #[test]
+    fn test_decided_requests_are_no_longer_in_flight() {
+        let session = SeqNo::ZERO;
+
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let consensus_guard = ProposerConsensusGuard::new(view.clone(), 10);
+
+        let mut recently_proposed = RecentlyProposed::new(2);
+
+        recently_proposed.view_installed(view.sequence_number());
+
+        let decided = (NodeId(1000), session, SeqNo::ZERO);
+        let in_flight = (NodeId(1001), session, SeqNo::ZERO);
+
+        assert!(recently_proposed.insert(decided));
+        assert!(recently_proposed.insert(in_flight));
+
+        // Nothing has been decided yet
+        forget_decided_requests(&consensus_guard, &mut recently_proposed);
+
+        assert!(recently_proposed.contains(&decided));
+
+        // The batch with the first request is decided
+        let digest = Digest::from_bytes(&[1; Digest::LENGTH]).unwrap();
+
+        consensus_guard.batch_decided(&[ClientRqInfo::new(digest, decided.0, decided.2, decided.1)]);
+
+        forget_decided_requests(&consensus_guard, &mut recently_proposed);
+
+        assert!(!recently_proposed.contains(&decided));
+        assert!(recently_proposed.contains(&in_flight));
+
+        // The decided requests are only handed out once
+        assert!(consensus_guard.take_decided_requests().is_empty());
+
+        // It no longer takes up room, so proposing another request
+        // doesn't evict the one which is still in flight
+        assert!(recently_proposed.insert((NodeId(1002), session, SeqNo::ZERO)));
+        assert!(recently_proposed.contains(&in_flight));
+        assert!(!recently_proposed.insert(in_flight));
+
+        // The decided request is not mistaken for one in flight
+        assert!(!recently_proposed.contains(&decided));
+    }
+}
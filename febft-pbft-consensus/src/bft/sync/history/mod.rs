@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+
+use crate::bft::sync::view::ViewInfo;
+
+/// What caused a given view change to start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewChangeTrigger {
+    /// Client requests timed out locally
+    RequestTimeout,
+    /// We have received enough STOP messages from other replicas
+    ReceivedStops,
+    /// A node is joining the quorum
+    QuorumJoin(NodeId),
+}
+
+/// How a given view change was concluded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewChangeOutcome {
+    /// The view change protocol ran to completion
+    Installed,
+    /// The view was installed by the state transfer protocol
+    InstalledFromStateTransfer,
+    /// The view change was aborted since its collects were not sound
+    AbortedUnsound,
+}
+
+/// A record of a view change that has taken place
+#[derive(Debug, Clone)]
+pub struct ViewChangeRecord {
+    trigger: ViewChangeTrigger,
+    from_view: SeqNo,
+    to_view: SeqNo,
+    old_leader: NodeId,
+    new_leader: NodeId,
+    duration: Duration,
+    outcome: ViewChangeOutcome,
+}
+
+impl ViewChangeRecord {
+    pub fn trigger(&self) -> ViewChangeTrigger {
+        self.trigger
+    }
+
+    pub fn from_view(&self) -> SeqNo {
+        self.from_view
+    }
+
+    pub fn to_view(&self) -> SeqNo {
+        self.to_view
+    }
+
+    pub fn old_leader(&self) -> NodeId {
+        self.old_leader
+    }
+
+    pub fn new_leader(&self) -> NodeId {
+        self.new_leader
+    }
+
+    /// How long the view change took, from the moment it was triggered
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn outcome(&self) -> ViewChangeOutcome {
+        self.outcome
+    }
+}
+
+/// A bounded history of the last view changes performed by this replica.
+/// When full, the oldest records are discarded.
+pub struct ViewChangeHistory {
+    capacity: usize,
+    // The trigger and start time of the view change currently taking place
+    ongoing: Option<(ViewChangeTrigger, Instant)>,
+    records: VecDeque<ViewChangeRecord>,
+}
+
+impl ViewChangeHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ongoing: None,
+            records: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Register that a view change has been triggered.
+    /// If there is already a view change taking place, the first trigger is kept
+    pub fn view_change_started(&mut self, trigger: ViewChangeTrigger) {
+        if self.ongoing.is_none() {
+            self.ongoing = Some((trigger, Instant::now()));
+        }
+    }
+
+    /// Register that the view change from `from` to `to` has been concluded
+    pub fn view_change_concluded(&mut self, from: &ViewInfo, to: &ViewInfo, outcome: ViewChangeOutcome) {
+        let (trigger, duration) = match self.ongoing.take() {
+            Some((trigger, started)) => (trigger, started.elapsed()),
+            // We did not witness the start of this view change
+            None => (ViewChangeTrigger::ReceivedStops, Duration::ZERO),
+        };
+
+        if self.capacity == 0 {
+            return;
+        }
+
+        while self.records.len() >= self.capacity {
+            self.records.pop_front();
+        }
+
+        self.records.push_back(ViewChangeRecord {
+            trigger,
+            from_view: from.sequence_number(),
+            to_view: to.sequence_number(),
+            old_leader: from.leader(),
+            new_leader: to.leader(),
+            duration,
+            outcome,
+        });
+    }
+
+    /// Whether there is a view change currently taking place
+    pub fn is_ongoing(&self) -> bool {
+        self.ongoing.is_some()
+    }
+
+    /// The recorded view changes, from oldest to most recent
+    pub fn records(&self) -> impl Iterator<Item=&ViewChangeRecord> {
+        self.records.iter()
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use super::*;
+
+    #[test]
+    fn test_view_change_history() {
+        let mut history = ViewChangeHistory::new(2);
+
+        let view_0 = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let view_1 = view_0.next_view();
+        let view_2 = view_1.next_view();
+        let view_3 = view_2.next_view();
+
+        history.view_change_started(ViewChangeTrigger::RequestTimeout);
+        history.view_change_concluded(&view_0, &view_1, ViewChangeOutcome::Installed);
+
+        history.view_change_started(ViewChangeTrigger::ReceivedStops);
+        // The first trigger is the one that is kept
+        history.view_change_started(ViewChangeTrigger::RequestTimeout);
+        history.view_change_concluded(&view_1, &view_2, ViewChangeOutcome::Installed);
+
+        history.view_change_started(ViewChangeTrigger::QuorumJoin(NodeId(4)));
+        history.view_change_concluded(&view_2, &view_3, ViewChangeOutcome::InstalledFromStateTransfer);
+
+        assert!(!history.is_ongoing());
+
+        let records: Vec<_> = history.records().collect();
+
+        // The oldest record was discarded
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(records[0].trigger(), ViewChangeTrigger::ReceivedStops);
+        assert_eq!(records[0].from_view(), view_1.sequence_number());
+        assert_eq!(records[0].to_view(), view_2.sequence_number());
+        assert_eq!(records[0].old_leader(), view_1.leader());
+        assert_eq!(records[0].new_leader(), view_2.leader());
+        assert_eq!(records[0].outcome(), ViewChangeOutcome::Installed);
+
+        assert_eq!(records[1].trigger(), ViewChangeTrigger::QuorumJoin(NodeId(4)));
+        assert_eq!(records[1].to_view(), view_3.sequence_number());
+        assert_eq!(records[1].outcome(), ViewChangeOutcome::InstalledFromStateTransfer);
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::Instant;
+
+use atlas_common::node_id::NodeId;
+
+use crate::bft::sync::view::ViewInfo;
+
+/// Our own membership in the quorum: whether we are attempting to join it and
+/// whether we have been removed from it (in which case our view and log are stale,
+/// so we must catch up with the quorum before being integrated again).
+#[derive(Debug, Default)]
+pub struct QuorumJoin {
+    // Whether we have been removed from the quorum (and therefore hold stale state)
+    removed: bool,
+    // Whether we are currently attempting to join the quorum
+    entering: bool,
+    // Whether our current attempt is a rejoin after having been removed
+    rejoining: bool,
+    // When we give up on our current attempt to join the quorum
+    deadline: Option<Instant>,
+    // Whether we have given up on our last attempt, without reporting it yet
+    expired: bool,
+}
+
+impl QuorumJoin {
+    /// We are moving from `current_view` to `view`. This must be called as soon as we know
+    /// about the new view (and not only once it is finalized), so a removal that happens
+    /// during a view change is not missed.
+    ///
+    /// Returns whether we have just been removed from the quorum
+    pub fn view_installing(&mut self, node: NodeId, current_view: &ViewInfo, view: &ViewInfo) -> bool {
+        let removed = current_view.quorum_members().contains(&node) && !view.quorum_members().contains(&node);
+
+        if removed && !self.removed {
+            self.removed = true;
+
+            return true;
+        }
+
+        false
+    }
+
+    /// A view has been installed. When we are a member of it, any attempt of ours
+    /// to join the quorum is over and our state is no longer stale
+    pub fn view_installed(&mut self, node: NodeId, view: &ViewInfo) {
+        if view.quorum_members().contains(&node) {
+            self.removed = false;
+            self.abandon();
+        }
+    }
+
+    /// Start an attempt to join the quorum, which we give up on at `deadline`.
+    /// Returns whether we are rejoining after having been removed
+    pub fn begin(&mut self, deadline: Instant) -> bool {
+        self.entering = true;
+        self.rejoining = self.removed;
+        self.deadline = Some(deadline);
+        self.expired = false;
+
+        self.rejoining
+    }
+
+    /// Whether we must run the state transfer protocol before being integrated in
+    /// the quorum. This is only the case once per rejoin attempt.
+    pub fn must_catch_up(&mut self) -> bool {
+        std::mem::replace(&mut self.rejoining, false)
+    }
+
+    /// Leave the join path, discarding all of the state of the current attempt
+    pub fn abandon(&mut self) {
+        self.entering = false;
+        self.rejoining = false;
+        self.deadline = None;
+    }
+
+    pub fn is_entering(&self) -> bool {
+        self.entering
+    }
+
+    pub fn is_removed(&self) -> bool {
+        self.removed
+    }
+
+    /// Whether the current attempt to join the quorum has run out of time
+    pub fn has_expired(&self, now: Instant) -> bool {
+        self.entering && self.deadline.map_or(false, |deadline| now >= deadline)
+    }
+
+    /// Give up on the current attempt to join the quorum, if it has run out of time.
+    /// The failure is kept until it is taken with [Self::take_expired], so it can be
+    /// reported to the reconfiguration protocol.
+    ///
+    /// Returns whether we gave up on the attempt
+    pub fn expire(&mut self, now: Instant) -> bool {
+        if !self.has_expired(now) {
+            return false;
+        }
+
+        self.abandon();
+        self.expired = true;
+
+        true
+    }
+
+    /// Take the failure of our last attempt to join the quorum, if we gave up on it
+    /// and it has not been reported yet
+    pub fn take_expired(&mut self) -> bool {
+        std::mem::replace(&mut self.expired, false)
+    }
+}
+
+#[cfg(test)]
+mod join_tests {
+    use std::time::{Duration, Instant};
+
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use crate::bft::sync::must_run_cst;
+    use crate::bft::sync::view::ViewInfo;
+
+    use super::QuorumJoin;
+
+    #[test]
+    fn test_removed_node_catches_up_before_rejoining() {
+        let removed = NodeId(4);
+
+        let view_0 = ViewInfo::from_quorum(SeqNo::ZERO, vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), removed]).unwrap();
+
+        let mut join = QuorumJoin::default();
+
+        // We are removed in the middle of a view change, before the new view is finalized
+        let view_1 = ViewInfo::from_quorum(view_0.sequence_number().next(), vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)]).unwrap();
+
+        assert!(join.view_installing(removed, &view_0, &view_1));
+        assert!(join.is_removed());
+
+        // The quorum advances without us
+        let view_2 = view_1.next_view();
+
+        assert!(!join.view_installing(removed, &view_1, &view_2));
+        join.view_installed(removed, &view_2);
+
+        assert!(join.is_removed());
+
+        assert!(join.begin(Instant::now() + Duration::from_secs(10)));
+        assert!(join.is_entering());
+
+        // The view change which integrates us can't be finalized before we catch up
+        // with the quorum, even if our log seems to be up to date with it
+        let last_execution = SeqNo::ZERO.next();
+
+        assert!(must_run_cst(&mut join, Some(last_execution), last_execution));
+
+        // We are not participating in the quorum while the state transfer runs
+        assert!(join.is_removed());
+        assert!(join.is_entering());
+
+        // Once it completes, the view change is resumed and finalized,
+        // and we only have to catch up once
+        assert!(!must_run_cst(&mut join, Some(last_execution), last_execution));
+
+        // Which installs the view that integrates us, so we start participating again
+        let view_3 = view_2.next_view_with_new_node(removed);
+
+        join.view_installed(removed, &view_3);
+
+        assert!(!join.is_removed());
+        assert!(!join.is_entering());
+
+        // A node that was never removed joins without catching up first
+        assert!(!join.begin(Instant::now() + Duration::from_secs(10)));
+        assert!(!must_run_cst(&mut join, Some(last_execution), last_execution));
+
+        // Unless it is behind the quorum by more than one decision
+        assert!(must_run_cst(&mut join, Some(SeqNo::ZERO), last_execution.next()));
+    }
+
+    #[test]
+    fn test_expired_join_attempt_is_reported_once() {
+        let mut join = QuorumJoin::default();
+
+        let start = Instant::now();
+        let deadline = start + Duration::from_secs(10);
+
+        join.begin(deadline);
+
+        // The quorum still has time to integrate us
+        assert!(!join.expire(start));
+        assert!(join.is_entering());
+        assert!(!join.take_expired());
+
+        // It did not, so we give up on the attempt
+        assert!(join.expire(deadline));
+        assert!(!join.is_entering());
+
+        // Giving up only happens once
+        assert!(!join.expire(deadline + Duration::from_secs(1)));
+
+        // And the failure is reported once to the reconfiguration protocol
+        assert!(join.take_expired());
+        assert!(!join.take_expired());
+
+        // A new attempt discards a failure that was not reported
+        join.begin(deadline);
+        join.expire(deadline);
+        join.begin(deadline + Duration::from_secs(10));
+
+        assert!(!join.take_expired());
+    }
+}
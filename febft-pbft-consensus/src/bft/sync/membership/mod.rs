@@ -0,0 +1,184 @@
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+
+use crate::bft::message::{PBFTMessage, ViewChangeMessageKind};
+use crate::bft::sync::is_within_view_window;
+use crate::bft::sync::view::ViewInfo;
+
+/// How the sender of a given message relates to the views we are working with.
+/// This is the single policy used to decide whether messages from nodes that are not
+/// part of the quorum (for example, nodes that have just been removed from it or that
+/// are not yet added) are processed, both by the consensus and by the synchronizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SenderMembership {
+    /// The sender is a member of the current view or of the view we are changing to
+    Member,
+    /// The sender is a node whose entrance into the quorum we know is underway, and the message
+    /// belongs to a view ahead of ours (within the look-ahead window), which we have not installed yet.
+    /// The membership is checked again once the view is known
+    FutureView,
+    /// The sender is not a member, but the message is part of the reconfiguration
+    /// of the quorum (the node is joining) so it must be handled
+    Reconfiguration,
+    /// The sender is not a member of any relevant view, the message must be dropped
+    NonMember,
+}
+
+impl SenderMembership {
+    /// Whether a message from this sender should be processed
+    pub fn is_accepted(&self) -> bool {
+        !matches!(self, SenderMembership::NonMember)
+    }
+}
+
+/// The view a message belongs to, if it belongs to one
+fn message_view<O>(message: &PBFTMessage<O>) -> Option<SeqNo> {
+    match message {
+        PBFTMessage::Consensus(consensus) => Some(consensus.view()),
+        PBFTMessage::ViewChange(view_change) => Some(view_change.sequence_number()),
+        PBFTMessage::ObserverMessage(_) => None,
+    }
+}
+
+/// Classify the sender of a message, according to the current view, the view we are
+/// currently changing to (if any) and the nodes we know are joining the quorum.
+/// Messages from a known joining node for views past the latest view we know of, but no more than
+/// `max_view_look_ahead` views past it, are classified as [SenderMembership::FutureView].
+/// Any other node is not trusted to tell us about views we don't know, so tagging a message
+/// with a future view does not exempt it from the non member policy
+pub fn classify_sender<O>(from: NodeId, message: &PBFTMessage<O>,
+                          current_view: &ViewInfo, next_view: Option<&ViewInfo>,
+                          known_joining: &[NodeId], max_view_look_ahead: usize) -> SenderMembership {
+    let is_member = current_view.quorum_members().contains(&from)
+        || next_view.map_or(false, |view| view.quorum_members().contains(&from));
+
+    if is_member {
+        return SenderMembership::Member;
+    }
+
+    let latest_known_view = next_view.unwrap_or(current_view).sequence_number();
+
+    if let Some(view) = message_view(message).filter(|_| known_joining.contains(&from)) {
+        if view > latest_known_view && is_within_view_window(latest_known_view, view, max_view_look_ahead) {
+            return SenderMembership::FutureView;
+        }
+    }
+
+    match message {
+        PBFTMessage::ViewChange(view_change) => {
+            match view_change.kind() {
+                // A node announcing its own entrance into the quorum
+                ViewChangeMessageKind::StopQuorumJoin(joining) if *joining == from => SenderMembership::Reconfiguration,
+                _ => SenderMembership::NonMember,
+            }
+        }
+        // Observers are not required to be a part of the quorum
+        PBFTMessage::ObserverMessage(_) => SenderMembership::Reconfiguration,
+        PBFTMessage::Consensus(_) => SenderMembership::NonMember,
+    }
+}
+
+#[cfg(test)]
+mod membership_tests {
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use crate::bft::message::{ConsensusMessage, ConsensusMessageKind, PBFTMessage, ViewChangeMessage, ViewChangeMessageKind};
+    use crate::bft::sync::view::ViewInfo;
+
+    use super::{classify_sender, SenderMembership};
+
+    const MAX_LOOK_AHEAD: usize = 4;
+
+    #[test]
+    fn test_removed_node_consensus_message_is_dropped() {
+        let previous_view = ViewInfo::from_quorum(SeqNo::ZERO, vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)]).unwrap();
+        let current_view = ViewInfo::from_quorum(previous_view.sequence_number().next(), vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)]).unwrap();
+
+        let digest = Digest::from_bytes(&[1; Digest::LENGTH]).unwrap();
+
+        let message: PBFTMessage<()> = PBFTMessage::Consensus(ConsensusMessage::new(SeqNo::ZERO.next(), current_view.sequence_number(),
+                                                                                   ConsensusMessageKind::Prepare(digest)));
+
+        assert_eq!(classify_sender(NodeId(0), &message, &current_view, None, &[], MAX_LOOK_AHEAD), SenderMembership::Member);
+        assert_eq!(classify_sender(NodeId(4), &message, &current_view, None, &[], MAX_LOOK_AHEAD), SenderMembership::NonMember);
+        assert!(!classify_sender(NodeId(4), &message, &current_view, None, &[], MAX_LOOK_AHEAD).is_accepted());
+    }
+
+    #[test]
+    fn test_joining_node_join_message_is_handled() {
+        let current_view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let joining = NodeId(4);
+
+        let join_message: PBFTMessage<()> = PBFTMessage::ViewChange(ViewChangeMessage::new(current_view.sequence_number().next(),
+                                                                                          ViewChangeMessageKind::StopQuorumJoin(joining)));
+
+        let other_join_message: PBFTMessage<()> = PBFTMessage::ViewChange(ViewChangeMessage::new(current_view.sequence_number().next(),
+                                                                                                ViewChangeMessageKind::StopQuorumJoin(NodeId(5))));
+
+        assert_eq!(classify_sender(joining, &join_message, &current_view, None, &[], MAX_LOOK_AHEAD), SenderMembership::Reconfiguration);
+        // A non member cannot vote for the entrance of another node
+        assert_eq!(classify_sender(joining, &other_join_message, &current_view, None, &[], MAX_LOOK_AHEAD), SenderMembership::NonMember);
+
+        // Once the view including the joining node is being installed, it is treated as a member
+        let next_view = current_view.next_view_with_new_node(joining);
+
+        let stop_message: PBFTMessage<()> = PBFTMessage::ViewChange(ViewChangeMessage::new(next_view.sequence_number(),
+                                                                                          ViewChangeMessageKind::Stop(vec![])));
+
+        assert_eq!(classify_sender(joining, &stop_message, &current_view, Some(&next_view), &[], MAX_LOOK_AHEAD), SenderMembership::Member);
+    }
+
+    #[test]
+    fn test_known_joining_node_is_accepted_for_future_views() {
+        let current_view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let joining = NodeId(4);
+
+        // The quorum has already started the view change which integrates the joining node,
+        // but we have not installed the view that includes it yet
+        let future_view = current_view.next_view_with_new_node(joining);
+
+        let message: PBFTMessage<()> = PBFTMessage::Consensus(ConsensusMessage::new(SeqNo::ZERO.next(), future_view.sequence_number(),
+                                                                                   ConsensusMessageKind::Prepare(Digest::from_bytes(&[1; Digest::LENGTH]).unwrap())));
+
+        assert_eq!(classify_sender(joining, &message, &current_view, None, &[joining], MAX_LOOK_AHEAD), SenderMembership::FutureView);
+        assert!(classify_sender(joining, &message, &current_view, None, &[joining], MAX_LOOK_AHEAD).is_accepted());
+
+        // Messages for views beyond the look-ahead window are still dropped
+        let mut far_view = future_view.sequence_number();
+
+        for _ in 0..MAX_LOOK_AHEAD {
+            far_view = far_view.next();
+        }
+
+        let far_message: PBFTMessage<()> = PBFTMessage::ViewChange(ViewChangeMessage::new(far_view, ViewChangeMessageKind::Stop(vec![])));
+
+        assert_eq!(classify_sender(joining, &far_message, &current_view, None, &[joining], MAX_LOOK_AHEAD), SenderMembership::NonMember);
+
+        // As are its messages for the view we are currently in, where it is not a member
+        let current_message: PBFTMessage<()> = PBFTMessage::Consensus(ConsensusMessage::new(SeqNo::ZERO.next(), current_view.sequence_number(),
+                                                                                           ConsensusMessageKind::Prepare(Digest::from_bytes(&[1; Digest::LENGTH]).unwrap())));
+
+        assert_eq!(classify_sender(joining, &current_message, &current_view, None, &[joining], MAX_LOOK_AHEAD), SenderMembership::NonMember);
+    }
+
+    #[test]
+    fn test_unknown_node_cannot_claim_a_future_view() {
+        let current_view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let unknown = NodeId(7);
+
+        let next_view = current_view.sequence_number().next();
+
+        let consensus_message: PBFTMessage<()> = PBFTMessage::Consensus(ConsensusMessage::new(SeqNo::ZERO.next(), next_view,
+                                                                                             ConsensusMessageKind::Prepare(Digest::from_bytes(&[1; Digest::LENGTH]).unwrap())));
+
+        let stop_message: PBFTMessage<()> = PBFTMessage::ViewChange(ViewChangeMessage::new(next_view, ViewChangeMessageKind::Stop(vec![])));
+
+        assert_eq!(classify_sender(unknown, &consensus_message, &current_view, None, &[], MAX_LOOK_AHEAD), SenderMembership::NonMember);
+        assert_eq!(classify_sender(unknown, &stop_message, &current_view, None, &[], MAX_LOOK_AHEAD), SenderMembership::NonMember);
+
+        // Nor does another node's entrance into the quorum make it trusted
+        assert_eq!(classify_sender(unknown, &consensus_message, &current_view, None, &[NodeId(4)], MAX_LOOK_AHEAD), SenderMembership::NonMember);
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     cmp::Ordering,
     collections::VecDeque,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use std::cell::Cell;
 use std::collections::{BTreeMap, BTreeSet};
@@ -15,8 +15,10 @@ use log::{debug, error, info, warn};
 #[cfg(feature = "serialize_serde")]
 use serde::{Deserialize, Serialize};
 
-use atlas_common::{collections, prng};
+use atlas_common::channel::ChannelSyncTx;
+use atlas_common::collections;
 use atlas_common::crypto::hash::Digest;
+use atlas_common::crypto::signature::PublicKey;
 use atlas_common::error::*;
 use atlas_common::node_id::NodeId;
 use atlas_common::ordering::{Orderable, SeqNo, tbo_advance_message_queue, tbo_pop_message, tbo_queue_message_arc};
@@ -29,20 +31,33 @@ use atlas_core::persistent_log::OrderingProtocolLog;
 use atlas_core::request_pre_processing::RequestPreProcessor;
 use atlas_core::smr::smr_decision_log::{ShareableMessage, unwrap_shareable_message};
 use atlas_core::timeouts::{RqTimeout, Timeouts};
+use atlas_metrics::metrics::metric_increment;
 use atlas_smr_application::serialize::ApplicationData;
 
 use crate::bft::{OPDecision, PBFT};
+use crate::bft::config::{SynchronizerConfig, UnsoundViewChangePolicy, ViewChangeTimeoutConfig};
 use crate::bft::consensus::{Consensus, ConsensusStatus};
 use crate::bft::log::decisions::{CollectData, Proof, ViewDecisionPair};
 use crate::bft::log::Log;
 use crate::bft::message::{ConsensusMessage, ConsensusMessageKind, FwdConsensusMessage, PBFTMessage, ViewChangeMessage, ViewChangeMessageKind};
+use crate::bft::metric::{SYNC_EXCESS_VIEW_MSGS_DROPPED_ID, SYNC_FUTURE_VIEW_MSGS_DROPPED_ID};
+use crate::bft::sync::join::QuorumJoin;
+use crate::bft::sync::history::{ViewChangeHistory, ViewChangeOutcome, ViewChangeRecord, ViewChangeTrigger};
+use crate::bft::sync::membership::{classify_sender, SenderMembership};
+use crate::bft::sync::nonce::{InstalledNonceSource, NonceSource};
 use crate::bft::sync::view::ViewInfo;
+use crate::bft::sync::watchdog::{ConnectivitySource, QuorumAlert, QuorumConnectivityMonitor};
 
 use self::{follower_sync::FollowerSynchronizer, replica_sync::ReplicaSynchronizer};
 
 pub mod follower_sync;
 pub mod replica_sync;
 pub mod view;
+pub mod nonce;
+pub mod history;
+pub mod membership;
+pub mod watchdog;
+pub mod join;
 
 /// Attempt to extract a msg from the tbo queue
 /// If the message is not null (there is a message in the tbo queue)
@@ -95,7 +110,7 @@ macro_rules! finalize_view_change {
             // we need to run cst before proceeding with view change
             FinalizeStatus::RunCst(state) => {
                 $self.finalize_state.replace(Some(state));
-                $self.phase.replace(ProtoPhase::SyncingState);
+                $self.set_phase(ProtoPhase::SyncingState);
                 SynchronizerStatus::RunCst
             }
             // we may finish the view change proto
@@ -182,6 +197,11 @@ impl Sound {
     }
 }
 
+/// Whether we abort a view change whose collects are `sound` (or not), following the given policy
+fn aborts_view_change(policy: UnsoundViewChangePolicy, sound: &Sound) -> bool {
+    !sound.test() && policy == UnsoundViewChangePolicy::AbortAndRetry
+}
+
 /// Represents a queue of view change messages that arrive out of context into a node.
 pub struct TboQueue<O> {
     // the current view
@@ -199,10 +219,165 @@ pub struct TboQueue<O> {
     stop_data: VecDeque<VecDeque<ShareableMessage<PBFTMessage<O>>>>,
     // stores all SYNC messages for the next view
     sync: VecDeque<VecDeque<ShareableMessage<PBFTMessage<O>>>>,
+    // How many views ahead of the next view we are willing to queue messages for
+    max_view_look_ahead: usize,
+    // How many views past the next one we have aborted changing to, without installing them.
+    // The queues start at the view we are stopping toward, which follows the aborted ones
+    skipped_views: usize,
+}
+
+/// Check if a view change message with the sequence number `msg_seq` falls within the window
+/// of views we are willing to queue messages for, starting at `base_seq`.
+/// Messages for older views are within the window, as they are dropped by the queue itself
+pub(crate) fn is_within_view_window(base_seq: SeqNo, msg_seq: SeqNo, max_view_look_ahead: usize) -> bool {
+    match msg_seq.index(base_seq) {
+        Either::Right(i) => i <= max_view_look_ahead,
+        Either::Left(_) => true,
+    }
+}
+
+/// Check if a `SYNC` message for the view `msg_seq`, sent by `from`, belongs to a
+/// view change newer than the one we are currently running (to the `running` view).
+/// This happens when the leader of the view we are changing to fails as well, in which case
+/// the rest of the quorum moves on to the following view.
+/// Returns the view we should jump to, if the message was sent by the leader of that view
+fn superseding_view(running: &ViewInfo, msg_seq: SeqNo, from: NodeId) -> Option<ViewInfo> {
+    if msg_seq <= running.sequence_number() {
+        return None;
+    }
+
+    let newer_view = running.peek(msg_seq);
+
+    if newer_view.leader() != from {
+        return None;
+    }
+
+    Some(newer_view)
+}
+
+/// Check if the senders of the valid `STOP-DATA` messages carried by a `SYNC` for `newer_view`
+/// form a quorum of it, which proves that a quorum has given up on the view change we are running
+fn proves_superseding_quorum(newer_view: &ViewInfo, senders: &BTreeSet<NodeId>) -> bool {
+    senders.iter()
+        .filter(|sender| newer_view.quorum_members().contains(sender))
+        .count() >= newer_view.params().quorum()
+}
+
+/// What we do with the view change we are running toward the `running` view, in `phase`,
+/// upon receiving a `SYNC` for a newer view
+#[derive(Debug)]
+enum SupersedingSync {
+    /// The `SYNC` is not from the leader of a newer view change, so we keep ours
+    Keep,
+    /// The `SYNC` is from the leader of the given newer view, but does not prove that a quorum
+    /// has stopped for it, so we keep ours
+    Unproven(ViewInfo),
+    /// A quorum has given up on our view change, so we abandon it and jump to the given view
+    Abandon(ViewInfo),
+}
+
+/// Decide what to do with the view change we are running toward the `running` view, in `phase`,
+/// upon receiving a `SYNC` for the view `msg_seq` from `from`.
+/// `signed_senders` returns the senders of the validly signed collects carried by the `SYNC`,
+/// if they all pertain to the view change to the given view
+fn superseding_sync<F>(phase: ProtoPhase, running: &ViewInfo, msg_seq: SeqNo, from: NodeId, signed_senders: F) -> SupersedingSync
+    where F: FnOnce(SeqNo) -> Option<BTreeSet<NodeId>> {
+    match phase {
+        ProtoPhase::Stopping2(_) | ProtoPhase::StoppingData(_) | ProtoPhase::Syncing => {}
+        _ => return SupersedingSync::Keep,
+    }
+
+    let newer_view = match superseding_view(running, msg_seq, from) {
+        Some(newer_view) => newer_view,
+        None => return SupersedingSync::Keep,
+    };
+
+    let proven = signed_senders(newer_view.sequence_number())
+        .map_or(false, |senders| proves_superseding_quorum(&newer_view, &senders));
+
+    if proven {
+        SupersedingSync::Abandon(newer_view)
+    } else {
+        SupersedingSync::Unproven(newer_view)
+    }
+}
+
+/// How the stopping phase advances once we have counted `stops` STOP messages for the view we are stopping toward
+#[derive(Debug)]
+enum StopProgress {
+    /// We have not sent our own STOP, and not enough replicas have stopped for us to join them
+    Waiting(usize),
+    /// More than `f` replicas have stopped, so we join the view change with our own STOP
+    Join,
+    /// We have sent our STOP, and are waiting for a quorum of them
+    Stopped(usize),
+    /// A quorum has stopped, so we install the view we are stopping toward and move on to the given phase
+    Install(ProtoPhase),
+}
+
+/// Advance the stopping phase, in which we are in `phase`, after counting `stops` STOP messages
+/// for the `stopping_view`, while in the `current_view`
+fn stop_progress(phase: ProtoPhase, stops: usize, current_view: &ViewInfo, stopping_view: &ViewInfo, our_id: NodeId) -> StopProgress {
+    // NOTE: we only take this branch of the code before
+    // we have sent our own STOP message
+    if let ProtoPhase::Stopping(_) = phase {
+        return if stops > current_view.params().f() {
+            StopProgress::Join
+        } else {
+            StopProgress::Waiting(stops)
+        };
+    }
+
+    if stops < current_view.params().quorum() {
+        return StopProgress::Stopped(stops);
+    }
+
+    // The new leader collects the STOP-DATA messages, while the others wait for its SYNC
+    if stopping_view.leader() == our_id {
+        StopProgress::Install(ProtoPhase::StoppingData(0))
+    } else {
+        StopProgress::Install(ProtoPhase::Syncing)
+    }
+}
+
+/// Whether the leader of the `next_view` has received enough STOP-DATA messages to send its SYNC
+fn has_stop_data_quorum(collects: usize, next_view: &ViewInfo) -> bool {
+    collects >= next_view.params().quorum()
+}
+
+/// Check if we can queue a message from `from`, given the senders of the messages already
+/// queued for the same view. We keep at most `max_senders` distinct senders and, when
+/// `one_per_sender` is set, at most one message per sender, so the memory used by each view is bounded
+fn can_queue_sender(queued: impl Iterator<Item=NodeId>, from: NodeId, max_senders: usize, one_per_sender: bool) -> bool {
+    let mut senders = BTreeSet::new();
+
+    for sender in queued {
+        if sender == from {
+            return !one_per_sender;
+        }
+
+        senders.insert(sender);
+    }
+
+    senders.len() < max_senders
+}
+
+/// How many distinct senders we queue messages from, for the view `look_ahead` views past the next one.
+/// Every view change adds at most one node to the quorum, and a node that is joining it also
+/// announces itself, so this is bounded by the size of the current view
+fn max_senders_for_view(current_view: &ViewInfo, look_ahead: usize) -> usize {
+    current_view.params().n() + look_ahead + 1
+}
+
+/// Whether a message of kind `new` replaces a queued message of kind `queued` from the same sender,
+/// for the same view. A `STOP` takes precedence over a `STOP-QUORUM-JOIN`, as it means the
+/// sender wants to change views regardless of the node joining the quorum
+fn takes_precedence<O>(new: &ViewChangeMessageKind<O>, queued: &ViewChangeMessageKind<O>) -> bool {
+    matches!((new, queued), (ViewChangeMessageKind::Stop(_), ViewChangeMessageKind::StopQuorumJoin(_)))
 }
 
 impl<O> TboQueue<O> {
-    pub(crate) fn new(view: ViewInfo) -> Self {
+    pub(crate) fn new(view: ViewInfo, max_view_look_ahead: usize) -> Self {
         Self {
             view,
             next_view: None,
@@ -211,6 +386,8 @@ impl<O> TboQueue<O> {
             stop: VecDeque::new(),
             stop_data: VecDeque::new(),
             sync: VecDeque::new(),
+            max_view_look_ahead,
+            skipped_views: 0,
         }
     }
 
@@ -222,6 +399,38 @@ impl<O> TboQueue<O> {
         self.next_view.as_ref()
     }
 
+    /// Forget the next view, as we are no longer changing to it
+    pub fn clear_next_view(&mut self) {
+        self.next_view = None;
+    }
+
+    /// The sequence number of the view we are stopping toward, which is the first view
+    /// we queue messages for. This is the view after ours, unless we have aborted changing to it
+    fn stopping_seq(&self) -> SeqNo {
+        let mut seq = self.view.sequence_number().next();
+
+        for _ in 0..self.skipped_views {
+            seq = seq.next();
+        }
+
+        seq
+    }
+
+    /// The view we are stopping toward
+    pub fn stopping_view(&self) -> ViewInfo {
+        self.view.peek(self.stopping_seq())
+    }
+
+    /// Abort changing to the view we are stopping toward, without installing it,
+    /// so that we stop toward the view that follows it instead.
+    /// The messages queued for the aborted view are discarded
+    pub fn skip_stopping_view(&mut self) {
+        self.next_view = None;
+        self.skipped_views += 1;
+
+        self.next_instance_queue();
+    }
+
     /// Advance to the next view we are working on
     pub fn advance(&mut self) -> bool {
         if let Some(next_view) = self.next_view.take() {
@@ -245,10 +454,13 @@ impl<O> TboQueue<O> {
 
                 self.previous_view = Some(prev_view);
 
-                for _ in 0..i {
+                // The queues of the views we have skipped have already been discarded
+                for _ in 0..i.saturating_sub(self.skipped_views) {
                     self.next_instance_queue();
                 }
 
+                self.skipped_views = self.skipped_views.saturating_sub(i);
+
                 true
             }
             Either::Right(_) => {
@@ -301,29 +513,142 @@ impl<O> TboQueue<O> {
         self.sync.get(0).map(|deque| deque.len() > 0).unwrap_or(false)
     }
 
+    /// Check whether the given message is too far ahead of our current view to be queued.
+    /// Without this, a faulty node could make us allocate queues for arbitrarily distant views
+    fn is_too_far_ahead(&self, m: &ShareableMessage<PBFTMessage<O>>) -> bool {
+        // NOTE: we use the stopping view because we want to retrieve messages
+        // for v+1, as we haven't started installing the new view yet
+        let seq = self.stopping_seq();
+
+        if is_within_view_window(seq, m.sequence_number(), self.max_view_look_ahead) {
+            return false;
+        }
+
+        warn!("Dropping view change message from {:?} for view {:?}, as it is too far ahead of our current view {:?}",
+            m.header().from(), m.sequence_number(), self.view.sequence_number());
+
+        metric_increment(SYNC_FUTURE_VIEW_MSGS_DROPPED_ID, Some(1));
+
+        true
+    }
+
+    /// The index of the queue of the view of the given message, relative to the view we are stopping toward.
+    /// Messages for older views have no index, as they are dropped by the queue itself
+    fn view_queue_index(&self, m: &ShareableMessage<PBFTMessage<O>>) -> Option<usize> {
+        let seq = self.stopping_seq();
+
+        match m.sequence_number().index(seq) {
+            Either::Right(i) => Some(i),
+            Either::Left(_) => None,
+        }
+    }
+
+    /// Check whether the queue for the view of the given message is already full,
+    /// or already holds a message from its sender.
+    /// The queue of the next view, which is the one we are processing, accepts more than one
+    /// message from each sender (for example, retransmitted or superseding messages)
+    fn is_excess(&self, queue: &VecDeque<VecDeque<ShareableMessage<PBFTMessage<O>>>>, m: &ShareableMessage<PBFTMessage<O>>) -> bool {
+        let index = match self.view_queue_index(m) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let view_queue = match queue.get(index) {
+            Some(view_queue) => view_queue,
+            None => return false,
+        };
+
+        let max_senders = max_senders_for_view(&self.view, index);
+
+        if can_queue_sender(view_queue.iter().map(|queued| queued.header().from()), m.header().from(), max_senders, index > 0) {
+            return false;
+        }
+
+        warn!("Dropping view change message from {:?} for view {:?}, as we have already queued one from it or the view's queue is full",
+            m.header().from(), m.sequence_number());
+
+        metric_increment(SYNC_EXCESS_VIEW_MSGS_DROPPED_ID, Some(1));
+
+        true
+    }
+
+    /// STOP and STOP-QUORUM-JOIN messages share the same queue, so a `STOP` arriving after a
+    /// queued `STOP-QUORUM-JOIN` from the same sender must replace it, instead of being dropped
+    fn drop_superseded_stop(&mut self, m: &ShareableMessage<PBFTMessage<O>>) {
+        let index = match self.view_queue_index(m) {
+            Some(index) => index,
+            None => return,
+        };
+
+        let view_queue = match self.stop.get_mut(index) {
+            Some(view_queue) => view_queue,
+            None => return,
+        };
+
+        let (from, kind) = (m.header().from(), m.message().view_change().kind());
+
+        view_queue.retain(|queued| queued.header().from() != from || !takes_precedence(kind, queued.message().view_change().kind()));
+    }
+
     /// Queues a `STOP` message for later processing, or drops it
-    /// immediately if it pertains to an older view change instance.
+    /// immediately if it pertains to an older view change instance
+    /// or to a view too far ahead of our own.
     fn queue_stop(&mut self, m: ShareableMessage<PBFTMessage<O>>) {
-        // NOTE: we use next() because we want to retrieve messages
+        if self.is_too_far_ahead(&m) {
+            return;
+        }
+
+        self.drop_superseded_stop(&m);
+
+        if self.is_excess(&self.stop, &m) {
+            return;
+        }
+
+        // NOTE: we use the stopping view because we want to retrieve messages
         // for v+1, as we haven't started installing the new view yet
-        let seq = self.view.sequence_number().next();
+        let seq = self.stopping_seq();
         tbo_queue_message_arc(seq, &mut self.stop, (m.sequence_number(), m))
     }
 
     /// Queues a `STOP-DATA` message for later processing, or drops it
-    /// immediately if it pertains to an older view change instance.
+    /// immediately if it pertains to an older view change instance
+    /// or to a view too far ahead of our own.
     fn queue_stop_data(&mut self, m: ShareableMessage<PBFTMessage<O>>) {
-        let seq = self.view.sequence_number().next();
+        if self.is_too_far_ahead(&m) || self.is_excess(&self.stop_data, &m) {
+            return;
+        }
+
+        let seq = self.stopping_seq();
         tbo_queue_message_arc(seq, &mut self.stop_data, (m.sequence_number(), m))
     }
 
     /// Queues a `SYNC` message for later processing, or drops it
-    /// immediately if it pertains to an older view change instance.
+    /// immediately if it pertains to an older view change instance
+    /// or to a view too far ahead of our own.
     fn queue_sync(&mut self, m: ShareableMessage<PBFTMessage<O>>) {
-        let seq = self.view.sequence_number().next();
+        if self.is_too_far_ahead(&m) || self.is_excess(&self.sync, &m) {
+            return;
+        }
+
+        let seq = self.stopping_seq();
         tbo_queue_message_arc(seq, &mut self.sync, (m.sequence_number(), m))
     }
 
+    /// Take a queued `SYNC` message sent by the leader of a view newer than the `running` one,
+    /// meaning the view change we are running has been superseded
+    fn pop_superseding_sync(&mut self, running: &ViewInfo) -> Option<ShareableMessage<PBFTMessage<O>>> {
+        for queue in self.sync.iter_mut() {
+            let position = queue.iter()
+                .position(|m| superseding_view(running, m.sequence_number(), m.header().from()).is_some());
+
+            if let Some(position) = position {
+                return queue.remove(position);
+            }
+        }
+
+        None
+    }
+
     pub fn view(&self) -> &ViewInfo {
         &self.view
     }
@@ -333,8 +658,9 @@ impl<O> TboQueue<O> {
     }
 }
 
+/// The phases of the view change protocol
 #[derive(Copy, Clone, Debug)]
-pub(super) enum ProtoPhase {
+pub enum ProtoPhase {
     // the view change protocol isn't running;
     // we are watching pending client requests for
     // any potential timeouts
@@ -365,6 +691,101 @@ pub(super) enum ProtoPhase {
     SyncingState,
 }
 
+impl ProtoPhase {
+    /// Whether we must have installed the next view while in this phase
+    fn requires_next_view(&self) -> bool {
+        matches!(self, ProtoPhase::StoppingData(_) | ProtoPhase::Syncing)
+    }
+}
+
+/// What we do with the STOP message we are re-sending, when the timeouts layer notifies us
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StopRetransmission {
+    /// Re-send it, as the view change has not advanced since we sent it
+    Retransmit,
+    /// Keep waiting for its retransmission timer
+    Wait,
+    /// Stop re-sending it
+    Stop,
+}
+
+/// Decide what to do with the STOP message we are re-sending, in the given phase,
+/// according to whether its retransmission timer is among the timeouts we were notified of.
+/// It is only re-sent while we wait for the other replicas to join the view change
+fn stop_retransmission_action(phase: ProtoPhase, timer_fired: bool) -> StopRetransmission {
+    match phase {
+        ProtoPhase::Stopping2(_) | ProtoPhase::ViewStopping2(_) if timer_fired => StopRetransmission::Retransmit,
+        ProtoPhase::Stopping2(_) | ProtoPhase::ViewStopping2(_) => StopRetransmission::Wait,
+        _ => StopRetransmission::Stop,
+    }
+}
+
+/// Whether we must run the state transfer protocol before finalizing a view change
+/// whose latest executed consensus instance is `last_executed_cid`
+fn must_run_cst(quorum_join: &mut QuorumJoin, last_execution: Option<SeqNo>, last_executed_cid: SeqNo) -> bool {
+    // If we are rejoining the quorum, our state can't be trusted to be up to date,
+    // so we must always run the state transfer protocol before finalizing
+    if quorum_join.must_catch_up() {
+        return true;
+    }
+
+    //If we are more than one operation behind the most recent consensus id,
+    //Then we must run a consensus state transfer
+    u32::from(last_execution.unwrap_or(SeqNo::ZERO)) + 1 < u32::from(last_executed_cid)
+}
+
+/// Whether the phase we are in is consistent with the next view we have installed
+fn is_phase_consistent(phase: &ProtoPhase, next_view: Option<&ViewInfo>) -> bool {
+    !phase.requires_next_view() || next_view.is_some()
+}
+
+/// The state accumulated by a view change which is still in progress,
+/// along with the state of the quorum join path
+struct PartialViewChange<'a, O> {
+    stopped: &'a mut IntMap<Vec<StoredRequestMessage<O>>>,
+    collects: &'a mut IntMap<StoredMessage<PBFTMessage<O>>>,
+    finalize_state: &'a mut Option<FinalizeState<O>>,
+    currently_adding: &'a mut BTreeMap<NodeId, BTreeSet<NodeId>>,
+    currently_adding_node: &'a Cell<Option<NodeId>>,
+    quorum_join: &'a mut QuorumJoin,
+}
+
+impl<'a, O> PartialViewChange<'a, O> {
+    /// Discard everything the view change has accumulated so far and leave the join path
+    fn discard(self) {
+        self.stopped.clear();
+        self.collects.clear();
+        self.currently_adding.clear();
+        self.finalize_state.take();
+
+        leave_join_path(self.currently_adding_node, self.quorum_join);
+    }
+}
+
+/// Leave the quorum join path, discarding the node we were adding to the quorum
+/// and any attempt of our own to join it (including its deadline)
+fn leave_join_path(currently_adding_node: &Cell<Option<NodeId>>, quorum_join: &mut QuorumJoin) {
+    currently_adding_node.replace(None);
+    quorum_join.abandon();
+}
+
+/// The phase we must be in, given the next view we have installed.
+/// When the phase requires a next view which isn't installed, the partial view change
+/// is discarded and we must go back to [ProtoPhase::Init]
+fn reset_inconsistent_phase<O>(phase: ProtoPhase, next_view: Option<&ViewInfo>, partial: PartialViewChange<'_, O>) -> ProtoPhase {
+    if is_phase_consistent(&phase, next_view) {
+        phase
+    } else {
+        partial.discard();
+
+        ProtoPhase::Init
+    }
+}
+
+/// A transition between two phases of the view change protocol,
+/// along with the sequence number of the view we were in at the time
+pub type PhaseTransition = (ProtoPhase, ProtoPhase, SeqNo);
+
 // TODO: finish statuses returned from `process_message`
 #[derive(Debug)]
 pub enum SynchronizerStatus<O> {
@@ -458,8 +879,26 @@ pub struct Synchronizer<D: ApplicationData> {
     collects: Mutex<CollectsType<D>>,
     // Used to store the finalize state when we are forced to run the CST protocol
     finalize_state: RefCell<Option<FinalizeState<D::Request>>>,
-    // We need to keep track of whether we are entering the quorum
-    entering_quorum: Cell<bool>,
+    // Whether we are entering the quorum, and whether we have been removed from it
+    quorum_join: RefCell<QuorumJoin>,
+    // How long we wait for the quorum to integrate us when joining it
+    join_timeout: Duration,
+    // The source of the nonces used in the messages we forge
+    nonce_source: InstalledNonceSource,
+    // The history of the latest view changes
+    view_change_history: Mutex<ViewChangeHistory>,
+    // Where we report the transitions between phases, if anywhere
+    phase_listener: Mutex<Option<ChannelSyncTx<PhaseTransition>>>,
+    // The timed out requests that caused the latest STOP we have broadcast
+    last_view_change_trigger: Mutex<Option<Vec<ClientRqInfo>>>,
+    // Watches our connectivity to the quorum
+    quorum_monitor: Mutex<QuorumConnectivityMonitor>,
+    // Where we report the alerts about our connectivity to the quorum, if anywhere
+    quorum_alert_listener: Mutex<Option<ChannelSyncTx<QuorumAlert>>>,
+    // Whether we validate that membership changes preserve the quorum intersection
+    check_quorum_intersection: bool,
+    // What we do when the collects of a view change are not sound
+    unsound_view_change_policy: UnsoundViewChangePolicy,
     // Replica accessory
     accessory: SynchronizerAccessory<D>,
 }
@@ -490,13 +929,15 @@ impl<D> AbstractSynchronizer<D> for Synchronizer<D>
 
         let current_view = self.view();
 
+        self.track_removal(&current_view, &view);
+
         if let Some(previous_view) = view.previous_view() {
             if current_view.sequence_number() != previous_view.sequence_number() {
                 if !self.tbo.lock().unwrap().install_view(previous_view) {
                     // If we don't install a new view, then we don't want to forget our current state now do we?
 
                     debug!("Replacing our phase with Init");
-                    self.phase.replace(ProtoPhase::Init);
+                    self.set_phase(ProtoPhase::Init);
                 }
             }
 
@@ -507,16 +948,25 @@ impl<D> AbstractSynchronizer<D> for Synchronizer<D>
                 // If we are the leader of the new view, it means that we might still
                 // Have time to actually process the messages that we received from the
                 // Other nodes in time to maintain regency
-                self.phase.replace(ProtoPhase::StoppingData(0));
+                self.set_phase(ProtoPhase::StoppingData(0));
             } else if self.can_process_sync() {
                 // If we are not the leader, then we need to check for the same thing
                 // But for sync messages. If we have a sync message in our queue,
                 // It means that we probably didn't receive the first decision after
                 // The view change in the log, which means we can't keep executing
                 // Without first processing this sync message
-                self.phase.replace(ProtoPhase::Syncing);
+                self.set_phase(ProtoPhase::Syncing);
             } else {
-                self.tbo.lock().unwrap().install_view(view);
+                self.tbo.lock().unwrap().install_view(view.clone());
+
+                self.quorum_join.borrow_mut().view_installed(self.node_id, &view);
+
+                let mut history = self.view_change_history.lock().unwrap();
+
+                if history.is_ongoing() {
+                    history.view_change_concluded(&current_view, &view, ViewChangeOutcome::InstalledFromStateTransfer);
+                }
+
                 return false;
             }
 
@@ -528,7 +978,7 @@ impl<D> AbstractSynchronizer<D> for Synchronizer<D>
             if !self.tbo.lock().unwrap().install_view(view) {
                 // If we don't install a new view, then we don't want to forget our current state now do we?
                 debug!("Replacing our phase with Init");
-                self.phase.replace(ProtoPhase::Init);
+                self.set_phase(ProtoPhase::Init);
             }
         }
 
@@ -542,7 +992,7 @@ impl<D> AbstractSynchronizer<D> for Synchronizer<D>
 
 impl<D> Synchronizer<D> where D: ApplicationData + 'static,
 {
-    pub fn new_follower(node_id: NodeId, view: ViewInfo) -> Arc<Self> {
+    pub fn new_follower(node_id: NodeId, view: ViewInfo, sync_config: SynchronizerConfig) -> Arc<Self> {
         Arc::new(Self {
             node_id,
             phase: Cell::new(ProtoPhase::Init),
@@ -550,14 +1000,24 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
             currently_adding_node: Cell::new(None),
             currently_adding: RefCell::new(Default::default()),
             collects: Mutex::new(Default::default()),
-            tbo: Mutex::new(TboQueue::new(view)),
+            tbo: Mutex::new(TboQueue::new(view, sync_config.max_view_look_ahead)),
             finalize_state: RefCell::new(None),
-            entering_quorum: Cell::new(false),
+            quorum_join: RefCell::new(QuorumJoin::default()),
+            join_timeout: sync_config.join_timeout,
+            nonce_source: InstalledNonceSource::new(),
+            view_change_history: Mutex::new(ViewChangeHistory::new(sync_config.view_change_history)),
+            last_view_change_trigger: Mutex::new(None),
+            phase_listener: Mutex::new(None),
+            quorum_monitor: Mutex::new(QuorumConnectivityMonitor::new(sync_config.quorum_alert_after)),
+            quorum_alert_listener: Mutex::new(None),
+            check_quorum_intersection: sync_config.check_quorum_intersection,
+            unsound_view_change_policy: sync_config.unsound_view_change_policy,
             accessory: SynchronizerAccessory::Follower(FollowerSynchronizer::new()),
         })
     }
 
-    pub fn new_replica(node_id: NodeId, view: ViewInfo, timeout_dur: Duration) -> Arc<Self> {
+    pub fn new_replica(node_id: NodeId, view: ViewInfo, timeout_dur: Duration, timeout_config: ViewChangeTimeoutConfig,
+                       sync_config: SynchronizerConfig) -> Arc<Self> {
         Arc::new(Self {
             node_id,
             phase: Cell::new(ProtoPhase::Init),
@@ -565,15 +1025,26 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
             currently_adding_node: Cell::new(None),
             currently_adding: RefCell::new(Default::default()),
             collects: Mutex::new(Default::default()),
-            tbo: Mutex::new(TboQueue::new(view)),
+            tbo: Mutex::new(TboQueue::new(view, sync_config.max_view_look_ahead)),
             finalize_state: RefCell::new(None),
-            entering_quorum: Cell::new(false),
-            accessory: SynchronizerAccessory::Replica(ReplicaSynchronizer::new(timeout_dur)),
+            quorum_join: RefCell::new(QuorumJoin::default()),
+            join_timeout: sync_config.join_timeout,
+            nonce_source: InstalledNonceSource::new(),
+            view_change_history: Mutex::new(ViewChangeHistory::new(sync_config.view_change_history)),
+            last_view_change_trigger: Mutex::new(None),
+            phase_listener: Mutex::new(None),
+            quorum_monitor: Mutex::new(QuorumConnectivityMonitor::new(sync_config.quorum_alert_after)),
+            quorum_alert_listener: Mutex::new(None),
+            check_quorum_intersection: sync_config.check_quorum_intersection,
+            unsound_view_change_policy: sync_config.unsound_view_change_policy,
+            accessory: SynchronizerAccessory::Replica(ReplicaSynchronizer::new(timeout_dur, timeout_config, sync_config.forward_batch_size, sync_config.forward_interval,
+                                                                                sync_config.stop_retransmit_interval, sync_config.max_stop_retransmits)),
         })
     }
 
     /// Initialize a new `Synchronizer` with the given quorum members.
-    pub fn initialize_with_quorum(node_id: NodeId, seq_no: SeqNo, quorum_members: Vec<NodeId>, timeout_dur: Duration) -> Result<Arc<Self>> {
+    pub fn initialize_with_quorum(node_id: NodeId, seq_no: SeqNo, quorum_members: Vec<NodeId>, timeout_dur: Duration,
+                                  timeout_config: ViewChangeTimeoutConfig, sync_config: SynchronizerConfig) -> Result<Arc<Self>> {
         let n = quorum_members.len();
 
         let f = (n - 1) / 3;
@@ -585,29 +1056,194 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
         Ok(Arc::new(Self {
             node_id,
             phase: Cell::new(ProtoPhase::Init),
-            tbo: Mutex::new(TboQueue::new(view_info)),
+            tbo: Mutex::new(TboQueue::new(view_info, sync_config.max_view_look_ahead)),
             stopped: RefCell::new(Default::default()),
             currently_adding_node: Cell::new(None),
             currently_adding: RefCell::new(Default::default()),
             collects: Mutex::new(Default::default()),
             finalize_state: RefCell::new(None),
-            entering_quorum: Cell::new(false),
-            accessory: SynchronizerAccessory::Replica(ReplicaSynchronizer::new(timeout_dur)),
+            quorum_join: RefCell::new(QuorumJoin::default()),
+            join_timeout: sync_config.join_timeout,
+            nonce_source: InstalledNonceSource::new(),
+            view_change_history: Mutex::new(ViewChangeHistory::new(sync_config.view_change_history)),
+            last_view_change_trigger: Mutex::new(None),
+            phase_listener: Mutex::new(None),
+            quorum_monitor: Mutex::new(QuorumConnectivityMonitor::new(sync_config.quorum_alert_after)),
+            quorum_alert_listener: Mutex::new(None),
+            check_quorum_intersection: sync_config.check_quorum_intersection,
+            unsound_view_change_policy: sync_config.unsound_view_change_policy,
+            accessory: SynchronizerAccessory::Replica(ReplicaSynchronizer::new(timeout_dur, timeout_config, sync_config.forward_batch_size, sync_config.forward_interval,
+                                                                                sync_config.stop_retransmit_interval, sync_config.max_stop_retransmits)),
         }))
     }
 
+    /// The latest view changes performed by this replica, from oldest to most recent.
+    /// The amount of records kept is configured in the [SynchronizerConfig]
+    pub fn view_change_history(&self) -> Vec<ViewChangeRecord> {
+        self.view_change_history.lock().unwrap().records().cloned().collect()
+    }
+
+    /// The client requests whose timeout caused the latest STOP message we have broadcast.
+    /// Returns `None` if we have not broadcast a STOP yet, or if the latest one was
+    /// sent because we received STOP messages from other replicas
+    pub fn last_view_change_trigger(&self) -> Option<Vec<ClientRqInfo>> {
+        self.last_view_change_trigger.lock().unwrap().clone()
+    }
+
+    /// Check our connectivity to the quorum of the current view, with the peers reported by the
+    /// installed [ConnectivitySource]. This is called whenever the ordering protocol is polled.
+    /// When we have been unable to reach a quorum for longer than the configured duration, and when
+    /// the quorum connectivity is restored afterwards, an alert is reported to the installed listener
+    /// so operators can be notified of the outage
+    pub fn watch_quorum_connectivity(&self) {
+        let view = self.view();
+
+        let alert = self.quorum_monitor.lock().unwrap().check(self.node_id, &view, Instant::now());
+
+        match &alert {
+            Some(QuorumAlert::QuorumUnreachable { below_quorum_for, reachable, .. }) => {
+                error!("{:?} // Unable to reach a quorum of view {:?} for {:?}. Reachable members: {:?}",
+                    self.node_id, view.sequence_number(), below_quorum_for, reachable);
+            }
+            Some(QuorumAlert::QuorumRestored { outage, .. }) => {
+                info!("{:?} // Quorum connectivity restored for view {:?} after {:?}",
+                    self.node_id, view.sequence_number(), outage);
+            }
+            None => {}
+        }
+
+        if let (Some(alert), Some(listener)) = (alert, &*self.quorum_alert_listener.lock().unwrap()) {
+            if listener.try_send_return(alert).is_err() {
+                warn!("{:?} // Failed to report quorum alert, listener is full or disconnected", self.node_id);
+            }
+        }
+    }
+
+    /// Use the given source to learn which peers we are connected to, when watching
+    /// our connectivity to the quorum. Until one is installed, the connectivity is not watched
+    pub fn install_connectivity_source(&self, source: Box<dyn ConnectivitySource>) {
+        self.quorum_monitor.lock().unwrap().install_source(source);
+    }
+
+    /// Report the alerts about our connectivity to the quorum to the given channel
+    pub fn install_quorum_alert_listener(&self, listener: ChannelSyncTx<QuorumAlert>) {
+        *self.quorum_alert_listener.lock().unwrap() = Some(listener);
+    }
+
+    /// Replace the source of nonces used by this synchronizer.
+    /// Mostly useful for tests, where we want the view change to be reproducible
+    pub fn install_nonce_source(&self, nonce_source: Box<dyn NonceSource>) {
+        self.nonce_source.install(nonce_source);
+    }
+
+    /// Report every transition between the phases of the view change protocol
+    /// to the given channel, providing an audit trail of the view changes
+    pub fn install_phase_listener(&self, listener: ChannelSyncTx<PhaseTransition>) {
+        *self.phase_listener.lock().unwrap() = Some(listener);
+    }
+
+    /// Move to a new phase of the view change protocol.
+    /// All phase transitions must go through here
+    fn set_phase(&self, new: ProtoPhase) {
+        let old = self.phase.replace(new);
+
+        debug!("{:?} // View change phase transition {:?} -> {:?}", self.node_id, old, new);
+
+        if let Some(listener) = &*self.phase_listener.lock().unwrap() {
+            let view = self.view().sequence_number();
+
+            if listener.try_send_return((old, new, view)).is_err() {
+                warn!("{:?} // Failed to report phase transition {:?} -> {:?}, listener is full or disconnected", self.node_id, old, new);
+            }
+        }
+    }
+
+    /// Check how the sender of a message relates to the current view, the view
+    /// we are changing to, if any, and the nodes we know are joining the quorum.
+    /// See [SenderMembership] for the policy applied to messages from non members
+    pub fn sender_membership(&self, from: NodeId, message: &PBFTMessage<D::Request>) -> SenderMembership {
+        let known_joining: Vec<NodeId> = self.currently_adding_node.get().into_iter()
+            .chain(self.currently_adding.borrow().keys().copied())
+            .collect();
+
+        let guard = self.tbo.lock().unwrap();
+
+        classify_sender(from, message, guard.view(), guard.next_view(), &known_joining, guard.max_view_look_ahead)
+    }
+
+    /// Build the view which adds the given node to the quorum of `view`.
+    /// When configured, the membership change is validated so that the quorums of
+    /// both views are guaranteed to intersect.
+    fn next_view_with_new_node(&self, view: &ViewInfo, joining: NodeId) -> Result<ViewInfo> {
+        if self.check_quorum_intersection {
+            view.try_next_view_with_new_node(joining)
+        } else {
+            Ok(view.next_view_with_new_node(joining))
+        }
+    }
+
     /// The next view that is going to be processed
     fn next_view(&self) -> Option<ViewInfo> { self.tbo.lock().unwrap().next_view().cloned() }
 
+    /// Get the next view, which must always be installed when we are in the
+    /// [ProtoPhase::StoppingData] or [ProtoPhase::Syncing] phases.
+    /// If it isn't, our state is inconsistent, so we discard the partial view change
+    /// state and reset back to the [ProtoPhase::Init] phase, where we can wait for
+    /// a new view change to be triggered.
+    fn next_view_or_reset(&self) -> Option<ViewInfo> {
+        let next_view = self.next_view();
+
+        if !is_phase_consistent(&self.phase.get(), next_view.as_ref()) {
+            error!("{:?} // We are in phase {:?} but we have not installed the next view. Our state is inconsistent, resetting to the init phase",
+                self.node_id, self.phase.get());
+
+            let phase = reset_inconsistent_phase(self.phase.get(), next_view.as_ref(), PartialViewChange {
+                stopped: &mut self.stopped.borrow_mut(),
+                collects: &mut self.collects.lock().unwrap(),
+                finalize_state: &mut self.finalize_state.borrow_mut(),
+                currently_adding: &mut self.currently_adding.borrow_mut(),
+                currently_adding_node: &self.currently_adding_node,
+                quorum_join: &mut self.quorum_join.borrow_mut(),
+            });
+
+            self.set_phase(phase);
+        }
+
+        next_view
+    }
+
+    /// Leave the quorum join path, discarding the node we were adding to the quorum
+    /// and any attempt of our own to join it (including its deadline), so no stale
+    /// join state survives into the next view change
+    fn leave_join_path(&self) {
+        leave_join_path(&self.currently_adding_node, &mut self.quorum_join.borrow_mut());
+    }
+
     /// The previous view that was processed
     fn previous_view(&self) -> Option<ViewInfo> { self.tbo.lock().unwrap().previous_view().clone() }
 
     /// Install the next view which we are currently working on changing to
-    fn install_next_view(&self, view: ViewInfo) { self.tbo.lock().unwrap().install_next_view(view) }
+    fn install_next_view(&self, view: ViewInfo) {
+        // Check whether we are being removed as soon as we know about the next view,
+        // instead of only once it is finalized
+        self.track_removal(&self.view(), &view);
+
+        self.tbo.lock().unwrap().install_next_view(view)
+    }
+
+    /// Keep track of whether we are being removed from the quorum when moving from `current_view` to `view`
+    fn track_removal(&self, current_view: &ViewInfo, view: &ViewInfo) {
+        if self.quorum_join.borrow_mut().view_installing(self.node_id, current_view, view) {
+            warn!("{:?} // We have been removed from the quorum in view {:?}", self.node_id, view.sequence_number());
+        }
+    }
 
     /// Advance the view the next one in the queue
     fn advance_view(&self) -> bool { self.tbo.lock().unwrap().advance() }
 
+    /// The view we are stopping toward. See [TboQueue::stopping_view]
+    pub(super) fn stopping_view(&self) -> ViewInfo { self.tbo.lock().unwrap().stopping_view() }
+
     /// Signal this `TboQueue` that it may be able to extract new
     /// view change messages from its internal storage.
     pub fn signal(&self) {
@@ -644,14 +1280,17 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                     &mut tbo_guard.stop
                 );
 
+                // Changing the phase needs access to the current view
+                drop(tbo_guard);
+
                 match &result {
                     SynchronizerPollStatus::NextMessage(message) => {
                         match message.message().view_change().kind() {
                             ViewChangeMessageKind::StopQuorumJoin(_) => {
-                                self.phase.replace(ProtoPhase::ViewStopping(0));
+                                self.set_phase(ProtoPhase::ViewStopping(0));
                             }
                             _ => {
-                                self.phase.replace(ProtoPhase::Stopping(0));
+                                self.set_phase(ProtoPhase::Stopping(0));
                             }
                         }
                     }
@@ -660,31 +1299,130 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
 
                 result
             }
-            ProtoPhase::Stopping(_) | ProtoPhase::Stopping2(_) | ProtoPhase::ViewStopping(_) | ProtoPhase::ViewStopping2(_) => {
+            ProtoPhase::Stopping(_) | ProtoPhase::ViewStopping(_) | ProtoPhase::ViewStopping2(_) => {
                 extract_msg!(D::Request=>
                     &mut tbo_guard.get_queue,
                     &mut tbo_guard.stop
                 )
             }
+            ProtoPhase::Stopping2(_) => {
+                let result = extract_msg!(D::Request=>
+                    &mut tbo_guard.get_queue,
+                    &mut tbo_guard.stop
+                );
+
+                Self::or_superseding_sync(result, &mut tbo_guard)
+            }
             ProtoPhase::StoppingData(_) => {
-                extract_msg!(D::Request  =>
+                let result = extract_msg!(D::Request  =>
                     &mut tbo_guard.get_queue,
                     &mut tbo_guard.stop_data
-                )
+                );
+
+                Self::or_superseding_sync(result, &mut tbo_guard)
             }
             ProtoPhase::Syncing => {
-                extract_msg!(D::Request  =>
+                let result = extract_msg!(D::Request  =>
                     &mut tbo_guard.get_queue,
                     &mut tbo_guard.sync
-                )
+                );
+
+                Self::or_superseding_sync(result, &mut tbo_guard)
             }
             ProtoPhase::SyncingState => SynchronizerPollStatus::ResumeViewChange,
         }
     }
 
+    /// When there are no more messages to process for the view change we are running,
+    /// check if we have queued a `SYNC` message for a newer view change, which
+    /// supersedes ours
+    fn or_superseding_sync(result: SynchronizerPollStatus<D::Request>, tbo: &mut TboQueue<D::Request>) -> SynchronizerPollStatus<D::Request> {
+        if !matches!(result, SynchronizerPollStatus::Recv) {
+            return result;
+        }
+
+        let running = tbo.next_view().cloned().unwrap_or_else(|| tbo.stopping_view());
+
+        match tbo.pop_superseding_sync(&running) {
+            Some(message) => SynchronizerPollStatus::NextMessage(message),
+            None => SynchronizerPollStatus::Recv,
+        }
+    }
+
+    /// Check if the given message is a `SYNC` for a view change newer than the one
+    /// we are currently running, in which case we abandon ours and jump to the newer one.
+    /// We only do so when the `SYNC` carries validly signed `STOP-DATA` messages for the newer
+    /// view from a quorum of its members, as otherwise a single faulty node posing as the
+    /// leader of the newer view could make us abandon a view change that is still live
+    fn abandon_superseded_view_change<NT>(&self, s_message: &ShareableMessage<PBFTMessage<D::Request>>, node: &NT)
+        where NT: OrderProtocolSendNode<D, PBFT<D>> {
+        let collects = match s_message.message().view_change().kind() {
+            ViewChangeMessageKind::Sync(collects) => collects.collects(),
+            _ => return,
+        };
+
+        let running = self.next_view().unwrap_or_else(|| self.stopping_view());
+
+        let newer_view = match superseding_sync(self.phase.get(), &running, s_message.sequence_number(), s_message.header().from(),
+                                                |newer_seq| signed_collect_senders::<D, _>(node, collects, newer_seq)) {
+            SupersedingSync::Keep => return,
+            SupersedingSync::Unproven(newer_view) => {
+                warn!("{:?} // Received SYNC for view {:?} from {:?} without a quorum of valid STOP-DATA messages. Not abandoning our view change to {:?}",
+                    self.node_id, newer_view.sequence_number(), s_message.header().from(), running.sequence_number());
+
+                return;
+            }
+            SupersedingSync::Abandon(newer_view) => newer_view,
+        };
+
+        warn!("{:?} // Received SYNC for view {:?} while changing to view {:?}. Abandoning our view change",
+            self.node_id, newer_view.sequence_number(), running.sequence_number());
+
+        self.stopped.borrow_mut().clear();
+        self.collects.lock().unwrap().clear();
+        self.finalize_state.borrow_mut().take();
+
+        if let SynchronizerAccessory::Replica(rep) = &self.accessory {
+            rep.view_change_failed();
+        }
+
+        self.install_next_view(newer_view);
+
+        self.set_phase(ProtoPhase::Syncing);
+    }
+
+    /// Abort the view change to `aborted_view`, whose collects are not sound, and start
+    /// changing to the view that follows it, with a backed off timeout.
+    /// Only used with [UnsoundViewChangePolicy::AbortAndRetry]
+    fn abort_unsound_view_change<NT>(&self, aborted_view: ViewInfo, timeouts: &Timeouts, log: &Log<D>, node: &NT) -> SynchronizerStatus<D::Request>
+        where NT: OrderProtocolSendNode<D, PBFT<D>> {
+        let current_view = self.view();
+
+        warn!("{:?} // Aborting the view change to view {:?} as it is not sound. Retrying with the following view",
+            self.node_id, aborted_view.sequence_number());
+
+        self.view_change_history.lock().unwrap()
+            .view_change_concluded(&current_view, &aborted_view, ViewChangeOutcome::AbortedUnsound);
+
+        self.collects.lock().unwrap().clear();
+        self.finalize_state.borrow_mut().take();
+
+        // Skip the view we failed to change to, so our STOP is for the view that follows it.
+        // It was never finalized, so we stay in our current view (the same as the consensus)
+        self.tbo.lock().unwrap().skip_stopping_view();
+
+        if let SynchronizerAccessory::Replica(rep) = &self.accessory {
+            rep.view_change_failed();
+        }
+
+        self.set_phase(ProtoPhase::Init);
+
+        self.begin_view_change(None, node, timeouts, log);
+
+        SynchronizerStatus::Running
+    }
+
     /// Advances the state of the view change state machine.
-    //
-    // TODO: retransmit STOP msgs
     pub fn process_message<NT>(
         &self,
         s_message: ShareableMessage<PBFTMessage<D::Request>>,
@@ -703,6 +1441,8 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                self.phase.get(),
                s_message.header().from());
 
+        self.abandon_superseded_view_change(&s_message, &**node);
+
         match self.phase.get() {
             ProtoPhase::Init => {
                 let (header, message) = (s_message.header(), s_message.message().view_change());
@@ -749,7 +1489,8 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
 
                 let msg_seq = message.sequence_number();
                 let current_view = self.view();
-                let next_seq = current_view.sequence_number().next();
+                let stopping_view = self.stopping_view();
+                let next_seq = stopping_view.sequence_number();
 
                 let i = match message.kind() {
                     ViewChangeMessageKind::Stop(_) | ViewChangeMessageKind::StopQuorumJoin(_) if msg_seq != next_seq => {
@@ -815,55 +1556,52 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
 
                 self.stopped.borrow_mut().insert(header.from().into(), stopped);
 
-                // NOTE: we only take this branch of the code before
-                // we have sent our own STOP message
-                if let ProtoPhase::Stopping(_i) = self.phase.get() {
-                    return if i > current_view.params().f() {
+                match stop_progress(self.phase.get(), i, &current_view, &stopping_view, node.id()) {
+                    StopProgress::Waiting(i) => {
+                        self.set_phase(ProtoPhase::Stopping(i));
+
+                        SynchronizerStatus::Nil
+                    }
+                    StopProgress::Join => {
                         self.begin_view_change(None, &**node, timeouts, log);
 
                         SynchronizerStatus::Running
-                    } else {
-                        self.phase.replace(ProtoPhase::Stopping(i));
-
-                        SynchronizerStatus::Nil
-                    };
-                }
+                    }
+                    StopProgress::Stopped(i) => {
+                        self.set_phase(ProtoPhase::Stopping2(i));
 
-                if i >= current_view.params().quorum() {
-                    let next_view = current_view.next_view();
+                        SynchronizerStatus::Running
+                    }
+                    StopProgress::Install(phase) => {
+                        let next_view = stopping_view;
 
-                    let previous_view = current_view.clone();
+                        let previous_view = current_view.clone();
 
-                    //We have received the necessary amount of stopping requests
-                    //To now that we should move to the next view
+                        //We have received the necessary amount of stopping requests
+                        //To now that we should move to the next view
 
-                    let next_leader = next_view.leader();
+                        warn!("{:?} // Stopping quorum reached, moving to next view {:?}. ", node.id(), next_view);
 
-                    warn!("{:?} // Stopping quorum reached, moving to next view {:?}. ", node.id(), next_view);
+                        self.install_next_view(next_view);
 
-                    self.install_next_view(next_view);
+                        match &self.accessory {
+                            SynchronizerAccessory::Replica(rep) => {
+                                rep.handle_stopping_quorum(self, previous_view, consensus,
+                                                           log, rq_pre_processor, timeouts, &**node)
+                            }
+                            SynchronizerAccessory::Follower(_) => {}
+                        }
 
-                    match &self.accessory {
-                        SynchronizerAccessory::Replica(rep) => {
-                            rep.handle_stopping_quorum(self, previous_view, consensus,
-                                                       log, rq_pre_processor, timeouts, &**node)
+                        if let ProtoPhase::StoppingData(_) = phase {
+                            //Move to the stopping data phase as we are the new leader
+                            warn!("{:?} // I am the new leader, moving to the stopping data phase.", node.id());
                         }
-                        SynchronizerAccessory::Follower(_) => {}
-                    }
 
-                    if next_leader == node.id() {
-                        warn!("{:?} // I am the new leader, moving to the stopping data phase.", node.id());
+                        self.set_phase(phase);
 
-                        //Move to the stopping data phase as we are the new leader
-                        self.phase.replace(ProtoPhase::StoppingData(0));
-                    } else {
-                        self.phase.replace(ProtoPhase::Syncing);
+                        SynchronizerStatus::Running
                     }
-                } else {
-                    self.phase.replace(ProtoPhase::Stopping2(i));
                 }
-
-                SynchronizerStatus::Running
             }
             ProtoPhase::ViewStopping(received) | ProtoPhase::ViewStopping2(received) => {
                 let (header, message) = (s_message.header(), s_message.message().view_change());
@@ -883,17 +1621,19 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                         return stop_status!(received, &current_view);
                     }
                     ViewChangeMessageKind::Stop(requests) => {
-                        let mut guard = self.tbo.lock().unwrap();
-
                         warn!("{:?} // Received stop message while in View Stopping state. Since STOP takes precendence over the quorum updating, we will now change to stopping phase ", node.id());
 
-                        guard.queue_stop(s_message);
+                        {
+                            let mut guard = self.tbo.lock().unwrap();
 
-                        self.phase.replace(ProtoPhase::Stopping(0));
+                            guard.queue_stop(s_message);
+                        }
+
+                        self.set_phase(ProtoPhase::Stopping(0));
 
                         return SynchronizerStatus::Running;
                     }
-                    ViewChangeMessageKind::StopQuorumJoin(node) => (received + 1, *node),
+                    ViewChangeMessageKind::StopQuorumJoin(node) => (received, *node),
                     ViewChangeMessageKind::StopData(_) => {
                         match &self.accessory {
                             SynchronizerAccessory::Follower(_) => {
@@ -926,34 +1666,56 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                     }
                 };
 
-                {
+                // Only votes from nodes which have not yet voted count towards the quorum
+                let received = {
                     let mut write_guard = self.currently_adding.borrow_mut();
 
-                    let received_votes = write_guard.entry(node_id).or_insert_with(BTreeSet::new);
+                    match register_join_vote(&mut write_guard, header.from(), node_id) {
+                        JoinVote::New => {
+                            debug!("{:?} // Received stop quorum join message from {:?} with node {:?} ", node.id(), header.from(), node_id);
+
+                            received + 1
+                        }
+                        JoinVote::Duplicate => {
+                            debug!("{:?} // Received duplicate stop quorum join message from {:?} with node {:?} ", node.id(), header.from(), node_id);
+
+                            // drop attempts to vote twice
+                            return stop_status!(received, &current_view);
+                        }
+                        JoinVote::Conflicting(voted) => {
+                            warn!("{:?} // Received stop quorum join message from {:?} with node {:?}, but it had already voted for node {:?}. Ignoring",
+                                node.id(), header.from(), node_id, voted);
 
-                    if received_votes.insert(header.from()) {
-                        debug!("{:?} // Received stop quorum join message from {:?} with node {:?} ", node.id(), header.from(), node_id);
-                    } else {
-                        debug!("{:?} // Received duplicate stop quorum join message from {:?} with node {:?} ", node.id(), header.from(), node_id);
+                            return stop_status!(received, &current_view);
+                        }
                     }
-                }
+                };
 
                 // We don't need to actually receive the reconfiguration confirmation to add a node to the quorum, if the quorum is already reached
                 //TODO: Is this the correct procedure?
-                self.phase.replace(ProtoPhase::ViewStopping(received));
+                self.set_phase(ProtoPhase::ViewStopping(received));
 
                 if received >= current_view.params().quorum() {
-                    let mut votes: Vec<_> = self.currently_adding.borrow().iter().map(|(node, voters)| (*node, voters.len())).collect();
+                    let outcome = tally_join_votes(&self.currently_adding.borrow(), received,
+                                                   current_view.params().quorum(), current_view.params().n());
 
-                    votes.sort_by(|(node, votes), (node_2, votes_2)| votes_2.cmp(votes));
+                    match outcome {
+                        JoinTally::Elected { candidate: node_to_add, votes } => {
+                            self.currently_adding_node.replace(Some(node_to_add));
 
-                    if let Some(vote_count) = votes.first() {
-                        if vote_count.1 >= current_view.params().quorum() {
-                            self.currently_adding_node.replace(Some(node_id));
+                            let next_view = match self.next_view_with_new_node(&current_view, node_to_add) {
+                                Ok(next_view) => next_view,
+                                Err(err) => {
+                                    error!("{:?} // Refusing to add node {:?} to the quorum as the membership change is unsafe: {:?}",
+                                        node.id(), node_to_add, err);
 
-                            let node_to_add = vote_count.0;
+                                    self.currently_adding.borrow_mut().remove(&node_to_add);
+                                    self.leave_join_path();
+                                    self.set_phase(ProtoPhase::Init);
 
-                            let next_view = current_view.next_view_with_new_node(node_to_add);
+                                    return SynchronizerStatus::Nil;
+                                }
+                            };
 
                             let previous_view = current_view.clone();
 
@@ -962,7 +1724,7 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
 
                             let next_leader = next_view.leader();
 
-                            warn!("{:?} // Stopping quorum reached with {} votes for node {:?} moving to next view {:?}. ", node.id(), vote_count.1, node_to_add, next_view);
+                            warn!("{:?} // Stopping quorum reached with {} votes for node {:?} moving to next view {:?}. ", node.id(), votes, node_to_add, next_view);
 
                             self.install_next_view(next_view);
 
@@ -978,21 +1740,29 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                                 warn!("{:?} // I am the new leader, moving to the stopping data phase.", node.id());
 
                                 //Move to the stopping data phase as we are the new leader
-                                self.phase.replace(ProtoPhase::StoppingData(0));
+                                self.set_phase(ProtoPhase::StoppingData(0));
                             } else {
-                                self.phase.replace(ProtoPhase::Syncing);
+                                self.set_phase(ProtoPhase::Syncing);
                             }
-                        } else if received >= current_view.params().n() {
-                            error!("We have received view stopping messages from all nodes in the network and yet we don't have quorum {} votes for any node. {:?}",
-                                   current_view.params().quorum(), votes);
+                        }
+                        JoinTally::Split => {
+                            error!("{:?} // We have received view stopping messages from all nodes in the network and yet we don't have quorum {} votes for any node. {:?}",
+                                   node.id(), current_view.params().quorum(), self.currently_adding.borrow());
 
-                            todo!("")
-                        } else {
-                            warn!("{:?} // Stopping quorum reached, but not enough votes to add node {:?}. ", node.id(), vote_count.0);
+                            // Give up on this quorum join attempt, the view change timeouts
+                            // will drive the quorum alteration again
+                            self.currently_adding.borrow_mut().clear();
+                            self.leave_join_path();
+                            self.set_phase(ProtoPhase::Init);
+
+                            return SynchronizerStatus::Nil;
+                        }
+                        JoinTally::Pending => {
+                            warn!("{:?} // Stopping quorum reached, but not enough votes to add node {:?}. ", node.id(), node_id);
                         }
                     }
                 } else {
-                    self.phase.replace(ProtoPhase::ViewStopping2(received));
+                    self.set_phase(ProtoPhase::ViewStopping2(received));
                 }
 
                 SynchronizerStatus::Running
@@ -1010,7 +1780,10 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                         //Obtain the view seq no of the message
                         let msg_seq = message.sequence_number();
 
-                        let next_view = self.next_view().expect("We can't be here without having installed the next view?");
+                        let next_view = match self.next_view_or_reset() {
+                            Some(next_view) => next_view,
+                            None => return SynchronizerStatus::Nil,
+                        };
                         let seq = next_view.sequence_number();
 
                         // reject STOP-DATA messages if we are not the leader
@@ -1096,8 +1869,8 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
 
                         collects_guard.insert(header.from().into(), unwrapped_msg);
 
-                        if i < next_view.params().quorum() {
-                            self.phase.replace(ProtoPhase::StoppingData(i));
+                        if !has_stop_data_quorum(i, &next_view) {
+                            self.set_phase(ProtoPhase::StoppingData(i));
 
                             return SynchronizerStatus::Running;
                         } else {
@@ -1133,18 +1906,13 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                             let sound = sound(&next_view, &normalized_collects);
 
                             if !sound.test() {
-                                //FIXME: BFT-SMaRt doesn't do anything if `sound`
-                                // evaluates to false; do we keep the same behavior,
-                                // and wait for a new time out? but then, no other
-                                // consensus messages have been processed... this
-                                // may be a point of contention on the lib!
+                                error!("{:?} // The view change is not sound.", node.id());
 
-                                error!("{:?} // The view change is not sound. Cancelling.", node.id());
-                                /*
-                                collects_guard.clear();
+                                if aborts_view_change(self.unsound_view_change_policy, &sound) {
+                                    drop(collects_guard);
 
-                                return SynchronizerStatus::Running;
-                                */
+                                    return self.abort_unsound_view_change(next_view, timeouts, log, &**node);
+                                }
                             }
 
                             let p = rq_pre_processor.collect_all_pending_rqs();
@@ -1161,7 +1929,7 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
 
                                 let (message, buf) = message.into_inner();
 
-                                let mut prng_state = prng::State::new();
+                                let nonce = self.nonce_source.next_nonce();
 
                                 //Create the pre-prepare message that contains the requests
                                 //Collected during the STOPPING DATA phase
@@ -1169,7 +1937,7 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                                     next_view.leader(),
                                     next_view.leader(),
                                     buf,
-                                    prng_state.next_state(),
+                                    nonce,
                                     Some(digest),
                                     Some(&*node_sign),
                                 ).into_inner();
@@ -1226,7 +1994,10 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
             }
             ProtoPhase::Syncing => {
                 let msg_seq = s_message.sequence_number();
-                let next_view = self.next_view().expect("We should have a next view in this situation");
+                let next_view = match self.next_view_or_reset() {
+                    Some(next_view) => next_view,
+                    None => return SynchronizerStatus::Nil,
+                };
                 let seq = next_view.sequence_number();
 
                 // reject SYNC messages if these were not sent by the leader
@@ -1291,7 +2062,15 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
 
                 // leader has already performed this computation in the
                 // STOP-DATA phase of Mod-SMaRt
-                let signed: Vec<_> = signed_collects::<D, _>(&**node, collects);
+                let signed: Vec<_> = match signed_collects::<D, _>(&**node, collects, seq) {
+                    Some(signed) => signed,
+                    None => {
+                        error!("{:?} // The leader {:?} sent collects which do not pertain to the view change to {:?}. Rejecting the SYNC message",
+                            node.id(), next_view.leader(), seq);
+
+                        return SynchronizerStatus::Running;
+                    }
+                };
 
                 let proof = highest_proof::<D, _, _>(&next_view, &**node, signed.iter());
 
@@ -1306,14 +2085,11 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                 let sound = sound(&next_view, &normalized_collects);
 
                 if !sound.test() {
-                    error!("{:?} // The view change is not sound. Cancelling.", node.id());
-
-                    //FIXME: BFT-SMaRt doesn't do anything if `sound`
-                    // evaluates to false; do we keep the same behavior,
-                    // and wait for a new time out? but then, no other
-                    // consensus messages have been processed... this
-                    // may be a point of contention on the lib!
-                    //return SynchronizerStatus::Running;
+                    error!("{:?} // The view change is not sound.", node.id());
+
+                    if aborts_view_change(self.unsound_view_change_policy, &sound) {
+                        return self.abort_unsound_view_change(next_view, timeouts, log, &**node);
+                    }
                 }
 
                 let state = FinalizeState {
@@ -1437,14 +2213,37 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
             }
         }
 
-        //TODO: Timeout waiting for the sync/stopping data. This is because
-        // We actually might try to enter while the protocol is running a different view change,
-        // so the view change to integrate us into the quorum might be delayed
+        // We might try to enter while the protocol is running a different view change,
+        // so the view change to integrate us into the quorum might be delayed.
+        // We only give up on our attempt once the join timeout has passed
+        self.expire_join_attempt();
+
+        // Report the failure of our last attempt, even if we gave up on it while handling timeouts
+        if self.quorum_join.borrow_mut().take_expired() {
+            return ReconfigurationAttemptResult::Failed;
+        }
+
+        if self.quorum_join.borrow().is_entering() {
+            return ReconfigurationAttemptResult::InProgress;
+        }
 
         // Simulate that we were accepted into the quorum
-        let view = current_view.next_view_with_new_node(node.id());
+        let view = match self.next_view_with_new_node(&current_view, node.id()) {
+            Ok(view) => view,
+            Err(err) => {
+                error!("{:?} // Unable to join the quorum as the membership change is unsafe: {:?}", node.id(), err);
+
+                return ReconfigurationAttemptResult::Failed;
+            }
+        };
+
+        if self.quorum_join.borrow_mut().begin(Instant::now() + self.join_timeout) {
+            // We were previously a part of the quorum, so our view and log are stale.
+            // We must first catch up to the state of the quorum with the CST protocol
+            // before we can be integrated, to make sure we don't propagate stale proofs
+            info!("{:?} // Rejoining the quorum after having been removed from it, we will have to catch up with the quorum first", node.id());
+        }
 
-        self.entering_quorum.replace(true);
         self.currently_adding_node.replace(Some(self.node_id));
 
         self.install_next_view(view.clone());
@@ -1452,14 +2251,37 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
         if view.leader() == node.id() {
             // If we are the leader of the next view, then we should move to the stopping data phase and wait
             // For the rest of the nodes to send us the information
-            self.phase.replace(ProtoPhase::StoppingData(0));
+            self.set_phase(ProtoPhase::StoppingData(0));
         } else {
-            self.phase.replace(ProtoPhase::Syncing);
+            self.set_phase(ProtoPhase::Syncing);
         }
 
         return ReconfigurationAttemptResult::InProgress;
     }
 
+    /// Give up on our attempt to join the quorum if the quorum has not integrated us
+    /// within the join timeout, returning to the view we were in before the attempt
+    /// so the reconfiguration protocol can retry.
+    /// This is checked whenever the timeouts layer notifies us, and the failure is reported
+    /// to the reconfiguration protocol on its next call to [Self::attempt_join_quorum].
+    ///
+    /// Returns whether the attempt was given up on
+    pub fn expire_join_attempt(&self) -> bool {
+        if !self.quorum_join.borrow_mut().expire(Instant::now()) {
+            return false;
+        }
+
+        warn!("{:?} // The quorum has not integrated us within {:?}, giving up on joining it", self.node_id, self.join_timeout);
+
+        self.currently_adding_node.replace(None);
+        self.collects.lock().unwrap().clear();
+        self.tbo.lock().unwrap().clear_next_view();
+
+        self.set_phase(ProtoPhase::Init);
+
+        true
+    }
+
     /// Trigger a view change locally
     pub fn begin_quorum_view_change<NT>(&self,
                                         join_cert: Option<NodeId>,
@@ -1470,16 +2292,20 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
     {
         debug!("Beginning quorum view change with certificate {} at phase {:?}",  join_cert.is_some(), self.phase.get());
 
+        if let Some(joining_node) = &join_cert {
+            self.view_change_history.lock().unwrap().view_change_started(ViewChangeTrigger::QuorumJoin(*joining_node));
+        }
+
         match (self.phase.get(), &join_cert) {
             (ProtoPhase::ViewStopping(i), None) => {
                 // We have not received a join certificate message from the node, so we still will
-                self.phase.replace(ProtoPhase::ViewStopping(i + 1));
+                self.set_phase(ProtoPhase::ViewStopping(i + 1));
             }
             (ProtoPhase::ViewStopping(i), _) => {
                 // We have ourselves received a join certificate message from the node, so we will now
                 // Have to broadcast a STOP Quorum Join message. As such, we don't want to increment
                 // The amount of messages received (since we have not actually received any messages)
-                self.phase.replace(ProtoPhase::ViewStopping2(i));
+                self.set_phase(ProtoPhase::ViewStopping2(i));
             }
             (ProtoPhase::StoppingData(_), _) | (ProtoPhase::Syncing, _) | (ProtoPhase::ViewStopping2(_), _) => {
                 // we have already started a view change protocol
@@ -1493,7 +2319,7 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                 self.currently_adding_node.replace(None);
                 self.currently_adding.borrow_mut().clear();
 
-                self.phase.replace(ProtoPhase::ViewStopping2(0));
+                self.set_phase(ProtoPhase::ViewStopping2(0));
             }
         }
 
@@ -1523,6 +2349,14 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
         _log: &Log<D>,
     ) where NT: OrderProtocolSendNode<D, PBFT<D>>,
     {
+        let trigger = if timed_out.is_some() {
+            ViewChangeTrigger::RequestTimeout
+        } else {
+            ViewChangeTrigger::ReceivedStops
+        };
+
+        self.view_change_history.lock().unwrap().view_change_started(trigger);
+
         match (self.phase.get(), &timed_out) {
             // we have received STOP messages from peer nodes,
             // but haven't sent our own STOP, yet; (And in the case of followers we will never send it)
@@ -1531,25 +2365,25 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
             // so we need to update our phase with a new received message
             (ProtoPhase::Stopping(i), None) => {
                 // We update here to Stopping2 as we will send our own message right after this
-                self.phase.replace(ProtoPhase::Stopping2(i + 1));
+                self.set_phase(ProtoPhase::Stopping2(i + 1));
             }
             //When the timeout is not null, this means it was called from timed out client requests
             //And therefore we don't increase the received message count, just update the phase to Stopping2
             (ProtoPhase::Stopping(i), _) => {
                 // We have begun our own stopping protocol, so we will update our phase to Stopping2
-                self.phase.replace(ProtoPhase::Stopping2(i));
+                self.set_phase(ProtoPhase::Stopping2(i));
             }
             (ProtoPhase::ViewStopping(_), None) | (ProtoPhase::ViewStopping2(_), None) => {
                 // We are currently in the quorum alteration view change protocol. Since we have received
                 // A timeout and we know that timeouts take precedence over quorum alteration, we will
                 // Stop this current view stopping protocol and start the normal view change protocol
-                self.phase.replace(ProtoPhase::Stopping(1));
+                self.set_phase(ProtoPhase::Stopping(1));
             }
             (ProtoPhase::ViewStopping(_), _) | (ProtoPhase::ViewStopping2(_), _) => {
                 // We are currently in the quorum alteration view change protocol. Since we have received
                 // A timeout and we know that timeouts take precedence over quorum alteration, we will
                 // Stop this current view stopping protocol and start the normal view change protocol
-                self.phase.replace(ProtoPhase::Stopping2(0));
+                self.set_phase(ProtoPhase::Stopping2(0));
             }
             (ProtoPhase::StoppingData(_), _) | (ProtoPhase::Syncing, _) | (ProtoPhase::Stopping2(_), _) => {
                 // we have already started a view change protocol or we have already sent our STOP message
@@ -1564,18 +2398,22 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
                 // clear state from previous views
                 self.stopped.borrow_mut().clear();
                 self.collects.lock().unwrap().clear();
-                self.currently_adding_node.replace(None);
                 self.currently_adding.borrow_mut().clear();
-                self.entering_quorum.replace(false);
+                self.leave_join_path();
 
                 //Set the new state to be stopping
-                self.phase.replace(ProtoPhase::Stopping2(0));
+                self.set_phase(ProtoPhase::Stopping2(0));
             }
         };
 
         match &self.accessory {
             SynchronizerAccessory::Follower(_) => {}
             SynchronizerAccessory::Replica(rep) => {
+                let trigger = timed_out.as_ref()
+                    .map(|requests| requests.iter().map(ClientRqInfo::from).collect());
+
+                *self.last_view_change_trigger.lock().unwrap() = trigger;
+
                 rep.handle_begin_view_change(self, timeouts, node, timed_out)
             }
         }
@@ -1593,9 +2431,7 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
     {
         let last_executed_cid = proof.as_ref().map(|p| p.sequence_number()).unwrap_or(SeqNo::ZERO);
 
-        //If we are more than one operation behind the most recent consensus id,
-        //Then we must run a consensus state transfer
-        if u32::from(log.decision_log().last_execution().unwrap_or(SeqNo::ZERO)) + 1 < u32::from(last_executed_cid) {
+        if must_run_cst(&mut self.quorum_join.borrow_mut(), log.decision_log().last_execution(), last_executed_cid) {
             return FinalizeStatus::RunCst(state);
         }
 
@@ -1637,6 +2473,15 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
 
         let view = self.view();
 
+        if let Some(previous_view) = self.previous_view() {
+            self.view_change_history.lock().unwrap().view_change_concluded(&previous_view, &view, ViewChangeOutcome::Installed);
+
+            self.track_removal(&previous_view, &view);
+        }
+
+        // If we are a part of the quorum, any attempt of ours to join it is over
+        self.quorum_join.borrow_mut().view_installed(self.node_id, &view);
+
         warn!("{:?} // Finalizing view change to view {:?} and consensus ID {:?}, Adding node? {:?}", node.id(), view, curr_cid, self.currently_adding_node.get());
 
         let (header, message) = proposed.into_inner();
@@ -1667,11 +2512,15 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
             None
         };
 
+        if let SynchronizerAccessory::Replica(rep) = &self.accessory {
+            rep.view_change_succeeded();
+        }
+
         // finalize view change by broadcasting a PREPARE msg
         let consensus_result = consensus.finalize_view_change((header, message), &view, self, timeouts, log, node).expect("Failed to finalize view change in consensus");
 
         // Update proto phase
-        self.phase.replace(ProtoPhase::Init);
+        self.set_phase(ProtoPhase::Init);
 
         if self.currently_adding_node.get().is_some() {
             let node = self.currently_adding_node.replace(None);
@@ -1728,19 +2577,59 @@ impl<D> Synchronizer<D> where D: ApplicationData + 'static,
         }
     }
 
+    /// Forward the timed out requests that are still pending, if the forwarding rate allows it.
+    /// Should be called periodically, so the requests that were held back are eventually forwarded
+    pub fn flush_forwarded_requests<NT>(&self, node: &NT)
+        where NT: OrderProtocolSendNode<D, PBFT<D>> {
+        match &self.accessory {
+            SynchronizerAccessory::Follower(_) => {}
+            SynchronizerAccessory::Replica(rep) => {
+                rep.flush_forwarded_requests(self, node);
+            }
+        }
+    }
+
     /// Client requests have timed out. We must now send a stop message containing all of the
     /// Requests that have timed out
-    pub fn client_requests_timed_out(
+    pub fn client_requests_timed_out<NT>(
         &self,
-        my_id: NodeId,
+        node: &NT,
+        timeouts: &Timeouts,
         seq: &Vec<RqTimeout>,
-    ) -> SynchronizerStatus<D::Request> {
+    ) -> SynchronizerStatus<D::Request>
+        where NT: OrderProtocolSendNode<D, PBFT<D>> {
         match &self.accessory {
             SynchronizerAccessory::Follower(_) => {
                 SynchronizerStatus::Nil
             }
             SynchronizerAccessory::Replica(rep) => {
-                rep.client_requests_timed_out(self, my_id, seq)
+                let phase = self.phase.get();
+
+                // The retransmission timer is not a client request, so it never makes it into the result
+                let status = rep.client_requests_timed_out(self, node.id(), seq);
+
+                match stop_retransmission_action(phase, rep.is_stop_retransmit_due(seq)) {
+                    StopRetransmission::Retransmit => {
+                        // The other replicas have not joined the view change before our retransmission
+                        // timer fired, so our STOP (or STOP-QUORUM-JOIN) might have been lost on the way
+                        rep.retransmit_stop(self, node, timeouts);
+                    }
+                    StopRetransmission::Wait => {}
+                    StopRetransmission::Stop => {
+                        // We have advanced past the stopping phase (or the view change is over)
+                        rep.stop_retransmitting(timeouts);
+                    }
+                }
+
+                if let (ProtoPhase::StoppingData(_) | ProtoPhase::Syncing, SynchronizerStatus::RequestsTimedOut { .. }) = (phase, &status) {
+                    // The requests were watched again once the stopping quorum was reached,
+                    // so the new leader has not completed the view change in time
+                    if let Some(next_view) = self.next_view() {
+                        rep.view_change_stalled(next_view.sequence_number());
+                    }
+                }
+
+                status
             }
         }
     }
@@ -1794,6 +2683,62 @@ pub enum SynchronizerAccessory<D: ApplicationData> {
 //
 ////////////////////////////////////////////////////////////////////////////////
 
+/// The result of registering a STOP-QUORUM-JOIN vote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JoinVote {
+    /// The first vote from this node, which counts towards the quorum
+    New,
+    /// The node had already voted for this same candidate.
+    /// The vote is dropped and does not count towards the quorum
+    Duplicate,
+    /// The node had already voted for the given (different) candidate.
+    /// Only the first vote is kept, so this one is dropped as well
+    Conflicting(NodeId),
+}
+
+/// Register the vote of `voter` to add `candidate` to the quorum.
+/// Each node can only vote once per view change, so any subsequent votes
+/// are never counted (whether or not they are for the same candidate)
+fn register_join_vote(votes: &mut BTreeMap<NodeId, BTreeSet<NodeId>>, voter: NodeId, candidate: NodeId) -> JoinVote {
+    let previous_vote = votes.iter()
+        .find(|(_, voters)| voters.contains(&voter))
+        .map(|(voted, _)| *voted);
+
+    match previous_vote {
+        Some(voted) if voted == candidate => JoinVote::Duplicate,
+        Some(voted) => JoinVote::Conflicting(voted),
+        None => {
+            votes.entry(candidate).or_insert_with(BTreeSet::new).insert(voter);
+
+            JoinVote::New
+        }
+    }
+}
+
+/// The outcome of the STOP-QUORUM-JOIN votes received so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JoinTally {
+    /// The candidate has received a quorum of votes, so it should be added to the quorum
+    Elected { candidate: NodeId, votes: usize },
+    /// Every node has voted and yet no candidate has reached a quorum of votes
+    Split,
+    /// No candidate has reached a quorum of votes yet, but not every node has voted
+    Pending,
+}
+
+/// Tally the votes of `received` nodes to add a candidate to the quorum
+fn tally_join_votes(votes: &BTreeMap<NodeId, BTreeSet<NodeId>>, received: usize, quorum: usize, n: usize) -> JoinTally {
+    let leading = votes.iter()
+        .map(|(candidate, voters)| (*candidate, voters.len()))
+        .max_by_key(|(_, votes)| *votes);
+
+    match leading {
+        Some((candidate, votes)) if votes >= quorum => JoinTally::Elected { candidate, votes },
+        _ if received >= n => JoinTally::Split,
+        _ => JoinTally::Pending,
+    }
+}
+
 fn sound<'a, O>(curr_view: &ViewInfo, normalized_collects: &[Option<&'a CollectData<O>>]) -> Sound {
     // collect timestamps and values
     let mut seq_numbers = collections::hash_set();
@@ -1995,20 +2940,89 @@ fn normalized_collects<'a, O: 'a>(
     })
 }
 
+/// Check that a collect is a STOP-DATA message sent for the view change to `view_seq`,
+/// so that a leader cannot back its SYNC with collects of a stale or forged view change
+fn collect_pertains_to_view<O>(collect: &PBFTMessage<O>, view_seq: SeqNo) -> bool {
+    match collect {
+        PBFTMessage::ViewChange(view_change) => {
+            matches!(view_change.kind(), ViewChangeMessageKind::StopData(_))
+                && view_change.sequence_number() == view_seq
+        }
+        _ => false,
+    }
+}
+
+/// Obtain the collects with a valid signature.
+/// Returns `None` if any of the collects does not pertain to the view change to `view_seq`,
+/// in which case the SYNC message that carried them must be rejected
 fn signed_collects<D, NT>(
     node: &NT,
     collects: Vec<StoredMessage<PBFTMessage<D::Request>>>,
-) -> Vec<StoredMessage<PBFTMessage<D::Request>>>
+    view_seq: SeqNo,
+) -> Option<Vec<StoredMessage<PBFTMessage<D::Request>>>>
     where D: ApplicationData + 'static,
           NT: OrderProtocolSendNode<D, PBFT<D>>
 {
-    collects
+    if !collects.iter().all(|stored| collect_pertains_to_view(stored.message(), view_seq)) {
+        return None;
+    }
+
+    let mut keys = KeyCache::new();
+
+    Some(collects
         .into_iter()
-        .filter(|stored| validate_signature::<D, _, _>(node, &stored))
-        .collect()
+        .filter(|stored| validate_signature::<D, _, _>(node, &mut keys, &stored))
+        .collect())
+}
+
+/// The distinct senders of the collects with valid signatures, if all of the collects
+/// pertain to the view change to `view_seq`.
+/// Unlike [signed_collects], this does not take ownership of the collects
+fn signed_collect_senders<D, NT>(
+    node: &NT,
+    collects: &[StoredMessage<PBFTMessage<D::Request>>],
+    view_seq: SeqNo,
+) -> Option<BTreeSet<NodeId>>
+    where D: ApplicationData + 'static,
+          NT: OrderProtocolSendNode<D, PBFT<D>>
+{
+    if !collects.iter().all(|stored| collect_pertains_to_view(stored.message(), view_seq)) {
+        return None;
+    }
+
+    let mut keys = KeyCache::new();
+
+    Some(collects
+        .iter()
+        .filter(|stored| validate_signature::<D, _, _>(node, &mut keys, stored))
+        .map(|stored| stored.header().from())
+        .collect())
+}
+
+/// Caches the public keys of the senders of the messages we are validating,
+/// so that validating a quorum of collects (each with a quorum of prepares and commits)
+/// only looks up each sender's key once, instead of once per message
+struct KeyCache<K> {
+    keys: BTreeMap<NodeId, Option<K>>,
+}
+
+impl<K> KeyCache<K> {
+    fn new() -> Self {
+        Self {
+            keys: Default::default(),
+        }
+    }
+
+    /// Get the key of the given node, fetching it with `fetch` if we haven't done so yet
+    fn get_or_fetch<F>(&mut self, node: NodeId, fetch: F) -> Option<&K>
+        where F: FnOnce(&NodeId) -> Option<K> {
+        self.keys.entry(node)
+            .or_insert_with(|| fetch(&node))
+            .as_ref()
+    }
 }
 
-fn validate_signature<'a, D, M, NT>(node: &'a NT, stored: &'a StoredMessage<M>) -> bool
+fn validate_signature<'a, D, M, NT>(node: &'a NT, keys: &mut KeyCache<PublicKey>, stored: &'a StoredMessage<M>) -> bool
     where
         D: ApplicationData + 'static,
         NT: OrderProtocolSendNode<D, PBFT<D>>
@@ -2025,7 +3039,7 @@ fn validate_signature<'a, D, M, NT>(node: &'a NT, stored: &'a StoredMessage<M>)
 
     // check if we even have the public key of the node that claims
     // to have sent this particular message
-    let key = match node.network_info_provider().get_public_key(&stored.header().from()) {
+    let key = match keys.get_or_fetch(stored.header().from(), |from| node.network_info_provider().get_public_key(from)) {
         Some(k) => k,
         None => {
             error!("{:?} // Failed to get public key for node {:?}", node.id(), stored.header().from());
@@ -2034,7 +3048,7 @@ fn validate_signature<'a, D, M, NT>(node: &'a NT, stored: &'a StoredMessage<M>)
         }
     };
 
-    wm.is_valid(Some(&key), false)
+    wm.is_valid(Some(key), false)
 }
 
 fn highest_proof<'a, D, I, NT>(
@@ -2047,12 +3061,14 @@ fn highest_proof<'a, D, I, NT>(
         I: Iterator<Item=&'a StoredMessage<PBFTMessage<D::Request>>>,
         NT: OrderProtocolSendNode<D, PBFT<D>>
 {
+    let mut keys = KeyCache::new();
+
     collect_data(collects)
         // fetch proofs
         .filter_map(|collect| collect.last_proof())
         // check if COMMIT msgs are signed, and all have the same digest
         //
-        .filter(move |proof| {
+        .filter(|proof| {
             let digest = proof.batch_digest();
 
             let commits_valid = proof
@@ -2064,8 +3080,8 @@ fn highest_proof<'a, D, I, NT>(
                         //If he does not have the digest, then it is not valid
                         .unwrap_or(false)
                 })
-                .filter(move |&stored|
-                    { validate_signature::<D, _, _>(node, stored) })
+                .filter(|&stored|
+                    { validate_signature::<D, _, _>(node, &mut keys, stored) })
                 .count() >= view.params().quorum();
 
             let prepares_valid = proof
@@ -2078,8 +3094,8 @@ fn highest_proof<'a, D, I, NT>(
                         //If he does not have the digest, then it is not valid
                         .unwrap_or(false)
                 })
-                .filter(move |&stored|
-                    { validate_signature::<D, _, _>(node, stored) })
+                .filter(|&stored|
+                    { validate_signature::<D, _, _>(node, &mut keys, stored) })
                 .count() >= view.params().quorum();
 
             debug!("{:?} // Proof {:?} is valid? commits valid: {:?} &&  prepares_valid: {:?}",
@@ -2105,4 +3121,468 @@ impl<O> Debug for SynchronizerPollStatus<O> {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod sync_tests {
+    use std::collections::BTreeMap;
+
+    use atlas_common::node_id::NodeId;
+
+    use crate::bft::log::decisions::{IncompleteProof, PrepareSet};
+
+    use super::*;
+
+    #[test]
+    fn test_duplicate_join_votes_count_once() {
+        let mut votes = BTreeMap::new();
+
+        let candidate = NodeId(4);
+
+        assert_eq!(register_join_vote(&mut votes, NodeId(0), candidate), JoinVote::New);
+        assert_eq!(register_join_vote(&mut votes, NodeId(0), candidate), JoinVote::Duplicate);
+        assert_eq!(register_join_vote(&mut votes, NodeId(0), NodeId(5)), JoinVote::Conflicting(candidate));
+        assert_eq!(register_join_vote(&mut votes, NodeId(1), candidate), JoinVote::New);
+
+        assert_eq!(votes.get(&candidate).map(BTreeSet::len), Some(2));
+        assert!(votes.get(&NodeId(5)).is_none());
+    }
+
+    #[test]
+    fn test_far_future_view_is_rejected() {
+        const MAX_LOOK_AHEAD: usize = 4;
+
+        let next_view = SeqNo::ZERO.next();
+
+        let mut far_future_view = next_view;
+
+        for _ in 0..MAX_LOOK_AHEAD {
+            far_future_view = far_future_view.next();
+        }
+
+        assert!(is_within_view_window(next_view, next_view, MAX_LOOK_AHEAD));
+        assert!(is_within_view_window(next_view, SeqNo::ZERO, MAX_LOOK_AHEAD));
+        assert!(is_within_view_window(next_view, far_future_view, MAX_LOOK_AHEAD));
+        assert!(!is_within_view_window(next_view, far_future_view.next(), MAX_LOOK_AHEAD));
+        assert!(!is_within_view_window(next_view, SeqNo::from(1000u32), MAX_LOOK_AHEAD));
+    }
+
+    #[test]
+    fn test_back_to_back_leader_failures() {
+        const MAX_LOOK_AHEAD: usize = 4;
+
+        let view_0 = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let mut tbo: TboQueue<()> = TboQueue::new(view_0.clone(), MAX_LOOK_AHEAD);
+
+        let view_1 = tbo.stopping_view();
+        let view_2 = view_1.next_view();
+
+        // We lead neither of the views we change to, so we wait for their SYNC
+        let our_id = *view_0.quorum_members().iter()
+            .find(|id| **id != view_1.leader() && **id != view_2.leader())
+            .unwrap();
+
+        // The leader of view 0 fails, so our requests time out and we send our STOP for view 1
+        let mut phase = ProtoPhase::Stopping2(0);
+
+        let stop = |view: &ViewInfo| ViewChangeMessage::<()>::new(view.sequence_number(), ViewChangeMessageKind::Stop(Vec::new()));
+
+        let mut stops = 0;
+
+        for from in view_1.quorum_members().iter().filter(|id| **id != our_id) {
+            let message = stop(&view_1);
+
+            assert!(matches!(message.kind(), ViewChangeMessageKind::Stop(_)));
+            assert_eq!(message.sequence_number(), tbo.stopping_view().sequence_number(), "STOP from {:?} is for the view we are stopping toward", from);
+
+            stops += 1;
+
+            match stop_progress(phase, stops, &tbo.view(), &tbo.stopping_view(), our_id) {
+                StopProgress::Stopped(i) => phase = ProtoPhase::Stopping2(i),
+                StopProgress::Install(next_phase) => {
+                    tbo.install_next_view(tbo.stopping_view());
+
+                    phase = next_phase;
+                }
+                progress => panic!("We have already sent our STOP, got {:?}", progress),
+            }
+        }
+
+        // A quorum stopped, so we wait for the SYNC of the leader of view 1
+        assert!(matches!(phase, ProtoPhase::Syncing));
+        assert_eq!(tbo.next_view().map(|view| view.sequence_number()), Some(view_1.sequence_number()));
+
+        // The leader of view 1 fails as well, so the rest of the quorum stops for view 2,
+        // and sends its STOP-DATA to the leader of view 2
+        let stop_datas: Vec<(NodeId, PBFTMessage<()>)> = view_2.quorum_members().iter()
+            .filter(|id| **id != our_id)
+            .map(|id| {
+                let incomplete_proof = IncompleteProof::new(SeqNo::ZERO, PrepareSet(Vec::new()), None);
+
+                (*id, PBFTMessage::ViewChange(ViewChangeMessage::new(
+                    view_2.sequence_number(),
+                    ViewChangeMessageKind::StopData(CollectData::new(incomplete_proof, None)),
+                )))
+            })
+            .collect();
+
+        let mut collected = 0;
+
+        for (i, (_, stop_data)) in stop_datas.iter().enumerate() {
+            assert!(collect_pertains_to_view(stop_data, view_2.sequence_number()));
+
+            collected += 1;
+
+            assert_eq!(has_stop_data_quorum(collected, &view_2), i + 1 == stop_datas.len());
+        }
+
+        let senders_of = |collects: &[(NodeId, PBFTMessage<()>)], seq: SeqNo| -> Option<BTreeSet<NodeId>> {
+            if !collects.iter().all(|(_, collect)| collect_pertains_to_view(collect, seq)) {
+                return None;
+            }
+
+            Some(collects.iter().map(|(from, _)| *from).collect())
+        };
+
+        let running = tbo.next_view().cloned().unwrap();
+
+        // A SYNC for view 2 which is not sent by its leader, or does not carry the STOP-DATA of a quorum, is not enough to abandon view 1
+        assert!(matches!(superseding_sync(phase, &running, view_2.sequence_number(), our_id, |seq| senders_of(&stop_datas, seq)),
+            SupersedingSync::Keep));
+        assert!(matches!(superseding_sync(phase, &running, view_2.sequence_number(), view_2.leader(), |seq| senders_of(&stop_datas[..1], seq)),
+            SupersedingSync::Unproven(_)));
+
+        // Neither is the SYNC of view 1's leader
+        assert!(matches!(superseding_sync(phase, &running, view_1.sequence_number(), view_1.leader(), |seq| senders_of(&stop_datas, seq)),
+            SupersedingSync::Keep));
+
+        // The SYNC of the leader of view 2 proves that a quorum has stopped for it, so we abandon view 1
+        match superseding_sync(phase, &running, view_2.sequence_number(), view_2.leader(), |seq| senders_of(&stop_datas, seq)) {
+            SupersedingSync::Abandon(newer_view) => {
+                tbo.install_next_view(newer_view);
+
+                phase = ProtoPhase::Syncing;
+            }
+            other => panic!("The view change to view 1 should have been abandoned, got {:?}", other),
+        }
+
+        assert!(matches!(phase, ProtoPhase::Syncing));
+
+        // Processing the SYNC finalizes the view change to view 2, skipping view 1
+        assert!(tbo.advance());
+
+        assert_eq!(tbo.view().sequence_number(), view_2.sequence_number());
+        assert_eq!(tbo.view().leader(), view_2.leader());
+        assert_eq!(tbo.stopping_view().sequence_number(), view_2.next_view().sequence_number());
+    }
+
+    #[test]
+    fn test_aborted_view_is_skipped_without_being_installed() {
+        const MAX_LOOK_AHEAD: usize = 4;
+
+        let view_0 = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let mut tbo: TboQueue<()> = TboQueue::new(view_0.clone(), MAX_LOOK_AHEAD);
+
+        let view_1 = tbo.stopping_view();
+
+        assert_eq!(view_1.sequence_number(), view_0.next_view().sequence_number());
+
+        // The view change to view 1 is not sound, so we abort it and retry with view 2
+        tbo.install_next_view(view_1.clone());
+        tbo.skip_stopping_view();
+
+        let view_2 = tbo.stopping_view();
+
+        assert_eq!(view_2.sequence_number(), view_1.next_view().sequence_number());
+        assert!(tbo.next_view().is_none());
+
+        // View 1 was never finalized, so we are still in the same view as the consensus
+        assert_eq!(tbo.view().sequence_number(), view_0.sequence_number());
+
+        // Once the view change to view 2 is done, we move past both views
+        tbo.install_next_view(view_2.clone());
+
+        assert!(tbo.advance());
+        assert_eq!(tbo.view().sequence_number(), view_2.sequence_number());
+        assert_eq!(tbo.stopping_view().sequence_number(), view_2.next_view().sequence_number());
+    }
+
+    #[test]
+    fn test_unsound_view_change_is_retried_with_the_next_view() {
+        const MAX_LOOK_AHEAD: usize = 4;
+
+        let view_0 = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let mut tbo: TboQueue<()> = TboQueue::new(view_0.clone(), MAX_LOOK_AHEAD);
+
+        // A quorum has stopped, so we are changing to view 1
+        let view_1 = tbo.stopping_view();
+
+        tbo.install_next_view(view_1.clone());
+
+        let mut phase = ProtoPhase::Syncing;
+
+        // Every replica claims a value was prepared by a quorum in a previous view,
+        // but none of them can certify it, so the collects neither bind a value nor are unbound
+        let digest = Digest::from_bytes(&[1; Digest::LENGTH]).unwrap();
+
+        let collects: Vec<CollectData<()>> = (0..view_1.params().quorum())
+            .map(|_| {
+                let incomplete_proof = IncompleteProof::new(SeqNo::ZERO, PrepareSet(Vec::new()),
+                                                            Some(ViewDecisionPair(SeqNo::ZERO.next(), digest.clone())));
+
+                CollectData::new(incomplete_proof, None)
+            })
+            .collect();
+
+        let normalized_collects: Vec<Option<&CollectData<()>>> = collects.iter().map(Some).collect();
+
+        let sound = sound(&view_1, &normalized_collects);
+
+        assert!(!sound.test());
+
+        // By default, the view change proceeds regardless
+        assert!(!aborts_view_change(UnsoundViewChangePolicy::default(), &sound));
+
+        // But when configured to abort and retry, the view change to view 1 is aborted
+        assert!(aborts_view_change(UnsoundViewChangePolicy::AbortAndRetry, &sound));
+        assert!(matches!(phase, ProtoPhase::Syncing));
+
+        tbo.skip_stopping_view();
+        phase = ProtoPhase::Init;
+
+        // And we send our STOP for the following view
+        phase = match phase {
+            ProtoPhase::Init => ProtoPhase::Stopping2(0),
+            other => other,
+        };
+
+        assert!(matches!(phase, ProtoPhase::Stopping2(0)));
+        assert!(tbo.next_view().is_none());
+        assert_eq!(tbo.view().sequence_number(), view_0.sequence_number());
+        assert_eq!(tbo.stopping_view().sequence_number(), view_1.next_view().sequence_number());
+        assert_eq!(tbo.stopping_view().leader(), view_1.next_view().leader());
+
+        // Sound collects are never aborted
+        let unbound: Vec<CollectData<()>> = (0..view_1.params().quorum())
+            .map(|_| CollectData::new(IncompleteProof::new(SeqNo::ZERO, PrepareSet(Vec::new()), None), None))
+            .collect();
+
+        let normalized_collects: Vec<Option<&CollectData<()>>> = unbound.iter().map(Some).collect();
+
+        assert!(!aborts_view_change(UnsoundViewChangePolicy::AbortAndRetry, &super::sound(&view_1, &normalized_collects)));
+    }
+
+    #[test]
+    fn test_view_queue_senders_are_capped() {
+        const MAX_SENDERS: usize = 3;
+
+        let queued = [NodeId(0), NodeId(1)];
+
+        // Only one message per sender is kept for future views
+        assert!(!can_queue_sender(queued.into_iter(), NodeId(1), MAX_SENDERS, true));
+        assert!(can_queue_sender(queued.into_iter(), NodeId(2), MAX_SENDERS, true));
+
+        // But not for the next view, which we are processing
+        assert!(can_queue_sender(queued.into_iter(), NodeId(1), MAX_SENDERS, false));
+
+        // And only up to the maximum amount of distinct senders
+        let queued = [NodeId(0), NodeId(1), NodeId(2)];
+
+        assert!(!can_queue_sender(queued.into_iter(), NodeId(3), MAX_SENDERS, true));
+        assert!(!can_queue_sender(queued.into_iter(), NodeId(3), MAX_SENDERS, false));
+        assert!(can_queue_sender(queued.into_iter(), NodeId(2), MAX_SENDERS, false));
+
+        // The cap follows the size of the view, so large views don't lose messages
+        let large_view = ViewInfo::new(SeqNo::ZERO, 100, 33).unwrap();
+
+        let senders: Vec<NodeId> = large_view.quorum_members().iter().take(99).cloned().collect();
+
+        assert!(can_queue_sender(senders.into_iter(), NodeId(99), max_senders_for_view(&large_view, 0), true));
+
+        // A STOP from a sender replaces its queued STOP-QUORUM-JOIN, but not the other way around
+        let stop: ViewChangeMessageKind<()> = ViewChangeMessageKind::Stop(vec![]);
+        let join: ViewChangeMessageKind<()> = ViewChangeMessageKind::StopQuorumJoin(NodeId(4));
+
+        assert!(takes_precedence(&stop, &join));
+        assert!(!takes_precedence(&join, &stop));
+        assert!(!takes_precedence(&stop, &stop));
+    }
+
+    #[test]
+    fn test_sync_with_collect_from_wrong_view_is_rejected() {
+        let view_0 = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let view_1 = view_0.next_view();
+
+        let collect = |view: &ViewInfo| {
+            let incomplete_proof = IncompleteProof::new(SeqNo::ZERO, PrepareSet(Vec::new()), None);
+
+            PBFTMessage::<()>::ViewChange(ViewChangeMessage::new(
+                view.sequence_number(),
+                ViewChangeMessageKind::StopData(CollectData::new(incomplete_proof, None)),
+            ))
+        };
+
+        let view_change_seq = view_1.sequence_number();
+
+        assert!(collect_pertains_to_view(&collect(&view_1), view_change_seq));
+
+        // A collect from the previous view change is stale, so the SYNC is rejected
+        assert!(!collect_pertains_to_view(&collect(&view_0), view_change_seq));
+
+        // As is any message which is not a collect
+        let stop = PBFTMessage::<()>::ViewChange(ViewChangeMessage::new(view_change_seq, ViewChangeMessageKind::Stop(Vec::new())));
+
+        assert!(!collect_pertains_to_view(&stop, view_change_seq));
+    }
+
+    #[test]
+    fn test_split_join_votes_do_not_panic() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let (quorum, n) = (view.params().quorum(), view.params().n());
+
+        let mut votes = BTreeMap::new();
+
+        // Two nodes attempt to join concurrently and the quorum splits its votes
+        assert_eq!(register_join_vote(&mut votes, NodeId(0), NodeId(4)), JoinVote::New);
+        assert_eq!(register_join_vote(&mut votes, NodeId(1), NodeId(4)), JoinVote::New);
+        assert_eq!(register_join_vote(&mut votes, NodeId(2), NodeId(5)), JoinVote::New);
+
+        assert_eq!(tally_join_votes(&votes, 3, quorum, n), JoinTally::Pending);
+
+        assert_eq!(register_join_vote(&mut votes, NodeId(3), NodeId(5)), JoinVote::New);
+
+        assert_eq!(tally_join_votes(&votes, 4, quorum, n), JoinTally::Split);
+
+        // Once a candidate gets a quorum of votes, it is elected
+        let mut votes = BTreeMap::new();
+
+        for voter in 0..3 {
+            register_join_vote(&mut votes, NodeId(voter), NodeId(4));
+        }
+
+        assert_eq!(tally_join_votes(&votes, 3, quorum, n), JoinTally::Elected { candidate: NodeId(4), votes: 3 });
+    }
+
+    #[test]
+    fn test_sender_keys_are_fetched_once() {
+        let mut keys = KeyCache::new();
+
+        let mut fetches = 0;
+
+        for sender in [NodeId(0), NodeId(1), NodeId(0), NodeId(1), NodeId(0)] {
+            let key = keys.get_or_fetch(sender, |node| {
+                fetches += 1;
+
+                Some(node.0)
+            });
+
+            assert_eq!(key, Some(&sender.0));
+        }
+
+        // Unknown senders are also only looked up once
+        for _ in 0..3 {
+            assert!(keys.get_or_fetch(NodeId(5), |_| {
+                fetches += 1;
+
+                None
+            }).is_none());
+        }
+
+        assert_eq!(fetches, 3);
+    }
+
+    #[test]
+    fn test_missing_next_view_is_detected_and_join_state_reset() {
+        const MAX_LOOK_AHEAD: usize = 4;
+
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        // We are in the middle of a view change, but the next view was never installed
+        let tbo: TboQueue<()> = TboQueue::new(view.clone(), MAX_LOOK_AHEAD);
+
+        assert!(tbo.next_view().is_none());
+
+        for phase in [ProtoPhase::StoppingData(1), ProtoPhase::Syncing] {
+            let mut stopped = IntMap::new();
+            let mut collects = IntMap::new();
+            let mut finalize_state = None;
+
+            // We were adding a node to the quorum, with votes from other replicas
+            let mut currently_adding = BTreeMap::from([(NodeId(4), BTreeSet::from([NodeId(0), NodeId(1)]))]);
+            let currently_adding_node = Cell::new(Some(NodeId(4)));
+
+            // And we were removed and started joining the quorum again ourselves
+            let mut quorum_join = QuorumJoin::default();
+
+            let without_us = ViewInfo::from_quorum(view.next_view().sequence_number(), vec![NodeId(1), NodeId(2), NodeId(3), NodeId(4)]).unwrap();
+
+            assert!(quorum_join.view_installing(NodeId(0), &view, &without_us));
+
+            let deadline = Instant::now();
+
+            assert!(quorum_join.begin(deadline));
+
+            let reset = reset_inconsistent_phase::<()>(phase, tbo.next_view(), PartialViewChange {
+                stopped: &mut stopped,
+                collects: &mut collects,
+                finalize_state: &mut finalize_state,
+                currently_adding: &mut currently_adding,
+                currently_adding_node: &currently_adding_node,
+                quorum_join: &mut quorum_join,
+            });
+
+            assert!(matches!(reset, ProtoPhase::Init));
+
+            // No stale join state survives the reset
+            assert!(currently_adding.is_empty());
+            assert!(currently_adding_node.get().is_none());
+            assert!(!quorum_join.is_entering());
+            assert!(!quorum_join.must_catch_up());
+            assert!(!quorum_join.has_expired(deadline + Duration::from_secs(1)));
+            assert!(finalize_state.is_none());
+        }
+
+        // With the next view installed, or in a phase which doesn't need it, nothing is reset
+        let next_view = view.next_view();
+
+        for (phase, next_view) in [(ProtoPhase::Syncing, Some(&next_view)), (ProtoPhase::Stopping2(1), None)] {
+            let currently_adding_node = Cell::new(Some(NodeId(4)));
+            let mut currently_adding = BTreeMap::from([(NodeId(4), BTreeSet::from([NodeId(0)]))]);
+
+            let kept = reset_inconsistent_phase::<()>(phase, next_view, PartialViewChange {
+                stopped: &mut IntMap::new(),
+                collects: &mut IntMap::new(),
+                finalize_state: &mut None,
+                currently_adding: &mut currently_adding,
+                currently_adding_node: &currently_adding_node,
+                quorum_join: &mut QuorumJoin::default(),
+            });
+
+            assert_eq!(format!("{:?}", kept), format!("{:?}", phase));
+            assert_eq!(currently_adding.len(), 1);
+            assert_eq!(currently_adding_node.get(), Some(NodeId(4)));
+        }
+    }
+
+    #[test]
+    fn test_stop_is_retransmitted_only_while_stopping() {
+        // Our STOP and STOP-QUORUM-JOIN are re-sent whenever their timer fires
+        // while we are waiting for the other replicas to join the view change
+        assert_eq!(stop_retransmission_action(ProtoPhase::Stopping2(1), true), StopRetransmission::Retransmit);
+        assert_eq!(stop_retransmission_action(ProtoPhase::ViewStopping2(1), true), StopRetransmission::Retransmit);
+
+        // Client requests timing out in the meantime do not re-send it
+        assert_eq!(stop_retransmission_action(ProtoPhase::Stopping2(1), false), StopRetransmission::Wait);
+        assert_eq!(stop_retransmission_action(ProtoPhase::ViewStopping2(1), false), StopRetransmission::Wait);
+
+        // Once the view change has advanced past the stopping phase, or is over, we stop re-sending it
+        for phase in [ProtoPhase::StoppingData(0), ProtoPhase::Syncing, ProtoPhase::Init] {
+            assert_eq!(stop_retransmission_action(phase, true), StopRetransmission::Stop);
+            assert_eq!(stop_retransmission_action(phase, false), StopRetransmission::Stop);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::Mutex;
+
+use rand_core::RngCore;
+
+use atlas_common::prng;
+
+/// A source of nonces for the messages that are forged by the synchronizer
+/// (for example, the pre prepare message the new leader creates in the
+/// view change protocol).
+///
+/// This can be replaced by a deterministic implementation, so that the randomized
+/// decisions taken during a view change can be reproduced in tests
+pub trait NonceSource: Send {
+    /// Produce the next nonce
+    fn next_nonce(&mut self) -> u64;
+}
+
+/// The default nonce source, backed by the thread safe prng of atlas common
+pub struct PrngNonceSource {
+    state: prng::State,
+}
+
+impl PrngNonceSource {
+    pub fn new() -> Self {
+        Self {
+            state: prng::State::new(),
+        }
+    }
+}
+
+impl Default for PrngNonceSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NonceSource for PrngNonceSource {
+    fn next_nonce(&mut self) -> u64 {
+        self.state.next_state()
+    }
+}
+
+/// A nonce source backed by any given rng.
+/// When the rng is seeded, the produced nonce sequence is reproducible.
+pub struct RngNonceSource<R> {
+    rng: R,
+}
+
+impl<R> RngNonceSource<R> where R: RngCore + Send {
+    pub fn new(rng: R) -> Self {
+        Self {
+            rng
+        }
+    }
+}
+
+impl<R> NonceSource for RngNonceSource<R> where R: RngCore + Send {
+    fn next_nonce(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+}
+
+/// The nonce source installed in a synchronizer, from which it takes the nonces
+/// of the messages it forges during the view change protocol
+pub struct InstalledNonceSource {
+    source: Mutex<Box<dyn NonceSource>>,
+}
+
+impl InstalledNonceSource {
+    pub fn new() -> Self {
+        Self {
+            source: Mutex::new(Box::new(PrngNonceSource::new())),
+        }
+    }
+
+    /// Replace the installed source, so every following nonce is taken from it
+    pub fn install(&self, source: Box<dyn NonceSource>) {
+        *self.source.lock().unwrap() = source;
+    }
+
+    /// Take the nonce for the next message we forge
+    pub fn next_nonce(&self) -> u64 {
+        self.source.lock().unwrap().next_nonce()
+    }
+}
+
+impl Default for InstalledNonceSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod nonce_tests {
+    use rand_core::SeedableRng;
+
+    use super::*;
+
+    const SEED: u64 = 812679233723;
+
+    #[test]
+    fn test_seeded_nonce_sources_match() {
+        const VIEW_CHANGES: usize = 1000;
+
+        // Two synchronizers, which start out with the default prng backed source
+        let first = InstalledNonceSource::default();
+        let second = InstalledNonceSource::default();
+
+        // Draw some nonces before the sources are replaced
+        first.next_nonce();
+        second.next_nonce();
+        second.next_nonce();
+
+        first.install(Box::new(RngNonceSource::new(rand::rngs::SmallRng::seed_from_u64(SEED))));
+        second.install(Box::new(RngNonceSource::new(rand::rngs::SmallRng::seed_from_u64(SEED))));
+
+        // Every pre prepare they forge as the new leader carries the same nonce,
+        // regardless of the nonces drawn before the installation
+        let forged_by_first: Vec<u64> = (0..VIEW_CHANGES).map(|_| first.next_nonce()).collect();
+        let forged_by_second: Vec<u64> = (0..VIEW_CHANGES).map(|_| second.next_nonce()).collect();
+
+        assert_eq!(forged_by_first, forged_by_second);
+
+        // Installing the same source again replays the same view changes
+        first.install(Box::new(RngNonceSource::new(rand::rngs::SmallRng::seed_from_u64(SEED))));
+
+        let replayed: Vec<u64> = (0..VIEW_CHANGES).map(|_| first.next_nonce()).collect();
+
+        assert_eq!(replayed, forged_by_first);
+    }
+}
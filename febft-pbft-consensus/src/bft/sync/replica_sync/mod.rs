@@ -3,15 +3,16 @@
 //! This code allows a replica to change its view, where a new
 //! leader is elected.
 
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::marker::PhantomData;
 use std::time::{Duration, Instant};
 
 use log::{debug, error, info};
 
 use atlas_common::collections;
+use atlas_common::crypto::hash::Context;
 use atlas_common::node_id::NodeId;
-use atlas_common::ordering::Orderable;
+use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_communication::message::Header;
 use atlas_communication::protocol_node::ProtocolNetworkNode;
 use atlas_core::messages::{ClientRqInfo, ForwardedRequestsMessage, StoredRequestMessage};
@@ -21,6 +22,7 @@ use atlas_core::timeouts::{RqTimeout, TimeoutKind, TimeoutPhase, Timeouts};
 use atlas_metrics::metrics::{metric_duration, metric_increment};
 use atlas_smr_application::serialize::ApplicationData;
 
+use crate::bft::config::{TimeoutGrowth, ViewChangeTimeoutConfig};
 use crate::bft::consensus::Consensus;
 use crate::bft::log::decisions::CollectData;
 use crate::bft::log::Log;
@@ -37,14 +39,182 @@ use super::{AbstractSynchronizer, Synchronizer, SynchronizerStatus};
 // This synchronizer will only move forward on replica messages
 
 pub struct ReplicaSynchronizer<D: ApplicationData> {
+    // The request timeout when no view change has failed
+    base_timeout: Duration,
+    // How the request timeout grows with the failed view changes
+    timeout_config: ViewChangeTimeoutConfig,
+    // How many consecutive view changes have failed since the last one that succeeded
+    failed_view_changes: Cell<u32>,
+    // The view change we have already counted as failed, since the requests timed out again while it was running
+    stalled_view: Cell<Option<SeqNo>>,
     timeout_dur: Cell<Duration>,
+    // Coalesces the timed out requests we forward to the other replicas
+    forwarding: RefCell<ForwardingBatcher<StoredRequestMessage<D::Request>>>,
+    // Our latest STOP (or STOP-QUORUM-JOIN) message, re-sent until the view change advances
+    stop_retransmission: RefCell<StopRetransmitter<PBFTMessage<D::Request>, ClientRqInfo>>,
     _phantom: PhantomData<D>,
 }
 
+/// Coalesces requests into bounded batches, released at a controlled rate.
+///
+/// Used to forward timed out requests, so that a storm of timeouts does not
+/// turn into a storm of broadcasts that further saturates the network
+pub(super) struct ForwardingBatcher<R> {
+    max_batch_size: usize,
+    min_interval: Duration,
+    last_forward: Option<Instant>,
+    pending: Vec<R>,
+}
+
+impl<R> ForwardingBatcher<R> {
+    pub(super) fn new(max_batch_size: usize, min_interval: Duration) -> Self {
+        Self {
+            max_batch_size: max_batch_size.max(1),
+            min_interval,
+            last_forward: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Add requests to be forwarded
+    pub(super) fn queue(&mut self, mut requests: Vec<R>) {
+        self.pending.append(&mut requests);
+    }
+
+    /// Take the next batch of requests to forward, if we have any pending
+    /// and enough time has passed since the last batch was forwarded
+    pub(super) fn next_batch(&mut self, now: Instant) -> Option<Vec<R>> {
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        if let Some(last_forward) = self.last_forward {
+            if now.saturating_duration_since(last_forward) < self.min_interval {
+                return None;
+            }
+        }
+
+        self.last_forward = Some(now);
+
+        let batch_size = self.pending.len().min(self.max_batch_size);
+
+        let remaining = self.pending.split_off(batch_size);
+
+        Some(std::mem::replace(&mut self.pending, remaining))
+    }
+
+    pub(super) fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// The request timeout after `failed` consecutive failed view changes,
+/// which never exceeds the configured maximum (unless the base timeout itself does)
+pub(super) fn scaled_timeout(base: Duration, failed: u32, config: &ViewChangeTimeoutConfig) -> Duration {
+    let scaled = match config.growth {
+        TimeoutGrowth::Fixed => base,
+        TimeoutGrowth::Linear => base.saturating_mul(failed.saturating_add(1)),
+        TimeoutGrowth::Exponential => base.saturating_mul(2u32.saturating_pow(failed)),
+    };
+
+    scaled.min(config.max_timeout.max(base))
+}
+
+/// Re-sends the STOP message we have broadcast, up to a maximum amount of times.
+///
+/// A STOP message lost on the way to some of the replicas could otherwise
+/// stall the view change, as they would never learn that it is underway.
+/// A dedicated timer of `interval` is registered with the timeouts layer when the
+/// message is sent, and the message is re-sent whenever it fires before the view change advances
+pub(super) struct StopRetransmitter<M, T> {
+    interval: Duration,
+    max_retransmits: usize,
+    retransmits: usize,
+    message: Option<M>,
+    // The timer registered with the timeouts layer for the message we are re-sending
+    timer: Option<T>,
+}
+
+impl<M: Clone, T: PartialEq> StopRetransmitter<M, T> {
+    pub(super) fn new(interval: Duration, max_retransmits: usize) -> Self {
+        Self {
+            interval,
+            max_retransmits,
+            retransmits: 0,
+            message: None,
+            timer: None,
+        }
+    }
+
+    /// The timeout to register for the retransmission timer
+    pub(super) fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Start re-sending the given message whenever `timer` fires, replacing the one we were re-sending before.
+    /// Returns the timer of the replaced message, which should no longer be watched
+    pub(super) fn arm(&mut self, message: M, timer: T) -> Option<T> {
+        self.retransmits = 0;
+        self.message = Some(message);
+
+        self.timer.replace(timer)
+    }
+
+    /// Stop re-sending the message, as the view change has advanced.
+    /// Returns its timer, which should no longer be watched
+    pub(super) fn disarm(&mut self) -> Option<T> {
+        self.message = None;
+
+        self.timer.take()
+    }
+
+    /// Whether the given timeout is the timer of the message we are re-sending
+    pub(super) fn is_timer(&self, timer: &T) -> bool {
+        self.timer.as_ref() == Some(timer)
+    }
+
+    /// The timer of the message we are re-sending, if any
+    pub(super) fn timer(&self) -> Option<&T> {
+        self.timer.as_ref()
+    }
+
+    /// Take the message to re-send, if we have not yet re-sent it the maximum amount of times
+    pub(super) fn next_retransmit(&mut self) -> Option<M> {
+        self.message.as_ref()?;
+
+        if self.retransmits >= self.max_retransmits {
+            return None;
+        }
+
+        self.retransmits += 1;
+
+        self.message.clone()
+    }
+}
+
+/// The timeouts layer only watches client (and state transfer) requests, so the timer which
+/// re-sends our STOP message is registered as the timeout of a request no client can issue:
+/// one sent by ourselves, for the view we are stopping for
+fn stop_retransmit_timer(my_id: NodeId, view: SeqNo) -> ClientRqInfo {
+    let mut ctx = Context::new();
+
+    ctx.update(b"STOP-RETRANSMIT");
+    ctx.update(&u32::from(view).to_le_bytes());
+
+    ClientRqInfo::new(ctx.finish(), my_id, view, SeqNo::ZERO)
+}
+
 impl<D: ApplicationData + 'static> ReplicaSynchronizer<D> {
-    pub fn new(timeout_dur: Duration) -> Self {
+    pub fn new(timeout_dur: Duration, timeout_config: ViewChangeTimeoutConfig, forward_batch_size: usize, forward_interval: Duration,
+               stop_retransmit_interval: Duration, max_stop_retransmits: usize) -> Self {
         Self {
+            base_timeout: timeout_dur,
+            timeout_config,
+            failed_view_changes: Cell::new(0),
+            stalled_view: Cell::new(None),
             timeout_dur: Cell::new(timeout_dur),
+            forwarding: RefCell::new(ForwardingBatcher::new(forward_batch_size, forward_interval)),
+            stop_retransmission: RefCell::new(StopRetransmitter::new(stop_retransmit_interval, max_stop_retransmits)),
             _phantom: Default::default(),
         }
     }
@@ -73,6 +243,9 @@ impl<D: ApplicationData + 'static> ReplicaSynchronizer<D> {
         // - reset the timers of the requests in the STOP
         //   messages with TimeoutPhase::Init(_)
         // - send STOP-DATA message
+        // We have advanced past the stopping phase, so our STOP no longer needs to be re-sent
+        self.stop_retransmitting(timeouts);
+
         self.take_stopped_requests_and_register_them(base_sync, pre_processor, timeouts);
         self.watch_all_requests(timeouts);
 
@@ -117,16 +290,19 @@ impl<D: ApplicationData + 'static> ReplicaSynchronizer<D> {
 
         let current_view = base_sync.view();
 
-        //TODO: Timeout this request and keep sending it until we have achieved a new regency
+        // This is the view after the current one, unless we have aborted changing to it
+        let stopping_view = base_sync.stopping_view();
 
-        info!("{:?} // Beginning a view change from view {:?} to next view with stopped rqs {:?}",
-            node.id(), current_view, requests.len());
+        info!("{:?} // Beginning a view change from view {:?} to view {:?} with stopped rqs {:?}",
+            node.id(), current_view, stopping_view.sequence_number(), requests.len());
 
         let message = PBFTMessage::ViewChange(ViewChangeMessage::new(
-            current_view.sequence_number().next(),
+            stopping_view.sequence_number(),
             ViewChangeMessageKind::Stop(requests),
         ));
 
+        self.arm_stop_retransmission(message.clone(), stop_retransmit_timer(node.id(), stopping_view.sequence_number()), timeouts);
+
         let targets = current_view.quorum_members().clone();
 
         node.broadcast(message, targets.into_iter());
@@ -145,10 +321,14 @@ impl<D: ApplicationData + 'static> ReplicaSynchronizer<D> {
 
         let message = ViewChangeMessageKind::StopQuorumJoin(join_cert);
 
-        let message = ViewChangeMessage::new(current_view.sequence_number().next(), message);
+        let next_view = current_view.sequence_number().next();
+
+        let message = ViewChangeMessage::new(next_view, message);
 
         let message = PBFTMessage::ViewChange(message);
 
+        self.arm_stop_retransmission(message.clone(), stop_retransmit_timer(node.id(), next_view), timeouts);
+
         node.broadcast_signed(message, current_view.quorum_members().clone().into_iter());
     }
 
@@ -296,6 +476,11 @@ impl<D: ApplicationData + 'static> ReplicaSynchronizer<D> {
                         _ => unreachable!("Only client requests should be timed out at the synchronizer")
                     };
 
+                    if self.stop_retransmission.borrow().is_timer(rq_info) {
+                        // This is not a client request, it is the timer which re-sends our STOP
+                        continue;
+                    }
+
                     if *id == 0 {
                         forwarded.push(rq_info.clone());
                     } else if *id >= 1 {
@@ -341,12 +526,148 @@ impl<D: ApplicationData + 'static> ReplicaSynchronizer<D> {
         timed_out: Vec<StoredRequestMessage<D::Request>>,
         node: &NT,
     ) where NT: OrderProtocolSendNode<D, PBFT<D>> {
-        let message = ForwardedRequestsMessage::new(timed_out);
-        let view = base_sync.view();
+        self.forwarding.borrow_mut().queue(timed_out);
+
+        self.flush_forwarded_requests(base_sync, node);
+    }
+
+    /// Forward the next batch of pending timed out requests, if the forwarding rate allows it.
+    /// The requests which are not forwarded remain pending for the next flush
+    pub fn flush_forwarded_requests<NT>(
+        &self,
+        base_sync: &Synchronizer<D>,
+        node: &NT,
+    ) where NT: OrderProtocolSendNode<D, PBFT<D>> {
+        let batch = {
+            let mut forwarding = self.forwarding.borrow_mut();
+
+            let batch = forwarding.next_batch(Instant::now());
+
+            if batch.is_some() && forwarding.pending() > 0 {
+                debug!("{:?} // Forwarding batch of timed out requests, {} requests remain pending", node.id(), forwarding.pending());
+            }
+
+            batch
+        };
+
+        if let Some(timed_out) = batch {
+            let message = ForwardedRequestsMessage::new(timed_out);
+            let view = base_sync.view();
+
+            let targets = view.quorum_members().clone();
+
+            node.forward_requests(message, targets.into_iter());
+        }
+    }
+
+    /// Start re-sending the given STOP (or STOP-QUORUM-JOIN) message, registering
+    /// its retransmission timer with the timeouts layer
+    fn arm_stop_retransmission(&self, message: PBFTMessage<D::Request>, timer: ClientRqInfo, timeouts: &Timeouts) {
+        let replaced = self.stop_retransmission.borrow_mut().arm(message, timer);
+
+        if let Some(replaced) = replaced {
+            timeouts.cancel_client_rq_timeouts(Some(vec![replaced]));
+        }
+
+        self.watch_stop_retransmit_timer(timeouts);
+    }
+
+    /// Register the retransmission timer of our STOP message, so that we are notified
+    /// by the timeouts layer when it is time to re-send it
+    fn watch_stop_retransmit_timer(&self, timeouts: &Timeouts) {
+        let retransmission = self.stop_retransmission.borrow();
+
+        if let Some(timer) = retransmission.timer() {
+            timeouts.timeout_client_requests(retransmission.interval(), vec![timer.clone()]);
+        }
+    }
+
+    /// Whether the retransmission timer of our STOP message is among the given timeouts
+    pub fn is_stop_retransmit_due(&self, timed_out_rqs: &Vec<RqTimeout>) -> bool {
+        let retransmission = self.stop_retransmission.borrow();
+
+        timed_out_rqs.iter().any(|timeout| match timeout.timeout_kind() {
+            TimeoutKind::ClientRequestTimeout(rq) => retransmission.is_timer(rq),
+            _ => false,
+        })
+    }
+
+    /// Re-send our STOP message to the current view, as its retransmission timer has
+    /// fired before the view change has advanced past the stopping phase, and watch the timer again.
+    ///
+    /// Returns whether the message was re-sent, which stops happening once
+    /// we have re-sent it the maximum amount of times
+    pub fn retransmit_stop<NT>(
+        &self,
+        base_sync: &Synchronizer<D>,
+        node: &NT,
+        timeouts: &Timeouts,
+    ) -> bool where NT: OrderProtocolSendNode<D, PBFT<D>> {
+        let message = match self.stop_retransmission.borrow_mut().next_retransmit() {
+            Some(message) => message,
+            None => {
+                self.stop_retransmitting(timeouts);
+
+                return false;
+            }
+        };
+
+        let targets = base_sync.view().quorum_members().clone();
+
+        debug!("{:?} // Retransmitting our STOP message for view {:?}", node.id(), message.sequence_number());
+
+        match &message {
+            PBFTMessage::ViewChange(view_change) if matches!(view_change.kind(), ViewChangeMessageKind::StopQuorumJoin(_)) => {
+                node.broadcast_signed(message, targets.into_iter());
+            }
+            _ => {
+                node.broadcast(message, targets.into_iter());
+            }
+        }
+
+        self.watch_stop_retransmit_timer(timeouts);
 
-        let targets = view.quorum_members().clone();
+        true
+    }
+
+    /// Stop re-sending our STOP message, since the view change has advanced past the stopping phase
+    pub fn stop_retransmitting(&self, timeouts: &Timeouts) {
+        let timer = self.stop_retransmission.borrow_mut().disarm();
+
+        if let Some(timer) = timer {
+            timeouts.cancel_client_rq_timeouts(Some(vec![timer]));
+        }
+    }
+
+    /// Register that a view change has failed, growing the timeout of the requests
+    /// to give the next view change more time to complete
+    pub(super) fn view_change_failed(&self) {
+        let failed = self.failed_view_changes.get().saturating_add(1);
 
-        node.forward_requests(message, targets.into_iter());
+        self.failed_view_changes.set(failed);
+        self.timeout_dur.set(scaled_timeout(self.base_timeout, failed, &self.timeout_config));
+    }
+
+    /// Register that the requests have timed out again while we were changing to `view`,
+    /// meaning the view change has stalled (for example, because the new leader is faulty).
+    /// A stalled view change is only counted as failed once
+    pub(super) fn view_change_stalled(&self, view: SeqNo) {
+        if self.stalled_view.replace(Some(view)) == Some(view) {
+            return;
+        }
+
+        self.view_change_failed();
+    }
+
+    /// Register that a view change has succeeded, resetting the timeout of the requests
+    pub(super) fn view_change_succeeded(&self) {
+        self.failed_view_changes.set(0);
+        self.timeout_dur.set(self.base_timeout);
+    }
+
+    /// The current timeout of the requests
+    pub fn timeout(&self) -> Duration {
+        self.timeout_dur.get()
     }
 
     /// Obtain the requests that we know have timed out so we can send out a stop message
@@ -439,3 +760,127 @@ impl<D: ApplicationData + 'static> ReplicaSynchronizer<D> {
 /// accessed by both those threads.
 /// Since the other fields are going to be accessed by just 1 thread, we just need them to be Send, which they are
 unsafe impl<D: ApplicationData> Sync for ReplicaSynchronizer<D> {}
+
+#[cfg(test)]
+mod replica_sync_tests {
+    use std::time::{Duration, Instant};
+
+    use crate::bft::config::{TimeoutGrowth, ViewChangeTimeoutConfig};
+
+    use super::{ForwardingBatcher, scaled_timeout, StopRetransmitter};
+
+    #[test]
+    fn test_forwarded_requests_are_coalesced() {
+        const TIMEOUTS: usize = 100;
+        const REQUESTS_PER_TIMEOUT: usize = 10;
+        const MAX_BATCH_SIZE: usize = 256;
+
+        let interval = Duration::from_millis(100);
+
+        let mut batcher = ForwardingBatcher::new(MAX_BATCH_SIZE, interval);
+
+        let start = Instant::now();
+
+        let mut broadcasts = Vec::new();
+
+        for timeout in 0..TIMEOUTS {
+            batcher.queue((0..REQUESTS_PER_TIMEOUT).map(|rq| timeout * REQUESTS_PER_TIMEOUT + rq).collect());
+
+            if let Some(batch) = batcher.next_batch(start) {
+                broadcasts.push(batch);
+            }
+        }
+
+        // All of the simultaneous timeouts result in a single bounded broadcast
+        assert_eq!(broadcasts.len(), 1);
+        assert_eq!(broadcasts[0].len(), MAX_BATCH_SIZE);
+
+        let mut now = start;
+
+        while batcher.pending() > 0 {
+            // Nothing is forwarded before the interval has passed
+            assert!(batcher.next_batch(now).is_none());
+
+            now += interval;
+
+            broadcasts.push(batcher.next_batch(now).unwrap());
+        }
+
+        let total_requests = TIMEOUTS * REQUESTS_PER_TIMEOUT;
+
+        assert_eq!(broadcasts.len(), (total_requests + MAX_BATCH_SIZE - 1) / MAX_BATCH_SIZE);
+        assert!(broadcasts.iter().all(|batch| batch.len() <= MAX_BATCH_SIZE));
+
+        let forwarded: Vec<usize> = broadcasts.into_iter().flatten().collect();
+
+        assert_eq!(forwarded, (0..total_requests).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_stop_retransmissions_are_capped() {
+        const MAX_RETRANSMITS: usize = 3;
+
+        let interval = Duration::from_millis(500);
+
+        let mut retransmitter = StopRetransmitter::new(interval, MAX_RETRANSMITS);
+
+        assert_eq!(retransmitter.interval(), interval);
+
+        // Nothing is re-sent before we have sent a STOP
+        assert!(retransmitter.next_retransmit().is_none());
+        assert!(!retransmitter.is_timer(&1));
+
+        assert_eq!(retransmitter.arm("stop", 1), None);
+
+        // Only the timer of the STOP we are re-sending is recognized
+        assert!(retransmitter.is_timer(&1));
+        assert!(!retransmitter.is_timer(&2));
+
+        let mut retransmits = 0;
+
+        // Every time the retransmission timer fires, until we reach the maximum
+        for _ in 0..MAX_RETRANSMITS * 2 {
+            if let Some(message) = retransmitter.next_retransmit() {
+                assert_eq!(message, "stop");
+
+                retransmits += 1;
+            }
+        }
+
+        assert_eq!(retransmits, MAX_RETRANSMITS);
+
+        // Sending a new STOP resets the retransmissions and replaces the timer, until the view change advances
+        assert_eq!(retransmitter.arm("stop", 2), Some(1));
+
+        assert!(retransmitter.next_retransmit().is_some());
+        assert!(!retransmitter.is_timer(&1));
+
+        assert_eq!(retransmitter.disarm(), Some(2));
+
+        assert!(retransmitter.next_retransmit().is_none());
+        assert!(retransmitter.timer().is_none());
+    }
+
+    #[test]
+    fn test_timeout_scales_with_failed_view_changes() {
+        let base = Duration::from_secs(1);
+        let max_timeout = Duration::from_secs(10);
+
+        let fixed = ViewChangeTimeoutConfig::new(TimeoutGrowth::Fixed, max_timeout);
+        let linear = ViewChangeTimeoutConfig::new(TimeoutGrowth::Linear, max_timeout);
+        let exponential = ViewChangeTimeoutConfig::new(TimeoutGrowth::Exponential, max_timeout);
+
+        assert_eq!(scaled_timeout(base, 3, &fixed), base);
+
+        assert_eq!(scaled_timeout(base, 0, &linear), base);
+        assert_eq!(scaled_timeout(base, 3, &linear), Duration::from_secs(4));
+
+        assert_eq!(scaled_timeout(base, 0, &exponential), base);
+        assert_eq!(scaled_timeout(base, 3, &exponential), Duration::from_secs(8));
+
+        // The growth is capped
+        assert_eq!(scaled_timeout(base, 4, &exponential), max_timeout);
+        assert_eq!(scaled_timeout(base, u32::MAX, &exponential), max_timeout);
+        assert_eq!(scaled_timeout(base, u32::MAX, &linear), max_timeout);
+    }
+}
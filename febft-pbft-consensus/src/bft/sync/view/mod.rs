@@ -162,6 +162,19 @@ impl ViewInfo {
         Self::from_quorum(self.seq.next(), quorum_members).unwrap()
     }
 
+    /// Returns the next view with the new node added to the quorum, after validating
+    /// that the membership change preserves the quorum intersection property.
+    /// See [validate_membership_change]
+    pub fn try_next_view_with_new_node(&self, joined_node: NodeId) -> Result<ViewInfo> {
+        let mut quorum_members = self.quorum_members().clone();
+
+        quorum_members.push(joined_node);
+
+        validate_membership_change(self, &quorum_members)?;
+
+        Self::from_quorum(self.seq.next(), quorum_members)
+    }
+
     pub fn previous_view(&self) -> Option<ViewInfo> {
         if self.seq == SeqNo::ZERO {
             return None;
@@ -195,6 +208,41 @@ impl ViewInfo {
     pub fn hash_space_division(&self) -> &BTreeMap<NodeId, (Vec<u8>, Vec<u8>)> {
         &self.leader_hash_space_division
     }
+
+    /// The quorum members of this view which we are currently able to reach,
+    /// given the peers we are connected to (for example, `Node::connected_tx_peers()`).
+    /// We always consider ourselves reachable.
+    pub fn reachable_quorum_members(&self, our_id: NodeId, connected: &[NodeId]) -> ReachableQuorum {
+        let reachable = self.quorum_members.iter()
+            .filter(|member| **member == our_id || connected.contains(member))
+            .cloned()
+            .collect();
+
+        ReachableQuorum {
+            reachable,
+            quorum: self.params.quorum(),
+        }
+    }
+}
+
+/// The quorum members of a view that are currently reachable
+#[derive(Debug, Clone)]
+pub struct ReachableQuorum {
+    reachable: Vec<NodeId>,
+    quorum: usize,
+}
+
+impl ReachableQuorum {
+    /// The reachable quorum members
+    pub fn members(&self) -> &Vec<NodeId> {
+        &self.reachable
+    }
+
+    /// Whether the reachable members are not enough to form a quorum,
+    /// meaning we are at risk of losing liveness
+    pub fn is_below_quorum(&self) -> bool {
+        self.reachable.len() < self.quorum
+    }
 }
 
 /// Get the division of hash spaces for a given leader_set
@@ -214,6 +262,53 @@ fn calculate_hash_space_division(leader_set: &Vec<NodeId>) -> BTreeMap<NodeId, (
     slice_for_leaders
 }
 
+/// The minimum amount of nodes in which any two quorums of a view with the given params intersect.
+/// For `n = 3f + 1` this is `f + 1`, so at least one correct node.
+fn quorum_overlap(params: &SystemParams) -> usize {
+    (2 * params.quorum()).saturating_sub(params.n())
+}
+
+/// Validate that moving from the quorum of `current` to `new_members` in a single step is safe.
+///
+/// Any quorum of the current view and any quorum of the new view must intersect in at least
+/// as many nodes as two quorums of either view do (and never in zero nodes), otherwise both
+/// views could decide conflicting values. The quorums are the ones the protocol actually uses,
+/// given by [SystemParams::quorum].
+/// This rejects changes which add or remove too many nodes at once; such changes must be
+/// split into several smaller ones.
+pub fn validate_membership_change(current: &ViewInfo, new_members: &[NodeId]) -> Result<()> {
+    let old_n = current.quorum_members().len();
+    let new_n = new_members.len();
+
+    let added = new_members.iter().filter(|member| !current.quorum_members().contains(member)).count();
+
+    // Every node which takes part in either view
+    let union = old_n + added;
+
+    let (intersection, required) = if new_n == 0 {
+        (0, 1)
+    } else {
+        let new_params = SystemParams::new(new_n, (new_n - 1) / 3)?;
+
+        let intersection = (current.params().quorum() + new_params.quorum()).saturating_sub(union);
+
+        let required = quorum_overlap(current.params()).min(quorum_overlap(&new_params)).max(1);
+
+        (intersection, required)
+    };
+
+    if intersection < required {
+        return Err!(ViewError::UnsafeMembershipChange {
+            current: current.quorum_members().clone(),
+            proposed: new_members.to_vec(),
+            intersection,
+            required,
+        });
+    }
+
+    Ok(())
+}
+
 /// Check if a given requests is within a given hash space
 pub fn is_request_in_hash_space(rq: &Digest, hash_space: &(Vec<u8>, Vec<u8>)) -> bool {
     let start = &hash_space.0;
@@ -302,6 +397,68 @@ mod view_tests {
             assert_eq!(count, 1, "The digest {:?} was found in {} hash spaces", digest, count);
         }
     }
+
+    #[test]
+    fn test_reachable_quorum() {
+        use super::*;
+
+        let view_info = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let our_id = NodeId(0);
+
+        let mut connected = vec![NodeId(1), NodeId(2), NodeId(3)];
+
+        let reachable = view_info.reachable_quorum_members(our_id, &connected);
+
+        assert_eq!(reachable.members().len(), 4);
+        assert!(!reachable.is_below_quorum());
+
+        // Losing one member still leaves us with a quorum
+        connected.retain(|node| *node != NodeId(3));
+
+        let reachable = view_info.reachable_quorum_members(our_id, &connected);
+
+        assert_eq!(reachable.members(), &vec![NodeId(0), NodeId(1), NodeId(2)]);
+        assert!(!reachable.is_below_quorum());
+
+        // But losing two does not
+        connected.retain(|node| *node != NodeId(2));
+
+        let reachable = view_info.reachable_quorum_members(our_id, &connected);
+
+        assert!(reachable.is_below_quorum());
+    }
+
+    #[test]
+    fn test_membership_change_validation() {
+        use super::*;
+
+        let view_info = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        // Adding a single node is safe
+        assert!(view_info.try_next_view_with_new_node(NodeId(4)).is_ok());
+
+        // Removing a single node is safe as well
+        let larger_view = ViewInfo::from_quorum(SeqNo::ZERO, NodeId::targets_u32(0..5).collect()).unwrap();
+
+        assert!(validate_membership_change(&larger_view, &NodeId::targets_u32(0..4).collect::<Vec<_>>()).is_ok());
+
+        // Adding two nodes in a single step is not
+        let two_added: Vec<NodeId> = NodeId::targets_u32(0..6).collect();
+
+        assert!(validate_membership_change(&view_info, &two_added).is_err());
+
+        // Neither is swapping a member for a new node in a single step
+        let swapped = vec![NodeId(0), NodeId(1), NodeId(2), NodeId(4)];
+
+        assert!(validate_membership_change(&view_info, &swapped).is_err());
+
+        // Shrinking a view by more than its own quorums tolerate is rejected, using the quorum size of each view
+        let seven_view = ViewInfo::from_quorum(SeqNo::ZERO, NodeId::targets_u32(0..7).collect()).unwrap();
+
+        assert_eq!(seven_view.params().quorum(), 5);
+        assert!(validate_membership_change(&seven_view, &NodeId::targets_u32(0..4).collect::<Vec<_>>()).is_err());
+    }
 }
 
 impl Debug for ViewInfo {
@@ -314,5 +471,12 @@ impl Debug for ViewInfo {
 #[derive(Error, Debug)]
 pub enum ViewError {
     #[error("Leader is not contained in the quorum participants. Leader {0:?}, quorum {1:?}")]
-    LeaderNotInQuorum(NodeId, Vec<NodeId>)
+    LeaderNotInQuorum(NodeId, Vec<NodeId>),
+    #[error("Membership change from {current:?} to {proposed:?} is unsafe, quorums intersect in {intersection} nodes but {required} are required")]
+    UnsafeMembershipChange {
+        current: Vec<NodeId>,
+        proposed: Vec<NodeId>,
+        intersection: usize,
+        required: usize,
+    },
 }
\ No newline at end of file
@@ -0,0 +1,229 @@
+use std::time::{Duration, Instant};
+
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::SeqNo;
+
+use crate::bft::sync::view::{ReachableQuorum, ViewInfo};
+
+/// An alert about the connectivity of this replica to the quorum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuorumAlert {
+    /// We have not been able to reach a quorum of the view for longer
+    /// than the configured duration, so the system can't make progress
+    QuorumUnreachable {
+        view: SeqNo,
+        reachable: Vec<NodeId>,
+        below_quorum_for: Duration,
+    },
+    /// We are once again able to reach a quorum, after having alerted that it was unreachable
+    QuorumRestored {
+        view: SeqNo,
+        outage: Duration,
+    },
+}
+
+/// Watches the reachable members of the quorum, alerting when we are below quorum
+/// for longer than `alert_after` and when the quorum connectivity is restored.
+pub struct QuorumWatchdog {
+    alert_after: Duration,
+    // Since when we have been unable to reach a quorum
+    below_quorum_since: Option<Instant>,
+    // Whether we have already alerted about the current outage
+    alerted: bool,
+}
+
+impl QuorumWatchdog {
+    pub fn new(alert_after: Duration) -> Self {
+        Self {
+            alert_after,
+            below_quorum_since: None,
+            alerted: false,
+        }
+    }
+
+    /// Observe the currently reachable quorum members of the view `view`.
+    /// Returns an alert when the state of the quorum connectivity has changed
+    /// in a way that operators should be notified of.
+    pub fn observe(&mut self, view: SeqNo, reachable: &ReachableQuorum, now: Instant) -> Option<QuorumAlert> {
+        if !reachable.is_below_quorum() {
+            let since = self.below_quorum_since.take();
+
+            return if std::mem::replace(&mut self.alerted, false) {
+                Some(QuorumAlert::QuorumRestored {
+                    view,
+                    outage: since.map(|since| now.saturating_duration_since(since)).unwrap_or_default(),
+                })
+            } else {
+                None
+            };
+        }
+
+        let since = *self.below_quorum_since.get_or_insert(now);
+
+        let below_quorum_for = now.saturating_duration_since(since);
+
+        if self.alerted || below_quorum_for < self.alert_after {
+            return None;
+        }
+
+        self.alerted = true;
+
+        Some(QuorumAlert::QuorumUnreachable {
+            view,
+            reachable: reachable.members().clone(),
+            below_quorum_for,
+        })
+    }
+}
+
+/// Where we learn which peers we are currently connected to
+/// (for example, `Node::connected_tx_peers()`)
+pub trait ConnectivitySource: Send {
+    fn connected_peers(&self) -> Vec<NodeId>;
+}
+
+impl<F> ConnectivitySource for F where F: Fn() -> Vec<NodeId> + Send {
+    fn connected_peers(&self) -> Vec<NodeId> {
+        self()
+    }
+}
+
+/// How often we check our connectivity to the quorum
+const QUORUM_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Periodically feeds the peers reported by the installed [ConnectivitySource] to a [QuorumWatchdog].
+/// Nothing is checked until a source is installed
+pub struct QuorumConnectivityMonitor {
+    watchdog: QuorumWatchdog,
+    source: Option<Box<dyn ConnectivitySource>>,
+    last_check: Option<Instant>,
+}
+
+impl QuorumConnectivityMonitor {
+    pub fn new(alert_after: Duration) -> Self {
+        Self {
+            watchdog: QuorumWatchdog::new(alert_after),
+            source: None,
+            last_check: None,
+        }
+    }
+
+    pub fn install_source(&mut self, source: Box<dyn ConnectivitySource>) {
+        self.source = Some(source);
+    }
+
+    /// Check our connectivity to the quorum of `view`, unless we have already checked it
+    /// less than [QUORUM_CHECK_INTERVAL] ago. Returns the alert raised by the watchdog, if any
+    pub fn check(&mut self, our_id: NodeId, view: &ViewInfo, now: Instant) -> Option<QuorumAlert> {
+        let source = self.source.as_ref()?;
+
+        if self.last_check.map_or(false, |last| now.saturating_duration_since(last) < QUORUM_CHECK_INTERVAL) {
+            return None;
+        }
+
+        self.last_check = Some(now);
+
+        let reachable = view.reachable_quorum_members(our_id, &source.connected_peers());
+
+        self.watchdog.observe(view.sequence_number(), &reachable, now)
+    }
+}
+
+#[cfg(test)]
+mod watchdog_tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::{Orderable, SeqNo};
+
+    use crate::bft::sync::view::ViewInfo;
+
+    use super::{QuorumAlert, QuorumConnectivityMonitor, QuorumWatchdog};
+
+    #[test]
+    fn test_alert_fires_and_clears() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let our_id = NodeId(0);
+
+        let mut watchdog = QuorumWatchdog::new(Duration::from_secs(10));
+
+        let connected = view.reachable_quorum_members(our_id, &[NodeId(1), NodeId(2)]);
+        let partitioned = view.reachable_quorum_members(our_id, &[NodeId(1)]);
+
+        let start = Instant::now();
+
+        assert_eq!(watchdog.observe(view.sequence_number(), &connected, start), None);
+        assert_eq!(watchdog.observe(view.sequence_number(), &partitioned, start), None);
+
+        // Still within the allowed duration below quorum
+        assert_eq!(watchdog.observe(view.sequence_number(), &partitioned, start + Duration::from_secs(5)), None);
+
+        let alert = watchdog.observe(view.sequence_number(), &partitioned, start + Duration::from_secs(10));
+
+        assert_eq!(alert, Some(QuorumAlert::QuorumUnreachable {
+            view: view.sequence_number(),
+            reachable: vec![NodeId(0), NodeId(1)],
+            below_quorum_for: Duration::from_secs(10),
+        }));
+
+        // The alert only fires once per outage
+        assert_eq!(watchdog.observe(view.sequence_number(), &partitioned, start + Duration::from_secs(20)), None);
+
+        let restored = watchdog.observe(view.sequence_number(), &connected, start + Duration::from_secs(30));
+
+        assert_eq!(restored, Some(QuorumAlert::QuorumRestored {
+            view: view.sequence_number(),
+            outage: Duration::from_secs(30),
+        }));
+
+        assert_eq!(watchdog.observe(view.sequence_number(), &connected, start + Duration::from_secs(40)), None);
+    }
+
+    #[test]
+    fn test_monitor_alerts_from_the_connected_peers() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let our_id = NodeId(0);
+
+        let connected = Arc::new(Mutex::new(vec![NodeId(1), NodeId(2), NodeId(3)]));
+
+        let mut monitor = QuorumConnectivityMonitor::new(Duration::from_secs(10));
+
+        let start = Instant::now();
+
+        // Without a source of connectivity, there is nothing to check
+        connected.lock().unwrap().clear();
+
+        assert_eq!(monitor.check(our_id, &view, start + Duration::from_secs(60)), None);
+
+        monitor.install_source(Box::new({
+            let connected = connected.clone();
+
+            move || connected.lock().unwrap().clone()
+        }));
+
+        // We lose the connection to all of our peers
+        assert_eq!(monitor.check(our_id, &view, start), None);
+        assert_eq!(monitor.check(our_id, &view, start + Duration::from_secs(5)), None);
+
+        let alert = monitor.check(our_id, &view, start + Duration::from_secs(10));
+
+        assert_eq!(alert, Some(QuorumAlert::QuorumUnreachable {
+            view: view.sequence_number(),
+            reachable: vec![our_id],
+            below_quorum_for: Duration::from_secs(10),
+        }));
+
+        // The connectivity is restored, which is reported on the next check
+        *connected.lock().unwrap() = vec![NodeId(1), NodeId(2)];
+
+        assert_eq!(monitor.check(our_id, &view, start + Duration::from_millis(10500)), None);
+
+        let restored = monitor.check(our_id, &view, start + Duration::from_secs(12));
+
+        assert_eq!(restored, Some(QuorumAlert::QuorumRestored {
+            view: view.sequence_number(),
+            outage: Duration::from_secs(12),
+        }));
+    }
+}
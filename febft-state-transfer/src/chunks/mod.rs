@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serialize_serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use atlas_common::crypto::hash::{Context, Digest};
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+
+/// Describes how the serialized state of a checkpoint is split into chunks,
+/// along with the digest of each of them, so that chunks fetched from
+/// different replicas can be validated independently.
+///
+/// Replicas only agree on a manifest when it is exactly the same, so a
+/// quorum of matching manifests means the chunk digests can be trusted.
+///
+/// The checkpoint digest is produced by the execution layer, which may not
+/// digest the serialized state, so the manifest also carries the digest of
+/// the serialized state, which the reassembled state is checked against.
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateManifest {
+    seq: SeqNo,
+    // The digest of the entire checkpoint
+    digest: Digest,
+    // The digest of the serialized state
+    state_digest: Digest,
+    total_len: u64,
+    chunk_size: u64,
+    chunk_digests: Vec<Digest>,
+}
+
+impl StateManifest {
+    /// Build the manifest of the checkpoint at `seq`, with digest `digest`,
+    /// whose serialized state is `serialized`
+    pub fn new(seq: SeqNo, digest: Digest, serialized: &[u8], chunk_size: usize) -> Self {
+        let chunk_size = chunk_size.max(1);
+
+        let chunk_digests = serialized.chunks(chunk_size)
+            .map(digest_bytes)
+            .collect();
+
+        Self {
+            seq,
+            digest,
+            state_digest: digest_bytes(serialized),
+            total_len: serialized.len() as u64,
+            chunk_size: chunk_size as u64,
+            chunk_digests,
+        }
+    }
+
+    /// The digest of the entire checkpoint
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    /// The digest of the serialized state
+    pub fn state_digest(&self) -> &Digest {
+        &self.state_digest
+    }
+
+    /// The length of the serialized state
+    pub fn total_len(&self) -> u64 {
+        self.total_len
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_digests.len()
+    }
+
+    /// The offset and length of the chunk with the given index
+    pub fn chunk_range(&self, index: usize) -> (u64, u64) {
+        let offset = index as u64 * self.chunk_size;
+
+        (offset, self.chunk_size.min(self.total_len - offset))
+    }
+
+    /// The index of the chunk which starts at the given offset and has the given length, if any.
+    /// Only ranges which exactly match one of the chunks of the manifest have an index
+    pub fn chunk_index(&self, offset: u64, len: u64) -> Option<usize> {
+        if offset % self.chunk_size != 0 {
+            return None;
+        }
+
+        let index = (offset / self.chunk_size) as usize;
+
+        if index >= self.chunk_count() || self.chunk_range(index) != (offset, len) {
+            return None;
+        }
+
+        Some(index)
+    }
+}
+
+impl Orderable for StateManifest {
+    fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+}
+
+/// Reassembles the serialized state of a checkpoint from chunks fetched
+/// from multiple replicas in parallel.
+///
+/// Each chunk is requested from a single replica and validated against the
+/// agreed manifest as soon as it is received, so a faulty replica can only
+/// delay the transfer of the chunks it was assigned.
+pub struct ChunkedStateReceiver {
+    manifest: StateManifest,
+    data: Vec<u8>,
+    received: Vec<bool>,
+    // The replicas we can fetch chunks from
+    peers: Vec<NodeId>,
+    // The replica each of the missing chunks was requested from
+    assigned: BTreeMap<usize, NodeId>,
+}
+
+impl ChunkedStateReceiver {
+    /// Create a receiver for the state described by `manifest`, which
+    /// will be fetched from the given replicas
+    pub fn new(manifest: StateManifest, peers: Vec<NodeId>) -> Self {
+        Self {
+            data: vec![0; manifest.total_len() as usize],
+            received: vec![false; manifest.chunk_count()],
+            peers,
+            assigned: Default::default(),
+            manifest,
+        }
+    }
+
+    pub fn manifest(&self) -> &StateManifest {
+        &self.manifest
+    }
+
+    /// Assign the chunks that are still missing and not yet assigned to our
+    /// replicas, in a round robin fashion.
+    /// Returns the chunks (offset and length) that should be requested from each replica
+    pub fn assign(&mut self) -> BTreeMap<NodeId, Vec<(u64, u64)>> {
+        let mut requests = BTreeMap::new();
+
+        if self.peers.is_empty() {
+            return requests;
+        }
+
+        let missing = (0..self.manifest.chunk_count())
+            .filter(|index| !self.received[*index] && !self.assigned.contains_key(index))
+            .collect::<Vec<_>>();
+
+        for (position, index) in missing.into_iter().enumerate() {
+            let peer = self.peers[position % self.peers.len()];
+
+            self.assigned.insert(index, peer);
+
+            requests.entry(peer).or_insert_with(Vec::new).push(self.manifest.chunk_range(index));
+        }
+
+        requests
+    }
+
+    /// Receive a chunk from the given replica, validating it against the manifest
+    pub fn receive_chunk(&mut self, from: NodeId, offset: u64, data: &[u8]) -> Result<(), ChunkError> {
+        let index = self.manifest.chunk_index(offset, data.len() as u64)
+            .ok_or(ChunkError::UnknownChunk { offset, len: data.len() as u64 })?;
+
+        if self.received[index] {
+            return Ok(());
+        }
+
+        if self.assigned.get(&index) != Some(&from) {
+            return Err(ChunkError::NotAssigned { from, offset });
+        }
+
+        if digest_bytes(data) != self.manifest.chunk_digests[index] {
+            return Err(ChunkError::ChunkDigestMismatch { from, offset });
+        }
+
+        let start = offset as usize;
+
+        self.data[start..start + data.len()].copy_from_slice(data);
+        self.received[index] = true;
+        self.assigned.remove(&index);
+
+        Ok(())
+    }
+
+    /// The chunks (offset and length) currently requested from the given replica
+    pub fn assigned_to(&self, peer: NodeId) -> Vec<(u64, u64)> {
+        self.assigned.iter()
+            .filter(|(_, assigned)| **assigned == peer)
+            .map(|(index, _)| self.manifest.chunk_range(*index))
+            .collect()
+    }
+
+    /// Stop fetching chunks from the given replica, so that the chunks
+    /// assigned to it can be assigned to the other replicas
+    pub fn mark_faulty(&mut self, faulty: NodeId) {
+        self.peers.retain(|peer| *peer != faulty);
+        self.assigned.retain(|_, peer| *peer != faulty);
+    }
+
+    /// Whether there are still replicas we can fetch chunks from
+    pub fn has_peers(&self) -> bool {
+        !self.peers.is_empty()
+    }
+
+    /// The amount of chunks received so far
+    pub fn received_chunks(&self) -> usize {
+        self.received.iter().filter(|received| **received).count()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.received.iter().all(|received| *received)
+    }
+
+    /// Take the reassembled serialized state, checking that it matches the state digest of the manifest.
+    pub fn into_state_bytes(self) -> Result<Vec<u8>, ChunkError> {
+        if !self.is_complete() {
+            return Err(ChunkError::Incomplete);
+        }
+
+        if digest_bytes(&self.data) != *self.manifest.state_digest() {
+            return Err(ChunkError::StateDigestMismatch);
+        }
+
+        Ok(self.data)
+    }
+}
+
+fn digest_bytes(bytes: &[u8]) -> Digest {
+    let mut ctx = Context::new();
+
+    ctx.update(bytes);
+
+    ctx.finish()
+}
+
+#[derive(Error, Debug)]
+pub enum ChunkError {
+    #[error("Received a chunk with offset {offset} and len {len} which is not part of the manifest")]
+    UnknownChunk {
+        offset: u64,
+        len: u64,
+    },
+    #[error("Received the chunk with offset {offset} from {from:?}, which it was not requested from")]
+    NotAssigned {
+        from: NodeId,
+        offset: u64,
+    },
+    #[error("The chunk with offset {offset} received from {from:?} does not match the manifest")]
+    ChunkDigestMismatch {
+        from: NodeId,
+        offset: u64,
+    },
+    #[error("Not all the chunks of the state have been received")]
+    Incomplete,
+    #[error("The reassembled state does not match the state digest of the manifest")]
+    StateDigestMismatch,
+}
+
+#[cfg(test)]
+mod chunks_tests {
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use super::{ChunkedStateReceiver, digest_bytes, StateManifest};
+
+    #[test]
+    fn test_state_is_reassembled_from_multiple_peers() {
+        let serialized: Vec<u8> = (0..100u8).collect();
+
+        // The checkpoint digest comes from the execution layer, and is not a digest of the serialized state
+        let checkpoint_digest = Digest::from_bytes(&[1; Digest::LENGTH]).unwrap();
+
+        let manifest = StateManifest::new(SeqNo::ZERO.next(), checkpoint_digest, &serialized, 32);
+
+        assert_eq!(*manifest.state_digest(), digest_bytes(&serialized));
+        assert_eq!(manifest.chunk_count(), 4);
+        assert_eq!(manifest.chunk_range(3), (96, 4));
+
+        // Only the exact chunks of the manifest can be requested
+        assert_eq!(manifest.chunk_index(32, 32), Some(1));
+        assert!(manifest.chunk_index(16, 32).is_none());
+        assert!(manifest.chunk_index(96, 32).is_none());
+        assert!(manifest.chunk_index(128, 32).is_none());
+
+        let mut receiver = ChunkedStateReceiver::new(manifest, vec![NodeId(1), NodeId(2), NodeId(3)]);
+
+        let requests = receiver.assign();
+
+        assert_eq!(requests[&NodeId(1)], vec![(0, 32), (96, 4)]);
+        assert_eq!(requests[&NodeId(2)], vec![(32, 32)]);
+        assert_eq!(requests[&NodeId(3)], vec![(64, 32)]);
+
+        // A corrupted chunk is rejected
+        let mut corrupted = serialized[32..64].to_vec();
+        corrupted[0] ^= 1;
+
+        assert!(receiver.receive_chunk(NodeId(2), 32, &corrupted).is_err());
+
+        // So is a chunk sent by a replica it was not requested from
+        assert!(receiver.receive_chunk(NodeId(3), 0, &serialized[0..32]).is_err());
+
+        // The chunks of the faulty replica are assigned to the others
+        receiver.mark_faulty(NodeId(2));
+
+        let requests = receiver.assign();
+
+        assert_eq!(requests[&NodeId(1)], vec![(32, 32)]);
+        assert_eq!(receiver.assigned_to(NodeId(1)), vec![(0, 32), (32, 32), (96, 4)]);
+        assert!(receiver.assigned_to(NodeId(2)).is_empty());
+
+        for (peer, chunks) in [(NodeId(1), vec![(0, 32), (96, 4), (32, 32)]), (NodeId(3), vec![(64, 32)])] {
+            for (offset, len) in chunks {
+                let (start, end) = (offset as usize, (offset + len) as usize);
+
+                assert!(receiver.receive_chunk(peer, offset, &serialized[start..end]).is_ok());
+            }
+        }
+
+        assert!(receiver.is_complete());
+        assert_eq!(receiver.into_state_bytes().unwrap(), serialized);
+    }
+}
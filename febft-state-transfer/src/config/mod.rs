@@ -1,5 +1,112 @@
+use std::sync::Arc;
 use std::time::Duration;
 
+#[cfg(feature = "serialize_serde")]
+use serde::Deserialize;
+
+use atlas_common::crypto::hash::Digest;
+
+use crate::metrics::MetricsSink;
+use crate::serving::StateServingConfig;
+
+#[cfg_attr(feature = "serialize_serde", derive(Deserialize))]
+#[cfg_attr(feature = "serialize_serde", serde(default))]
 pub struct StateTransferConfig {
-    pub timeout_duration: Duration
-}
\ No newline at end of file
+    /// The base timeout for the requests of the CID round
+    pub timeout_duration: Duration,
+    /// The base timeout for the requests of the state round.
+    /// Since the state can be very large, this should be much larger than the CID one
+    pub state_timeout_duration: Duration,
+    /// The maximum value the timeouts of both rounds can grow to, as they are
+    /// doubled every time a round times out
+    pub max_timeout: Duration,
+    /// The sink to send the metrics to. When not provided, `atlas_metrics` is used
+    #[cfg_attr(feature = "serialize_serde", serde(skip))]
+    pub metrics_sink: Option<Arc<dyn MetricsSink>>,
+    /// How we serve our state to other recovering replicas
+    pub serving_config: StateServingConfig,
+    /// The function the execution layer uses to digest the checkpoints it delivers.
+    /// When provided, the digest of every checkpoint delivered by the application is
+    /// recomputed with it and checked against the digest the checkpoint carries, before
+    /// storing and serving it. Otherwise, checkpoints are not verified, as a digest
+    /// computed in any other way would not match
+    #[cfg_attr(feature = "serialize_serde", serde(skip))]
+    pub checkpoint_digest: Option<Arc<dyn CheckpointDigest>>,
+    /// When set, recovering replicas first agree on the manifest of the latest checkpoint
+    /// and then fetch its state in chunks of this size from multiple replicas in parallel.
+    /// Otherwise, every replica sends us its full state
+    pub state_chunk_size: Option<usize>,
+}
+
+impl StateTransferConfig {
+    pub fn new(timeout_duration: Duration) -> Self {
+        Self {
+            timeout_duration,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_state_timeout(mut self, state_timeout_duration: Duration) -> Self {
+        self.state_timeout_duration = state_timeout_duration;
+
+        self
+    }
+
+    pub fn with_max_timeout(mut self, max_timeout: Duration) -> Self {
+        self.max_timeout = max_timeout;
+
+        self
+    }
+
+    pub fn with_metrics_sink(mut self, metrics_sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics_sink = Some(metrics_sink);
+
+        self
+    }
+
+    pub fn with_serving_config(mut self, serving_config: StateServingConfig) -> Self {
+        self.serving_config = serving_config;
+
+        self
+    }
+
+    pub fn with_checkpoint_verification(mut self, checkpoint_digest: Arc<dyn CheckpointDigest>) -> Self {
+        self.checkpoint_digest = Some(checkpoint_digest);
+
+        self
+    }
+
+    pub fn with_state_chunk_size(mut self, state_chunk_size: usize) -> Self {
+        self.state_chunk_size = Some(state_chunk_size);
+
+        self
+    }
+}
+
+impl Default for StateTransferConfig {
+    fn default() -> Self {
+        Self {
+            timeout_duration: Duration::from_secs(3),
+            state_timeout_duration: Duration::from_secs(30),
+            max_timeout: Duration::from_secs(300),
+            metrics_sink: None,
+            serving_config: StateServingConfig::default(),
+            checkpoint_digest: None,
+            state_chunk_size: None,
+        }
+    }
+}
+
+/// Computes the digest of a checkpoint from its serialized state.
+///
+/// This must be the same function the execution layer uses to digest the
+/// checkpoints it delivers to the state transfer protocol.
+pub trait CheckpointDigest: Send + Sync {
+    fn digest(&self, serialized_state: &[u8]) -> Digest;
+}
+
+impl<F> CheckpointDigest for F where F: Fn(&[u8]) -> Digest + Send + Sync {
+    fn digest(&self, serialized_state: &[u8]) -> Digest {
+        self(serialized_state)
+    }
+}
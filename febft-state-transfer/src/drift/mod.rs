@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::SeqNo;
+
+/// A divergence between the checkpoint digests reported by the replicas
+/// for a given sequence number.
+#[derive(Debug, Clone)]
+pub struct DigestDivergence {
+    seq: SeqNo,
+    // The digest reported by the largest amount of replicas
+    majority_digest: Digest,
+    // The replicas that reported a digest different from the majority
+    divergent: Vec<NodeId>,
+}
+
+impl DigestDivergence {
+    /// The sequence number of the checkpoint where the divergence was detected
+    pub fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+
+    pub fn majority_digest(&self) -> &Digest {
+        &self.majority_digest
+    }
+
+    /// The replicas whose state does not match the majority
+    pub fn divergent_replicas(&self) -> &Vec<NodeId> {
+        &self.divergent
+    }
+}
+
+/// How far ahead of our latest checkpoint (in sequence numbers) we accept
+/// checkpoint digests from other replicas, by default
+pub const DEFAULT_DIGEST_WINDOW: u32 = 16384;
+
+/// Collects the checkpoint digests announced by the replicas,
+/// in order to detect replicas whose executed state has drifted
+/// (for example, due to non deterministic execution in the application)
+///
+/// Only digests from members of the quorum, for checkpoints within a window
+/// ahead of our latest checkpoint, are accepted, so other nodes can't make
+/// us keep an unbounded amount of digests.
+pub struct DigestDriftDetector {
+    // How far ahead of `low` we accept digests for
+    window: u32,
+    // Digests of checkpoints older than this one are no longer kept
+    low: SeqNo,
+    digests: BTreeMap<SeqNo, BTreeMap<NodeId, Digest>>,
+}
+
+impl DigestDriftDetector {
+    pub fn new(window: u32) -> Self {
+        Self {
+            window,
+            low: SeqNo::ZERO,
+            digests: Default::default(),
+        }
+    }
+
+    /// Register the checkpoint digest announced by a given replica, which must be
+    /// a member of the quorum `members`
+    pub fn register_digest(&mut self, from: NodeId, seq: SeqNo, digest: Digest, members: &[NodeId]) -> Result<(), DriftError> {
+        if !members.contains(&from) {
+            return Err(DriftError::NotAMember { from });
+        }
+
+        let high = SeqNo::from(u32::from(self.low).saturating_add(self.window));
+
+        if seq < self.low || seq > high {
+            return Err(DriftError::OutsideWindow { seq, low: self.low, high });
+        }
+
+        self.digests.entry(seq).or_insert_with(BTreeMap::new).insert(from, digest);
+
+        Ok(())
+    }
+
+    /// Check if the digests registered for the given sequence number diverge.
+    /// Only returns a divergence when at least `quorum` digests have been registered
+    /// for that sequence number, so the majority is meaningful.
+    pub fn check(&self, seq: SeqNo, quorum: usize) -> Option<DigestDivergence> {
+        let digests = self.digests.get(&seq)?;
+
+        if digests.len() < quorum {
+            return None;
+        }
+
+        let mut counts: Vec<(&Digest, usize)> = Vec::new();
+
+        for digest in digests.values() {
+            match counts.iter_mut().find(|(counted, _)| *counted == digest) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((digest, 1)),
+            }
+        }
+
+        if counts.len() <= 1 {
+            return None;
+        }
+
+        let (majority_digest, _) = counts.into_iter().max_by_key(|(_, count)| *count)?;
+
+        let divergent = digests.iter()
+            .filter(|(_, digest)| *digest != majority_digest)
+            .map(|(node, _)| *node)
+            .collect();
+
+        Some(DigestDivergence {
+            seq,
+            majority_digest: majority_digest.clone(),
+            divergent,
+        })
+    }
+
+    /// Discard the digests of checkpoints older than `seq`, moving the window forward
+    pub fn discard_older_than(&mut self, seq: SeqNo) {
+        if seq > self.low {
+            self.low = seq;
+        }
+
+        self.digests = self.digests.split_off(&self.low);
+    }
+}
+
+impl Default for DigestDriftDetector {
+    fn default() -> Self {
+        Self::new(DEFAULT_DIGEST_WINDOW)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DriftError {
+    #[error("Received a checkpoint digest from {from:?}, which is not a member of the quorum")]
+    NotAMember {
+        from: NodeId,
+    },
+    #[error("Received a checkpoint digest for seq {seq:?}, outside of the accepted window [{low:?}, {high:?}]")]
+    OutsideWindow {
+        seq: SeqNo,
+        low: SeqNo,
+        high: SeqNo,
+    },
+}
+
+#[cfg(test)]
+mod drift_tests {
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use super::{DigestDriftDetector, DriftError};
+
+    #[test]
+    fn test_divergent_digest_is_flagged() {
+        let mut detector = DigestDriftDetector::default();
+
+        let members: Vec<_> = (0..4).map(NodeId).collect();
+
+        let good_digest = Digest::from_bytes(&[1; Digest::LENGTH]).unwrap();
+        let bad_digest = Digest::from_bytes(&[2; Digest::LENGTH]).unwrap();
+
+        let first_checkpoint = SeqNo::ZERO.next();
+        let second_checkpoint = first_checkpoint.next();
+
+        for node in 0..4 {
+            detector.register_digest(NodeId(node), first_checkpoint, good_digest.clone(), &members).unwrap();
+        }
+
+        for node in 0..3 {
+            detector.register_digest(NodeId(node), second_checkpoint, good_digest.clone(), &members).unwrap();
+        }
+
+        detector.register_digest(NodeId(3), second_checkpoint, bad_digest, &members).unwrap();
+
+        assert!(detector.check(first_checkpoint, 3).is_none());
+
+        let divergence = detector.check(second_checkpoint, 3).expect("Divergence should have been detected");
+
+        assert_eq!(divergence.sequence_number(), second_checkpoint);
+        assert_eq!(divergence.majority_digest(), &good_digest);
+        assert_eq!(divergence.divergent_replicas(), &vec![NodeId(3)]);
+    }
+
+    #[test]
+    fn test_digests_from_non_members_or_outside_the_window_are_rejected() {
+        let mut detector = DigestDriftDetector::new(10);
+
+        let members: Vec<_> = (0..4).map(NodeId).collect();
+
+        let digest = Digest::from_bytes(&[1; Digest::LENGTH]).unwrap();
+
+        assert!(matches!(detector.register_digest(NodeId(4), SeqNo::from(5u32), digest.clone(), &members),
+            Err(DriftError::NotAMember { .. })));
+
+        assert!(matches!(detector.register_digest(NodeId(0), SeqNo::from(11u32), digest.clone(), &members),
+            Err(DriftError::OutsideWindow { .. })));
+
+        assert!(detector.register_digest(NodeId(0), SeqNo::from(10u32), digest.clone(), &members).is_ok());
+
+        // Once we move on to a newer checkpoint, the window moves along with it
+        detector.discard_older_than(SeqNo::from(10u32));
+
+        assert!(matches!(detector.register_digest(NodeId(0), SeqNo::from(5u32), digest.clone(), &members),
+            Err(DriftError::OutsideWindow { .. })));
+        assert!(detector.register_digest(NodeId(0), SeqNo::from(20u32), digest, &members).is_ok());
+    }
+}
@@ -1,6 +1,7 @@
 #![feature(inherent_associated_types)]
 
-use std::cmp::Ordering;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -33,16 +34,24 @@ use atlas_core::timeouts::{RqTimeout, TimeoutKind, Timeouts};
 use atlas_smr_application::app::Application;
 use atlas_smr_application::serialize::ApplicationData;
 use atlas_smr_application::state::monolithic_state::{InstallStateMessage, MonolithicState};
-use atlas_metrics::metrics::{metric_duration, metric_duration_end, metric_duration_start, metric_increment, metric_store_count};
 
-use crate::config::StateTransferConfig;
+use crate::chunks::{ChunkedStateReceiver, StateManifest};
+use crate::config::{CheckpointDigest, StateTransferConfig};
+use crate::drift::{DigestDivergence, DigestDriftDetector};
 use crate::message::{CstMessage, CstMessageKind};
+use crate::quorum::CstQuorums;
 use crate::message::serialize::CSTMsg;
-use crate::metrics::{STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID, STATE_TRANSFER_TIME_ID, TOTAL_STATE_INSTALLED_ID, TOTAL_STATE_TRANSFERED_ID, TOTAL_STATE_WAIT_ID};
+use crate::metrics::{AtlasMetricsSink, CHECKPOINTS_OVERLAPPED_ID, MetricsSink, STATE_REQUESTS_REJECTED_ID, STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID, STATE_TRANSFER_TIME_ID, TOTAL_STATE_INSTALLED_ID, TOTAL_STATE_TRANSFERED_ID, TOTAL_STATE_WAIT_ID};
+use crate::serving::StateServingLimiter;
 
 pub mod message;
 pub mod config;
 pub mod metrics;
+pub mod drift;
+pub mod serving;
+pub mod replay;
+pub mod chunks;
+pub mod quorum;
 
 /// The state of the checkpoint
 pub enum CheckpointState<D> {
@@ -69,6 +78,8 @@ enum ProtoPhase<S> {
     WaitingCheckpoint(Vec<StoredMessage<CstMessage<S>>>),
     ReceivingCid(usize),
     ReceivingState(usize),
+    ReceivingManifest(usize),
+    ReceivingChunks(ChunkedStateReceiver),
 }
 
 impl<S> Debug for ProtoPhase<S> {
@@ -86,10 +97,68 @@ impl<S> Debug for ProtoPhase<S> {
             ProtoPhase::ReceivingState(size) => {
                 write!(f, "Receiving state phase {} responses", size)
             }
+            ProtoPhase::ReceivingManifest(size) => {
+                write!(f, "Receiving state manifest phase {} responses", size)
+            }
+            ProtoPhase::ReceivingChunks(receiver) => {
+                write!(f, "Receiving state chunks phase, manifest {:?}", receiver.manifest().sequence_number())
+            }
+        }
+    }
+}
+
+impl<S> ProtoPhase<S> {
+    fn name(&self) -> &'static str {
+        match self {
+            ProtoPhase::Init => "Init",
+            ProtoPhase::WaitingCheckpoint(_) => "WaitingCheckpoint",
+            ProtoPhase::ReceivingCid(_) => "ReceivingCid",
+            ProtoPhase::ReceivingState(_) => "ReceivingState",
+            ProtoPhase::ReceivingManifest(_) => "ReceivingManifest",
+            ProtoPhase::ReceivingChunks(_) => "ReceivingChunks",
         }
     }
 }
 
+/// A snapshot of the progress of the CST protocol, meant to be exposed
+/// to operators or supervising components
+#[derive(Debug, Clone)]
+pub struct CstProgressReport {
+    phase: &'static str,
+    replies: usize,
+    target: usize,
+    seq: SeqNo,
+}
+
+impl CstProgressReport {
+    /// The name of the phase the protocol is currently in
+    pub fn phase(&self) -> &'static str {
+        self.phase
+    }
+
+    /// The amount of replies received in the current round.
+    /// When fetching the state in chunks, this is the amount of chunks received
+    pub fn replies(&self) -> usize {
+        self.replies
+    }
+
+    /// The amount of replies the current round is waiting for
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    /// The sequence number of the current CST request
+    pub fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+}
+
+impl std::fmt::Display for CstProgressReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}/{} replies (CST Seq {:?})", self.phase, self.replies, self.target, self.seq)
+    }
+}
+
 /// Contains state used by a recovering node.
 ///
 /// Cloning this is better than it was because of the read only checkpoint,
@@ -117,18 +186,329 @@ impl<S> RecoveryState<S> {
     }
 }
 
+/// The timeout of a given round of the CST protocol.
+/// It is doubled every time the round times out (up to `max`), and reset to the base
+/// value once the round completes
+#[derive(Debug, Clone, Copy)]
+struct PhaseTimeout {
+    base: Duration,
+    max: Duration,
+    curr: Duration,
+}
+
+impl PhaseTimeout {
+    fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max: max.max(base),
+            curr: base,
+        }
+    }
+
+    fn current(&self) -> Duration {
+        self.curr
+    }
+
+    fn back_off(&mut self) {
+        self.curr = self.curr.saturating_mul(2).min(self.max);
+    }
+
+    fn reset(&mut self) {
+        self.curr = self.base;
+    }
+}
+
+/// The timeouts of the requests of each round of the CST protocol
+#[derive(Debug, Clone, Copy)]
+struct RoundTimeouts {
+    // The timeout for the requests of the CID round
+    cid: PhaseTimeout,
+    // The timeout for the requests of the state round
+    // (which are much heavier than the CID ones), including
+    // when the state is fetched in chunks
+    state: PhaseTimeout,
+}
+
+impl RoundTimeouts {
+    fn new(cid: Duration, state: Duration, max: Duration) -> Self {
+        Self {
+            cid: PhaseTimeout::new(cid, max),
+            state: PhaseTimeout::new(state, max),
+        }
+    }
+
+    fn of_phase<S>(&mut self, phase: &ProtoPhase<S>) -> Option<&mut PhaseTimeout> {
+        match phase {
+            ProtoPhase::ReceivingCid(_) => Some(&mut self.cid),
+            ProtoPhase::ReceivingState(_) | ProtoPhase::ReceivingManifest(_) | ProtoPhase::ReceivingChunks(_) => Some(&mut self.state),
+            ProtoPhase::Init | ProtoPhase::WaitingCheckpoint(_) => None,
+        }
+    }
+
+    /// The timeout to register for the requests of the given phase, if it is waiting for replies
+    fn current<S>(&mut self, phase: &ProtoPhase<S>) -> Option<Duration> {
+        self.of_phase(phase).map(|timeout| timeout.current())
+    }
+
+    /// The requests of the given phase timed out, so back off its timeout
+    /// and return how the round should be retried
+    fn timed_out<S>(&mut self, phase: &ProtoPhase<S>) -> CstStatus<S> {
+        let status = match phase {
+            ProtoPhase::ReceivingCid(_) => CstStatus::RequestStateCid,
+            ProtoPhase::ReceivingState(_) | ProtoPhase::ReceivingManifest(_) | ProtoPhase::ReceivingChunks(_) => CstStatus::RequestState,
+            // ignore timeouts if not receiving any kind
+            // of state from peer nodes
+            ProtoPhase::Init | ProtoPhase::WaitingCheckpoint(_) => return CstStatus::Nil,
+        };
+
+        if let Some(timeout) = self.of_phase(phase) {
+            timeout.back_off();
+        }
+
+        status
+    }
+}
+
 #[derive(Debug)]
 struct ReceivedState<S> {
     count: usize,
     state: RecoveryState<S>,
 }
 
+/// The states received in the state round of the CST protocol, grouped by their digest
+#[derive(Debug)]
+struct ReceivedStates<S> {
+    states: HashMap<Digest, ReceivedState<S>>,
+}
+
+/// The outcome of the state round of the CST protocol, after a given amount of replies
+enum StateRoundResult<S> {
+    /// We need more replies before we can settle on a state
+    Running,
+    /// No state was vouched for by enough replicas
+    NoQuorum,
+    /// Enough replicas replied with the state with the given digest
+    Agreed(Digest, RecoveryState<S>),
+}
+
+impl<S> ReceivedStates<S> {
+    fn new() -> Self {
+        Self {
+            states: collections::hash_map(),
+        }
+    }
+
+    /// Register the state `from` replied with, unless it has already replied in this round.
+    /// Returns false if the reply was ignored
+    fn receive(&mut self, replies: &mut RoundReplies, from: NodeId, state: RecoveryState<S>) -> bool {
+        if !replies.register(from) {
+            return false;
+        }
+
+        let state_digest = state.checkpoint.digest().clone();
+
+        match self.states.get_mut(&state_digest) {
+            Some(current_state) => {
+                // The state is the same, but if the decision log is larger we want to store
+                // the newest one. We can do this since to be in the decision log, a replica must
+                // have all of the messages from at least 2f+1 replicas, so we know that the log is valid
+                if state.checkpoint().sequence_number() > current_state.state.checkpoint().sequence_number() {
+                    current_state.state = state;
+                }
+
+                current_state.count += 1;
+            }
+            None => {
+                self.states.insert(state_digest, ReceivedState { count: 1, state });
+            }
+        }
+
+        true
+    }
+
+    fn contains(&self, digest: &Digest) -> bool {
+        self.states.contains_key(digest)
+    }
+
+    /// Settle on the state vouched for by a weak quorum of the `replies` replies received so far.
+    /// A state is only accepted when at least one correct replica vouches for it, so
+    /// no combination of the replies of `f` faulty replicas can get a state accepted
+    fn conclude(&mut self, replies: usize, weak_quorum: usize, strong_quorum: usize) -> StateRoundResult<S> {
+        let agreed = self.states.iter()
+            .filter(|(_, state)| state.count >= weak_quorum)
+            .map(|(digest, _)| digest.clone())
+            .next();
+
+        match agreed {
+            Some(digest) => {
+                let state = self.states.remove(&digest).unwrap().state;
+
+                // NOTE: clear saved states when we return;
+                // this is important, because each state
+                // may be several GBs in size
+                self.states.clear();
+
+                StateRoundResult::Agreed(digest, state)
+            }
+            None if replies >= strong_quorum => {
+                self.states.clear();
+
+                StateRoundResult::NoQuorum
+            }
+            None => StateRoundResult::Running,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.states.clear();
+    }
+}
+
+/// The manifest of the checkpoint we are serving, along with its serialized state
+/// (when it is small enough to be kept), so we don't have to serialize the state
+/// again for every chunk request
+struct ServedChunks {
+    manifest: StateManifest,
+    serialized: Option<Vec<u8>>,
+}
+
+/// The replicas that have already replied in the current round of the CST protocol.
+/// Only the first reply of each replica is taken into account, so a faulty replica
+/// can't inflate the count of a given state (or cid) by sending it multiple times,
+/// nor vote for more than one state by sending conflicting replies
+#[derive(Debug, Default)]
+struct RoundReplies {
+    replied: BTreeSet<NodeId>,
+}
+
+impl RoundReplies {
+    /// Register a reply from the given replica.
+    /// Returns false if this replica has already replied in this round
+    fn register(&mut self, from: NodeId) -> bool {
+        self.replied.insert(from)
+    }
+
+    /// Whether the given replica has already replied in this round
+    fn has_replied(&self, from: &NodeId) -> bool {
+        self.replied.contains(from)
+    }
+
+    fn clear(&mut self) {
+        self.replied.clear();
+    }
+}
+
+/// The replicas that replied that they were too busy to serve us their state in the
+/// current round, along with the instant at which they told us to ask them again
+#[derive(Debug, Default)]
+struct BusyReplicas {
+    retry_at: BTreeMap<NodeId, Instant>,
+}
+
+impl BusyReplicas {
+    /// Register that the given replica is busy and should be asked again after `retry_after`
+    fn busy(&mut self, from: NodeId, retry_after: Duration, now: Instant) {
+        self.retry_at.insert(from, now + retry_after);
+    }
+
+    /// Take the replicas which should be asked again by `now`
+    fn take_due(&mut self, now: Instant) -> Vec<NodeId> {
+        let due: Vec<NodeId> = self.retry_at.iter()
+            .filter(|(_, retry_at)| **retry_at <= now)
+            .map(|(node, _)| *node)
+            .collect();
+
+        for node in &due {
+            self.retry_at.remove(node);
+        }
+
+        due
+    }
+
+    fn clear(&mut self) {
+        self.retry_at.clear();
+    }
+}
+
 #[derive(Debug)]
 struct ReceivedStateCid {
     cid: SeqNo,
     count: usize,
 }
 
+/// The checkpoint sequence numbers received in the CID round of the CST protocol,
+/// grouped by the digest of their checkpoint
+#[derive(Debug)]
+struct ReceivedStateCids {
+    cids: HashMap<Digest, ReceivedStateCid>,
+}
+
+/// The outcome of the CID round of the CST protocol, after a given amount of replies
+#[derive(Debug, PartialEq)]
+enum CidRoundResult {
+    /// We need more replies before we can settle on a checkpoint
+    Running,
+    /// A strong quorum of replicas agreed on the checkpoint with the given sequence number and digest
+    Agreed(SeqNo, Digest),
+    /// A strong quorum of replicas replied, and none of them has a checkpoint
+    Blank,
+}
+
+impl ReceivedStateCids {
+    fn new() -> Self {
+        Self {
+            cids: collections::hash_map(),
+        }
+    }
+
+    /// Register the checkpoint `from` replied with, unless it has already replied in this round.
+    /// Returns false if the reply was ignored
+    fn receive(&mut self, replies: &mut RoundReplies, from: NodeId, state_cid: Option<&(SeqNo, Digest)>) -> bool {
+        if !replies.register(from) {
+            return false;
+        }
+
+        if let Some((cid, digest)) = state_cid {
+            let received_state_cid = self.cids.entry(digest.clone()).or_insert_with(|| {
+                ReceivedStateCid {
+                    cid: *cid,
+                    count: 0,
+                }
+            });
+
+            if *cid > received_state_cid.cid {
+                // A newer checkpoint with the same state, only the replicas that have it count
+                received_state_cid.cid = *cid;
+                received_state_cid.count = 1;
+            } else if *cid == received_state_cid.cid {
+                received_state_cid.count += 1;
+            }
+        }
+
+        true
+    }
+
+    /// Settle on the checkpoint vouched for by a strong quorum of the `replies` replies received so far
+    fn conclude(&self, replies: usize, strong_quorum: usize) -> CidRoundResult {
+        if replies < strong_quorum {
+            return CidRoundResult::Running;
+        }
+
+        // we don't need the latest cid to be available in at least
+        // f+1 replicas since the replica has the proof that the system
+        // has decided
+        match self.cids.iter().max_by_key(|(_, cid)| cid.count) {
+            Some((digest, cid)) if cid.count >= strong_quorum => CidRoundResult::Agreed(cid.cid, digest.clone()),
+            Some(_) => CidRoundResult::Running,
+            None => CidRoundResult::Blank,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.cids.clear();
+    }
+}
+
 // NOTE: in this module, we may use cid interchangeably with
 // consensus sequence number
 /// The collaborative state transfer algorithm.
@@ -139,19 +519,46 @@ pub struct CollabStateTransfer<S, NT, PL>
     where S: MonolithicState + 'static {
     curr_seq: SeqNo,
     current_checkpoint_state: CheckpointState<S>,
-    base_timeout: Duration,
-    curr_timeout: Duration,
+    // The timeouts for the requests of each round
+    round_timeouts: RoundTimeouts,
     timeouts: Timeouts,
     // NOTE: remembers whose replies we have
     // received already, to avoid replays
     //voted: HashSet<NodeId>,
     node: Arc<NT>,
-    received_states: HashMap<Digest, ReceivedState<S>>,
-    received_state_ids: HashMap<Digest, ReceivedStateCid>,
+    received_states: ReceivedStates<S>,
+    received_state_ids: ReceivedStateCids,
+    // The replicas that replied with each of the received manifests
+    received_manifests: HashMap<Digest, (StateManifest, Vec<NodeId>)>,
+    // The replicas that have replied to our current request
+    round_replies: RoundReplies,
+    // The replicas that were too busy to serve us their state, and when to ask them again
+    busy_replicas: BusyReplicas,
+    // The sequence number and digest of the checkpoint agreed on in the last CID round
+    agreed_checkpoint: Option<(SeqNo, Digest)>,
+    // The amount of replies our current request is waiting for
+    round_target: usize,
     phase: ProtoPhase<S>,
 
     install_channel: ChannelSyncTx<InstallStateMessage<S>>,
 
+    // The checkpoint digests announced by the other replicas
+    drift_detector: DigestDriftDetector,
+
+    // Limits how many states we are serving to other replicas at the same time
+    state_serving: StateServingLimiter<StoredMessage<CstMessage<S>>>,
+
+    // Where we emit our metrics to
+    metrics: Arc<dyn MetricsSink>,
+
+    // The digest function of the execution layer, used to verify the checkpoints delivered by the application
+    checkpoint_digest: Option<Arc<dyn CheckpointDigest>>,
+
+    // The size of the chunks the state is fetched in, when fetching it from multiple replicas
+    state_chunk_size: Option<usize>,
+    // The chunks of our latest checkpoint, built when a replica first requests them
+    served_chunks: Option<ServedChunks>,
+
     /// Persistent logging for the state transfer protocol.
     persistent_log: PL,
 }
@@ -246,6 +653,10 @@ impl<S, NT, PL> StateTransferProtocol<S, NT, PL> for CollabStateTransfer<S, NT,
     }
 
     fn poll(&mut self) -> Result<STPollResult<CstM<Self::Serialization>>> {
+        self.serve_queued_state_requests();
+
+        self.retry_busy_replicas();
+
         Ok(STPollResult::ReceiveMsg)
     }
 
@@ -267,6 +678,16 @@ impl<S, NT, PL> StateTransferProtocol<S, NT, PL> for CollabStateTransfer<S, NT,
 
                 return Ok(());
             }
+            CstMessageKind::RequestStateManifest | CstMessageKind::RequestStateChunks { .. } => {
+                self.process_request_state(header, message);
+
+                return Ok(());
+            }
+            CstMessageKind::CheckpointDigest(seq, digest) => {
+                self.process_checkpoint_digest(view, header.from(), *seq, digest.clone());
+
+                return Ok(());
+            }
             _ => {}
         }
 
@@ -304,6 +725,16 @@ impl<S, NT, PL> StateTransferProtocol<S, NT, PL> for CollabStateTransfer<S, NT,
 
                 return Ok(STResult::StateTransferRunning);
             }
+            CstMessageKind::RequestStateManifest | CstMessageKind::RequestStateChunks { .. } => {
+                self.process_request_state(header, message);
+
+                return Ok(STResult::StateTransferRunning);
+            }
+            CstMessageKind::CheckpointDigest(seq, digest) => {
+                self.process_checkpoint_digest(view, header.from(), *seq, digest.clone());
+
+                return Ok(STResult::StateTransferRunning);
+            }
             _ => {}
         }
 
@@ -316,26 +747,21 @@ impl<S, NT, PL> StateTransferProtocol<S, NT, PL> for CollabStateTransfer<S, NT,
         match status {
             CstStatus::Running => (),
             CstStatus::State(state) => {
-                metric_store_count(TOTAL_STATE_TRANSFERED_ID, 0);
-                metric_duration_end(TOTAL_STATE_WAIT_ID);
+                let install_channel = &self.install_channel;
 
-                metric_store_count(TOTAL_STATE_INSTALLED_ID, 0);
-                metric_increment(TOTAL_STATE_INSTALLED_ID, Some(state.checkpoint.state().size().try_into().unwrap()));
-                let start = Instant::now();
+                let install_time = install_state_measured(&*self.metrics, state.checkpoint.state().size().try_into().unwrap(), || {
+                    install_channel.send_return(InstallStateMessage::new(state.checkpoint.state().clone())).unwrap();
+                });
 
-                self.install_channel.send_return(InstallStateMessage::new(state.checkpoint.state().clone())).unwrap();
-
-                metric_duration(STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID, start.elapsed());
-                metric_duration_end(STATE_TRANSFER_TIME_ID);
-                println!("Finished state transfer {:?}", start.elapsed());
+                println!("Finished state transfer {:?}", install_time);
                 return Ok(STResult::StateTransferFinished(state.checkpoint.sequence_number()));
             }
             CstStatus::SeqNo(seq) => {
                 if self.current_checkpoint_state.sequence_number() < seq {
                     debug!("{:?} // Requesting state {:?}", self.node.id(), seq);
-                    metric_duration_start(STATE_TRANSFER_TIME_ID);    
-                    metric_duration_start(TOTAL_STATE_WAIT_ID);
-                    metric_store_count(TOTAL_STATE_TRANSFERED_ID, 0);
+                    self.metrics.duration_start(STATE_TRANSFER_TIME_ID);    
+                    self.metrics.duration_start(TOTAL_STATE_WAIT_ID);
+                    self.metrics.store_count(TOTAL_STATE_TRANSFERED_ID, 0);
 
                     self.request_latest_state(view);
                 } else {
@@ -363,7 +789,7 @@ impl<S, NT, PL> StateTransferProtocol<S, NT, PL> for CollabStateTransfer<S, NT,
     }
 
     fn handle_app_state_requested(&mut self, seq: SeqNo) -> Result<ExecutionResult> {
-        metric_duration_start(CHECKPOINT_UPDATE_TIME_ID);
+        self.metrics.duration_start(CHECKPOINT_UPDATE_TIME_ID);
 
         let earlier = std::mem::replace(&mut self.current_checkpoint_state, CheckpointState::None);
 
@@ -372,11 +798,14 @@ impl<S, NT, PL> StateTransferProtocol<S, NT, PL> for CollabStateTransfer<S, NT,
             CheckpointState::Complete(earlier) => {
                 CheckpointState::PartialWithEarlier { seq, earlier }
             }
-// FIXME: this may not be an invalid state after all; we may just be generating
-// checkpoints too fast for the execution layer to keep up, delivering the
-// hash digests of the appstate
+            // We are still waiting for the execution layer to deliver the state of the
+            // previous checkpoint. This is not corruption, we are just generating
+            // checkpoints faster than the execution layer can keep up with
             _ => {
-                error!("Invalid checkpoint state detected");
+                warn!("{:?} // Checkpoint requested for seq {:?} while the previous one is still being generated, skipping it",
+                    self.node.id(), seq);
+
+                self.metrics.increment(CHECKPOINTS_OVERLAPPED_ID, Some(1));
 
                 self.current_checkpoint_state = earlier;
 
@@ -410,12 +839,7 @@ impl<S, NT, PL> MonolithicStateTransfer<S, NT, PL> for CollabStateTransfer<S, NT
     fn initialize(config: Self::Config, timeouts: Timeouts, node: Arc<NT>,
                   log: PL, executor_handle: ChannelSyncTx<InstallStateMessage<S>>) -> Result<Self>
         where Self: Sized {
-        let StateTransferConfig {
-            timeout_duration
-        } = config;
-
-
-        Ok(Self::new(node, timeout_duration, timeouts, log, executor_handle))
+        Ok(Self::new(node, config, timeouts, log, executor_handle))
     }
 
     fn handle_state_received_from_app(&mut self, state: Arc<ReadOnly<Checkpoint<S>>>) -> Result<()> {
@@ -429,6 +853,10 @@ impl<S, NT, PL> MonolithicStateTransfer<S, NT, PL> for CollabStateTransfer<S, NT
     }
 }
 
+/// The chunk size used to serve our state when the requesting replica
+/// fetches it in chunks but we have not configured one ourselves
+const DEFAULT_STATE_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
 type Ser<ST: StateTransferProtocol<S, NT, PL>, S, NT, PL> = <ST as StateTransferProtocol<S, NT, PL>>::Serialization;
 
 // TODO: request timeouts
@@ -439,19 +867,59 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
         NT: StateTransferSendNode<CSTMsg<S>> + 'static
 {
     /// Create a new instance of `CollabStateTransfer`.
-    pub fn new(node: Arc<NT>, base_timeout: Duration, timeouts: Timeouts, persistent_log: PL, install_channel: ChannelSyncTx<InstallStateMessage<S>>) -> Self {
+    pub fn new(node: Arc<NT>, config: StateTransferConfig, timeouts: Timeouts, persistent_log: PL,
+               install_channel: ChannelSyncTx<InstallStateMessage<S>>) -> Self {
+        let StateTransferConfig {
+            timeout_duration,
+            state_timeout_duration,
+            max_timeout,
+            metrics_sink,
+            serving_config,
+            checkpoint_digest,
+            state_chunk_size,
+        } = config;
+
+        let metrics = metrics_sink.unwrap_or_else(|| Arc::new(AtlasMetricsSink));
+
         Self {
             current_checkpoint_state: CheckpointState::None,
-            base_timeout,
-            curr_timeout: base_timeout,
+            round_timeouts: RoundTimeouts::new(timeout_duration, state_timeout_duration, max_timeout),
             timeouts,
             node,
-            received_states: collections::hash_map(),
-            received_state_ids: collections::hash_map(),
+            received_states: ReceivedStates::new(),
+            received_state_ids: ReceivedStateCids::new(),
+            received_manifests: collections::hash_map(),
+            round_replies: RoundReplies::default(),
+            busy_replicas: BusyReplicas::default(),
+            agreed_checkpoint: None,
+            round_target: 0,
             phase: ProtoPhase::Init,
             curr_seq: SeqNo::ZERO,
             persistent_log,
             install_channel,
+            drift_detector: DigestDriftDetector::default(),
+            state_serving: StateServingLimiter::new(serving_config),
+            metrics,
+            checkpoint_digest,
+            state_chunk_size,
+            served_chunks: None,
+        }
+    }
+
+    /// Report how far along the CST protocol currently is
+    pub fn progress(&self) -> CstProgressReport {
+        let (replies, target) = match &self.phase {
+            ProtoPhase::Init => (0, 0),
+            ProtoPhase::WaitingCheckpoint(waiting) => (waiting.len(), 0),
+            ProtoPhase::ReceivingCid(i) | ProtoPhase::ReceivingState(i) | ProtoPhase::ReceivingManifest(i) => (*i, self.round_target),
+            ProtoPhase::ReceivingChunks(receiver) => (receiver.received_chunks(), receiver.manifest().chunk_count()),
+        };
+
+        CstProgressReport {
+            phase: self.phase.name(),
+            replies,
+            target,
+            seq: self.curr_seq,
         }
     }
 
@@ -524,6 +992,9 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
         }
     }
 
+    /// Process a request for our state, for its manifest or for some of its chunks.
+    /// All of them require serializing (at least part of) our state, so they share
+    /// the limit on how many state requests we serve at the same time
     fn process_request_state(
         &mut self,
         header: Header,
@@ -531,127 +1002,296 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
     ) where
     {
         let start = Instant::now();
-        match &mut self.phase {
-            ProtoPhase::Init => {}
-            ProtoPhase::WaitingCheckpoint(waiting) => {
-                waiting.push(StoredMessage::new(header, message));
+        if let ProtoPhase::WaitingCheckpoint(waiting) = &mut self.phase {
+            waiting.push(StoredMessage::new(header, message));
 
-                return;
-            }
-            _ => {
-                // We can't reply to state requests when requesting state ourselves
-                return;
-            }
+            return;
         }
 
-        let state = match &self.current_checkpoint_state {
-            CheckpointState::PartialWithEarlier { earlier, seq } => { earlier.clone() }
-            CheckpointState::Complete(checkpoint) => {
-                checkpoint.clone()
-            }
-            _ => {
-                if let ProtoPhase::WaitingCheckpoint(waiting) = &mut self.phase {
-                    waiting.push(StoredMessage::new(header, message));
-                } else {
-                    self.phase = ProtoPhase::WaitingCheckpoint(vec![StoredMessage::new(header, message)]);
-                }
+        if self.serveable_checkpoint().is_none() && matches!(self.phase, ProtoPhase::Init) {
+            self.phase = ProtoPhase::WaitingCheckpoint(vec![StoredMessage::new(header, message)]);
 
-                return;
-            }
-        };
+            return;
+        }
 
-        let reply = CstMessage::new(
-            message.sequence_number(),
-            CstMessageKind::ReplyState(RecoveryState {
-                checkpoint: state,
-            }),
-        );
+        // When we are recovering ourselves, we can still serve our last finalized
+        // checkpoint to other recovering replicas. If we don't have one yet, the
+        // request stays queued until we do
+
+        let from = header.from();
+        let seq = message.sequence_number();
+
+        // Serializing and sending the state is expensive, so we limit how many
+        // states we serve at the same time. Excess requests wait for a free slot
+        if let Err((_, retry_after)) = self.state_serving.enqueue(from, StoredMessage::new(header, message), Instant::now()) {
+            warn!("{:?} // Too many pending state requests, rejecting request from {:?}. Retry after {:?}",
+                self.node.id(), from, retry_after);
+
+            self.metrics.increment(STATE_REQUESTS_REJECTED_ID, Some(1));
 
-        metric_duration(PROCESS_REQ_STATE_TIME_ID, start.elapsed());
+            let reply = CstMessage::new(seq, CstMessageKind::ReplyStateBusy(retry_after));
 
-        self.node.send(reply, header.from(), true).unwrap();
+            self.node.send(reply, from, true).unwrap();
+
+            return;
+        }
+
+        self.serve_queued_state_requests();
+
+        self.metrics.duration(PROCESS_REQ_STATE_TIME_ID, start.elapsed());
     }
 
-    /// Advances the state of the CST state machine.
-    pub fn process_message<V>(
-        &mut self,
-        view: V,
-        progress: CstProgress<S>,
-    ) -> CstStatus<S>
-        where V: NetworkView
-    {
-        match self.phase {
-            ProtoPhase::WaitingCheckpoint(_) => {
-                self.process_pending_state_requests();
+    /// Ask the replicas that were too busy to serve our request for it again,
+    /// once the delay they asked us to wait for has passed
+    fn retry_busy_replicas(&mut self) {
+        if !matches!(self.phase, ProtoPhase::ReceivingState(_) | ProtoPhase::ReceivingManifest(_) | ProtoPhase::ReceivingChunks(_)) {
+            return;
+        }
 
-                CstStatus::Nil
-            }
-            ProtoPhase::Init => {
-                let (header, message) = getmessage!(progress, CstStatus::Nil);
+        for replica in self.busy_replicas.take_due(Instant::now()) {
+            let kind = match &self.phase {
+                ProtoPhase::ReceivingState(_) => CstMessageKind::RequestState,
+                ProtoPhase::ReceivingManifest(_) => CstMessageKind::RequestStateManifest,
+                ProtoPhase::ReceivingChunks(receiver) => {
+                    let chunks = receiver.assigned_to(replica);
 
-                match message.kind() {
-                    CstMessageKind::RequestStateCid => {
-                        self.process_request_seq(header, message);
-                    }
-                    CstMessageKind::RequestState => {
-                        self.process_request_state(header, message);
+                    if chunks.is_empty() {
+                        continue;
                     }
-                    // we are not running cst, so drop any reply msgs
-                    //
-                    // TODO: maybe inspect cid msgs, and passively start
-                    // the state transfer protocol, by returning
-                    // CstStatus::RequestState
-                    _ => (),
+
+                    CstMessageKind::RequestStateChunks { seq: receiver.manifest().sequence_number(), chunks }
                 }
+                _ => unreachable!(),
+            };
 
-                CstStatus::Nil
-            }
-            ProtoPhase::ReceivingCid(i) => {
-                let (header, message) = getmessage!(progress, CstStatus::RequestStateCid);
+            debug!("{:?} // Retrying request to previously busy replica {:?}", self.node.id(), replica);
 
-                debug!("{:?} // Received Cid with {} responses from {:?} for CST Seq {:?} vs Ours {:?}", self.node.id(),
-                   i, header.from(), message.sequence_number(), self.curr_seq);
+            self.node.send(CstMessage::new(self.curr_seq, kind), replica, true).unwrap();
+        }
+    }
 
-                // drop cst messages with invalid seq no
-                if message.sequence_number() != self.curr_seq {
-                    debug!("{:?} // Wait what? {:?} {:?}", self.node.id(), self.curr_seq, message.sequence_number());
-                    // FIXME: how to handle old or newer messages?
-                    // BFT-SMaRt simply ignores messages with a
-                    // value of `queryID` different from the current
-                    // `queryID` a replica is tracking...
-                    // we will do the same for now
-                    //
-                    // TODO: implement timeouts to fix cases like this
-                    return CstStatus::Running;
-                }
+    /// The checkpoint we can currently serve to other replicas, if any
+    fn serveable_checkpoint(&self) -> Option<Arc<ReadOnly<Checkpoint<S>>>> {
+        self.current_checkpoint_state.serveable()
+    }
 
-                match message.kind() {
-                    CstMessageKind::ReplyStateCid(state_cid) => {
-                        if let Some((cid, digest)) = state_cid {
-                            debug!("{:?} // Received state cid {:?} with digest {:?} from {:?} with seq {:?}",
-                            self.node.id(), state_cid, digest, header.from(), cid);
-
-                            let received_state_cid = self.received_state_ids.entry(digest.clone()).or_insert_with(|| {
-                                ReceivedStateCid {
-                                    cid: *cid,
-                                    count: 0,
-                                }
-                            });
-
-                            if *cid > received_state_cid.cid {
-                                info!("{:?} // Received newer state for old cid {:?} vs new cid {:?} with digest {:?}.",
-                                    self.node.id(), received_state_cid.cid, *cid, digest);
-
-                                received_state_cid.cid = *cid;
-                                received_state_cid.count = 1;
-                            } else if *cid == received_state_cid.cid {
-                                info!("{:?} // Received matching state for cid {:?} with digest {:?}. Count {}",
-                                self.node.id(), received_state_cid.cid, digest, received_state_cid.count + 1);
-
-                                received_state_cid.count += 1;
+    /// Serve the queued state requests, for as long as there are free serving slots
+    fn serve_queued_state_requests(&mut self) {
+        if self.needs_checkpoint() {
+            // The requests will be processed once the checkpoint is finalized
+            return;
+        }
+
+        let state = match self.serveable_checkpoint() {
+            Some(state) => state,
+            None => return,
+        };
+
+        while let Some((from, request)) = self.state_serving.next_to_serve(Instant::now()) {
+            let (_, message) = request.into_inner();
+
+            match message.kind() {
+                CstMessageKind::RequestState => {
+                    debug!("{:?} // Serving state {:?} to {:?}", self.node.id(), state.sequence_number(), from);
+
+                    let reply = CstMessage::new(
+                        message.sequence_number(),
+                        CstMessageKind::ReplyState(RecoveryState {
+                            checkpoint: state.clone(),
+                        }),
+                    );
+
+                    self.node.send(reply, from, true).unwrap();
+                }
+                CstMessageKind::RequestStateManifest => self.serve_manifest(from, message),
+                CstMessageKind::RequestStateChunks { .. } => self.serve_chunks(from, message),
+                _ => unreachable!("only state requests are queued to be served"),
+            }
+        }
+    }
+
+    /// Reply with the manifest of the checkpoint we can currently serve
+    fn serve_manifest(&mut self, from: NodeId, message: CstMessage<S>) {
+        let manifest = match self.served_chunks() {
+            Ok(chunks) => chunks.map(|chunks| chunks.manifest.clone()),
+            Err(err) => {
+                error!("{:?} // Failed to split our state into chunks: {:?}", self.node.id(), err);
+
+                return;
+            }
+        };
+
+        debug!("{:?} // Replying to {:?} seq {:?} with state manifest {:?}", self.node.id(),
+            from, message.sequence_number(), manifest.as_ref().map(|manifest| manifest.sequence_number()));
+
+        let reply = CstMessage::new(message.sequence_number(), CstMessageKind::ReplyStateManifest(manifest));
+
+        self.node.send(reply, from, true).unwrap();
+    }
+
+    /// Reply with the requested chunks of our serialized state.
+    /// Requests for chunks of a checkpoint other than the one we are serving, or for ranges
+    /// which are not chunks of its manifest, are ignored and the requesting replica will retry
+    /// when it times out
+    fn serve_chunks(&mut self, from: NodeId, message: CstMessage<S>) {
+        let (seq, chunks) = match message.kind() {
+            CstMessageKind::RequestStateChunks { seq, chunks } => (*seq, chunks),
+            _ => return,
+        };
+
+        if let Err(err) = self.served_chunks() {
+            error!("{:?} // Failed to split our state into chunks: {:?}", self.node.id(), err);
+
+            return;
+        }
+
+        let served = match &self.served_chunks {
+            Some(served) if served.manifest.sequence_number() == seq => served,
+            _ => {
+                debug!("{:?} // Received request for chunks of state {:?} from {:?}, which we are not serving",
+                    self.node.id(), seq, from);
+
+                return;
+            }
+        };
+
+        if let Some((offset, len)) = chunks.iter().find(|(offset, len)| served.manifest.chunk_index(*offset, *len).is_none()) {
+            warn!("{:?} // Received request for invalid chunk {} {} from {:?}", self.node.id(), offset, len, from);
+
+            return;
+        }
+
+        let serialized = match &served.serialized {
+            Some(serialized) => Cow::Borrowed(serialized),
+            // The state is too large to keep in memory, so we serialize it again
+            None => match self.serveable_checkpoint().map(|checkpoint| serialize_state(checkpoint.state())) {
+                Some(Ok(serialized)) => Cow::Owned(serialized),
+                Some(Err(err)) => {
+                    error!("{:?} // Failed to serialize our state to serve its chunks: {:?}", self.node.id(), err);
+
+                    return;
+                }
+                None => return,
+            }
+        };
+
+        debug!("{:?} // Serving {} chunks of state {:?} to {:?}", self.node.id(), chunks.len(), seq, from);
+
+        for (offset, len) in chunks {
+            let (start, end) = (*offset as usize, (*offset + *len) as usize);
+
+            let kind = CstMessageKind::ReplyStateChunk {
+                offset: *offset,
+                len: *len,
+                data: serialized[start..end].to_vec(),
+            };
+
+            self.node.send(CstMessage::new(message.sequence_number(), kind), from, true).unwrap();
+        }
+    }
+
+    /// The chunks of the checkpoint we can currently serve, splitting its state
+    /// if it has changed since the last time.
+    /// The serialized state is only kept when it is no larger than the configured cap
+    fn served_chunks(&mut self) -> Result<Option<&ServedChunks>> {
+        let checkpoint = match self.serveable_checkpoint() {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(None),
+        };
+
+        let outdated = match &self.served_chunks {
+            Some(chunks) => chunks.manifest.sequence_number() != checkpoint.sequence_number(),
+            None => true,
+        };
+
+        if outdated {
+            let chunk_size = self.state_chunk_size.unwrap_or(DEFAULT_STATE_CHUNK_SIZE);
+
+            let serialized = serialize_state(checkpoint.state())?;
+
+            let manifest = StateManifest::new(checkpoint.sequence_number(), checkpoint.digest().clone(), &serialized, chunk_size);
+
+            let serialized = if serialized.len() <= self.state_serving.config().max_cached_state_size {
+                Some(serialized)
+            } else {
+                None
+            };
+
+            self.served_chunks = Some(ServedChunks { manifest, serialized });
+        }
+
+        Ok(self.served_chunks.as_ref())
+    }
+
+    /// Advances the state of the CST state machine.
+    pub fn process_message<V>(
+        &mut self,
+        view: V,
+        progress: CstProgress<S>,
+    ) -> CstStatus<S>
+        where V: NetworkView
+    {
+        match self.phase {
+            ProtoPhase::WaitingCheckpoint(_) => {
+                self.process_pending_state_requests();
+
+                CstStatus::Nil
+            }
+            ProtoPhase::Init => {
+                let (header, message) = getmessage!(progress, CstStatus::Nil);
+
+                match message.kind() {
+                    CstMessageKind::RequestStateCid => {
+                        self.process_request_seq(header, message);
+                    }
+                    CstMessageKind::RequestState => {
+                        self.process_request_state(header, message);
+                    }
+                    // we are not running cst, so drop any reply msgs
+                    //
+                    // TODO: maybe inspect cid msgs, and passively start
+                    // the state transfer protocol, by returning
+                    // CstStatus::RequestState
+                    _ => (),
+                }
+
+                CstStatus::Nil
+            }
+            ProtoPhase::ReceivingCid(i) => {
+                let (header, message) = getmessage!(progress, CstStatus::RequestStateCid);
+
+                debug!("{:?} // Received Cid with {} responses from {:?} for CST Seq {:?} vs Ours {:?}", self.node.id(),
+                   i, header.from(), message.sequence_number(), self.curr_seq);
+
+                // drop cst messages with invalid seq no
+                if message.sequence_number() != self.curr_seq {
+                    debug!("{:?} // Wait what? {:?} {:?}", self.node.id(), self.curr_seq, message.sequence_number());
+                    // FIXME: how to handle old or newer messages?
+                    // BFT-SMaRt simply ignores messages with a
+                    // value of `queryID` different from the current
+                    // `queryID` a replica is tracking...
+                    // we will do the same for now
+                    //
+                    // TODO: implement timeouts to fix cases like this
+                    return CstStatus::Running;
+                }
+
+                match message.kind() {
+                    CstMessageKind::ReplyStateCid(state_cid) => {
+                        if !self.received_state_ids.receive(&mut self.round_replies, header.from(), state_cid.as_ref()) {
+                            warn!("{:?} // Ignoring repeated reply from {:?} for CST Seq {:?}", self.node.id(), header.from(), self.curr_seq);
+
+                            return CstStatus::Running;
+                        }
+
+                        match state_cid {
+                            Some((cid, digest)) => {
+                                debug!("{:?} // Received state cid {:?} with digest {:?} from {:?}",
+                                    self.node.id(), cid, digest, header.from());
+                            }
+                            None => {
+                                debug!("{:?} // Received blank state cid from node {:?}", self.node.id(), header.from());
                             }
-                        } else {
-                            debug!("{:?} // Received blank state cid from node {:?}", self.node.id(), header.from());
                         }
                     }
                     CstMessageKind::RequestStateCid => {
@@ -670,52 +1310,47 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
 
                 // check if we have gathered enough cid
                 // replies from peer nodes
-                //
-                // TODO: check for more than one reply from the same node
                 let i = i + 1;
 
                 debug!("{:?} // Quorum count {}, i: {}, cst_seq {:?}. Current Latest Cid: {:?}",
-                        self.node.id(), view.quorum(), i,
+                        self.node.id(), view.strong_quorum(), i,
                         self.curr_seq, self.received_state_ids);
 
-                if i >= view.quorum() {
-                    self.phase = ProtoPhase::Init;
+                match self.received_state_ids.conclude(i, view.strong_quorum()) {
+                    CidRoundResult::Agreed(seq, digest) => {
+                        self.phase = ProtoPhase::Init;
 
-                    // reset timeout, since req was successful
-                    self.curr_timeout = self.base_timeout;
+                        info!("{:?} // Received quorum of states for CST Seq {:?} with digest {:?} and seq {:?}",
+                            self.node.id(), self.curr_seq, digest, seq);
 
-                    let mut received_state_ids: Vec<_> = self.received_state_ids.iter().map(|(digest, cid)| {
-                        (digest, cid.cid, cid.count)
-                    }).collect();
+                        // reset timeout, since req was successful
+                        self.round_timeouts.cid.reset();
 
-                    received_state_ids.sort_by(|(_, _, count), (_, _, count2)| {
-                        count.cmp(count2).reverse()
-                    });
+                        // The state we fetch must be the one the quorum agreed on
+                        self.agreed_checkpoint = Some((seq, digest));
+
+                        CstStatus::SeqNo(seq)
+                    }
+                    CidRoundResult::Blank => {
+                        self.phase = ProtoPhase::Init;
 
-                    if let Some((digest, seq, count)) = received_state_ids.first() {
-                        if *count >= view.quorum() {
-                            info!("{:?} // Received quorum of states for CST Seq {:?} with digest {:?} and seq {:?}",
-                                self.node.id(), self.curr_seq, digest, seq);
+                        // If we are completely blank, then no replicas have state, so we can initialize
+                        warn!("We have received a quorum of blank messages, which means we are probably at the start");
 
-                            return CstStatus::SeqNo(*seq);
-                        } else {
+                        self.round_timeouts.cid.reset();
+
+                        CstStatus::SeqNo(SeqNo::ZERO)
+                    }
+                    CidRoundResult::Running => {
+                        if i >= view.strong_quorum() {
                             warn!("Received quorum state messages but we still don't have a quorum of states? Faulty replica? {:?}", self.received_state_ids)
                         }
-                    } else {
-                        // If we are completely blank, then no replicas have state, so we can initialize
 
-                        warn!("We have received a quorum of blank messages, which means we are probably at the start");
-                        return CstStatus::SeqNo(SeqNo::ZERO);
-                    }
+                        self.phase = ProtoPhase::ReceivingCid(i);
 
-                    // we don't need the latest cid to be available in at least
-                    // f+1 replicas since the replica has the proof that the system
-                    // has decided
+                        CstStatus::Running
+                    }
                 }
-
-                self.phase = ProtoPhase::ReceivingCid(i);
-
-                CstStatus::Running
             }
             ProtoPhase::ReceivingState(i) => {
                 let (header, mut message) = getmessage!(progress, CstStatus::RequestState);
@@ -725,112 +1360,264 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
                     return CstStatus::Running;
                 }
 
+                if let CstMessageKind::ReplyStateBusy(retry_after) = message.kind() {
+                    self.replica_busy(header.from(), *retry_after);
+
+                    return CstStatus::Running;
+                }
+
                 let state = match message.take_state() {
                     Some(state) => state,
                     // drop invalid message kinds
                     None => return CstStatus::Running,
                 };
-                metric_increment(
-                    TOTAL_STATE_TRANSFERED_ID,
-                    Some(state.checkpoint.state().size().try_into().unwrap()),
-                );
 
+                let state_size = state.checkpoint.state().size();
                 let state_digest = state.checkpoint.digest().clone();
 
                 debug!("{:?} // Received state with digest {:?}, is contained in map? {}", self.node.id(),
-                state_digest, self.received_states.contains_key(&state_digest));
-
-                if self.received_states.contains_key(&state_digest) {
-                    let current_state = self.received_states.get_mut(&state_digest).unwrap();
+                state_digest, self.received_states.contains(&state_digest));
 
-                    let current_state_seq: SeqNo = current_state.state.checkpoint().sequence_number();
-                    let recv_state_seq: SeqNo = state.checkpoint().sequence_number();
+                if !self.received_states.receive(&mut self.round_replies, header.from(), state) {
+                    warn!("{:?} // Ignoring repeated reply from {:?} for CST Seq {:?}", self.node.id(), header.from(), self.curr_seq);
 
-                    match recv_state_seq.cmp(&current_state_seq) {
-                        Ordering::Less | Ordering::Equal => {
-                            // we have just verified that the state is the same, but the decision log is
-                            // smaller than the one we have already received
-                            current_state.count += 1;
-                        }
-                        Ordering::Greater => {
-                            current_state.state = state;
-                            // We have also verified that the state is the same but the decision log is
-                            // Larger, so we want to store the newest one. However we still want to increment the count
-                            // We can do this since to be in the decision log, a replica must have all of the messages
-                            // From at least 2f+1 replicas, so we know that the log is valid
-                            current_state.count += 1;
-                        }
-                    }
-                } else {
-                    self.received_states.insert(state_digest, ReceivedState { count: 1, state });
+                    return CstStatus::Running;
                 }
 
+                self.metrics.increment(
+                    TOTAL_STATE_TRANSFERED_ID,
+                    Some(state_size.try_into().unwrap()),
+                );
+
                 // check if we have gathered enough state
                 // replies from peer nodes
-                //
-                // TODO: check for more than one reply from the same node
                 let i = i + 1;
 
-                if i <= view.f() {
-                    self.phase = ProtoPhase::ReceivingState(i);
+                match self.received_states.conclude(i, view.weak_quorum(), view.strong_quorum()) {
+                    StateRoundResult::Running => {
+                        self.phase = ProtoPhase::ReceivingState(i);
+
+                        CstStatus::Running
+                    }
+                    StateRoundResult::NoQuorum => {
+                        debug!("{:?} // No states with at least {} matching replies", self.node.id(), view.weak_quorum());
+
+                        CstStatus::RequestState
+                    }
+                    StateRoundResult::Agreed(digest, state) => {
+                        self.phase = ProtoPhase::Init;
+
+                        // reset timeout, since req was successful
+                        self.round_timeouts.state.reset();
+
+                        info!("{:?} // Received quorum of states for CST Seq {:?} with digest {:?}, returning the state to the replica",
+                            self.node.id(), self.curr_seq, digest);
+
+                        CstStatus::State(state)
+                    }
+                }
+            }
+            ProtoPhase::ReceivingManifest(i) => {
+                let (header, message) = getmessage!(progress, CstStatus::RequestState);
+
+                if message.sequence_number() != self.curr_seq {
+                    // NOTE: check comment above, on ProtoPhase::ReceivingCid
                     return CstStatus::Running;
                 }
 
-                // NOTE: clear saved states when we return;
-                // this is important, because each state
-                // may be several GBs in size
+                if let CstMessageKind::ReplyStateBusy(retry_after) = message.kind() {
+                    self.replica_busy(header.from(), *retry_after);
+
+                    return CstStatus::Running;
+                }
 
-                // check if we have at least f+1 matching states
-                let digest = {
-                    let received_state = self.received_states.iter().max_by_key(|(_, st)| st.count);
-
-                    match received_state {
-                        Some((digest, _)) => digest.clone(),
-                        None => {
-                            return if i >= view.quorum() {
-                                self.received_states.clear();
-
-                                debug!("{:?} // No matching states found, clearing", self.node.id());
-                                CstStatus::RequestState
-                            } else {
-                                CstStatus::Running
-                            };
+                if let CstMessageKind::ReplyStateManifest(_) = message.kind() {
+                    if !self.accept_reply(header.from()) {
+                        return CstStatus::Running;
+                    }
+                }
+
+                match message.kind() {
+                    CstMessageKind::ReplyStateManifest(Some(manifest)) if !is_agreed_manifest(manifest, self.agreed_checkpoint.as_ref()) => {
+                        warn!("{:?} // Received state manifest {:?} {:?} from {:?}, which is not the checkpoint agreed on {:?}",
+                            self.node.id(), manifest.sequence_number(), manifest.digest(), header.from(), self.agreed_checkpoint);
+                    }
+                    CstMessageKind::ReplyStateManifest(Some(manifest)) => {
+                        let (received, senders) = self.received_manifests.entry(manifest.state_digest().clone())
+                            .or_insert_with(|| (manifest.clone(), Vec::new()));
+
+                        // Only exactly matching manifests vouch for the same chunk digests
+                        if *received == *manifest {
+                            senders.push(header.from());
+                        } else {
+                            warn!("{:?} // Received state manifest {:?} from {:?} which does not match the others with state digest {:?}",
+                                self.node.id(), manifest.sequence_number(), header.from(), manifest.state_digest());
                         }
                     }
-                };
+                    CstMessageKind::ReplyStateManifest(None) => {
+                        debug!("{:?} // Received blank state manifest from node {:?}", self.node.id(), header.from());
+                    }
+                    // drop invalid message kinds
+                    _ => return CstStatus::Running,
+                }
 
-                let received_state = {
-                    let received_state = self.received_states.remove(&digest);
-                    self.received_states.clear();
-                    received_state
-                };
+                let i = i + 1;
 
-                // reset timeout, since req was successful
-                self.curr_timeout = self.base_timeout;
+                // We need a weak quorum of matching manifests, so that at least one
+                // correct replica vouches for the chunk digests
+                let agreed = self.received_manifests.values()
+                    .find(|(_, senders)| senders.len() >= view.weak_quorum())
+                    .map(|(manifest, senders)| (manifest.clone(), senders.clone()));
 
-                // return the state
-                let f = view.f();
+                if let Some((manifest, senders)) = agreed {
+                    self.received_manifests.clear();
 
-                match received_state {
-                    Some(ReceivedState { count, state }) if count > f => {
-                        self.phase = ProtoPhase::Init;
+                    info!("{:?} // Received matching manifests for CST Seq {:?}, fetching {} chunks of state {:?} from {:?}",
+                        self.node.id(), self.curr_seq, manifest.chunk_count(), manifest.sequence_number(), senders);
 
-                        info!("{:?} // Received quorum of states for CST Seq {:?} with digest {:?}, returning the state to the replica",
-                            self.node.id(), self.curr_seq, digest);
+                    let mut receiver = ChunkedStateReceiver::new(manifest, senders);
 
+                    let requests = receiver.assign();
 
-                        CstStatus::State(state)
-                    }
-                    _ => {
-                        debug!("{:?} // No states with more than f {} count", self.node.id(), f);
+                    self.request_state_chunks(receiver.manifest().sequence_number(), requests);
 
-                        CstStatus::RequestState
-                    }
+                    self.phase = ProtoPhase::ReceivingChunks(receiver);
+
+                    return CstStatus::Running;
+                }
+
+                if i >= view.strong_quorum() {
+                    debug!("{:?} // No matching manifests found, clearing", self.node.id());
+
+                    self.received_manifests.clear();
+
+                    return CstStatus::RequestState;
+                }
+
+                self.phase = ProtoPhase::ReceivingManifest(i);
+
+                CstStatus::Running
+            }
+            ProtoPhase::ReceivingChunks(_) => {
+                let (header, message) = getmessage!(progress, CstStatus::RequestState);
+
+                if message.sequence_number() != self.curr_seq {
+                    // NOTE: check comment above, on ProtoPhase::ReceivingCid
+                    return CstStatus::Running;
+                }
+
+                if let CstMessageKind::ReplyStateBusy(retry_after) = message.kind() {
+                    self.replica_busy(header.from(), *retry_after);
+
+                    return CstStatus::Running;
                 }
+
+                self.process_state_chunk(header, message)
+            }
+        }
+    }
+
+    /// The given replica is too busy to serve our request, ask it again once it
+    /// told us it should have a free slot (see retry_busy_replicas)
+    fn replica_busy(&mut self, from: NodeId, retry_after: Duration) {
+        debug!("{:?} // Replica {:?} is too busy to serve our request, retry after {:?}",
+            self.node.id(), from, retry_after);
+
+        if !self.round_replies.has_replied(&from) {
+            self.busy_replicas.busy(from, retry_after, Instant::now());
+        }
+    }
+
+    /// Check if this is the first reply of the given replica in the current round,
+    /// ignoring duplicate (or conflicting) replies
+    fn accept_reply(&mut self, from: NodeId) -> bool {
+        if self.round_replies.register(from) {
+            return true;
+        }
+
+        warn!("{:?} // Ignoring repeated reply from {:?} for CST Seq {:?}", self.node.id(), from, self.curr_seq);
+
+        false
+    }
+
+    fn process_state_chunk(&mut self, header: Header, message: CstMessage<S>) -> CstStatus<S> {
+        let (offset, data) = match message.kind() {
+            CstMessageKind::ReplyStateChunk { offset, data, .. } => (*offset, data),
+            // drop invalid message kinds
+            _ => return CstStatus::Running,
+        };
+
+        let receiver = match &mut self.phase {
+            ProtoPhase::ReceivingChunks(receiver) => receiver,
+            _ => return CstStatus::Running,
+        };
+
+        if let Err(err) = receiver.receive_chunk(header.from(), offset, data) {
+            warn!("{:?} // Invalid state chunk received from {:?}: {:?}. Requesting its chunks from other replicas",
+                self.node.id(), header.from(), err);
+
+            receiver.mark_faulty(header.from());
+
+            if !receiver.has_peers() {
+                // There are no other replicas to fetch the chunks from, so we
+                // have to start over
+                return CstStatus::RequestState;
+            }
+
+            let requests = receiver.assign();
+
+            let seq = receiver.manifest().sequence_number();
+
+            self.request_state_chunks(seq, requests);
+
+            return CstStatus::Running;
+        }
+
+        self.metrics.increment(TOTAL_STATE_TRANSFERED_ID, Some(data.len() as u64));
+
+        if !receiver.is_complete() {
+            return CstStatus::Running;
+        }
+
+        let receiver = match std::mem::replace(&mut self.phase, ProtoPhase::Init) {
+            ProtoPhase::ReceivingChunks(receiver) => receiver,
+            _ => unreachable!(),
+        };
+
+        let (seq, digest) = (receiver.manifest().sequence_number(), receiver.manifest().digest().clone());
+
+        let state = match receiver.into_state_bytes() {
+            Ok(serialized) => S::deserialize_state(&serialized[..]),
+            Err(err) => Err(err.into()),
+        };
+
+        match state {
+            Ok(state) => {
+                self.round_timeouts.state.reset();
+
+                info!("{:?} // Received all the chunks of state {:?} for CST Seq {:?} with digest {:?}, returning the state to the replica",
+                    self.node.id(), seq, self.curr_seq, digest);
+
+                CstStatus::State(RecoveryState::new(Checkpoint::new(seq, state, digest)))
+            }
+            Err(err) => {
+                error!("{:?} // Failed to rebuild the state from its chunks: {:?}", self.node.id(), err);
+
+                CstStatus::RequestState
             }
         }
     }
 
+    fn request_state_chunks(&mut self, seq: SeqNo, requests: BTreeMap<NodeId, Vec<(u64, u64)>>) {
+        let cst_seq = self.curr_seq;
+
+        for (peer, chunks) in requests {
+            let message = CstMessage::new(cst_seq, CstMessageKind::RequestStateChunks { seq, chunks });
+
+            self.node.send(message, peer, true).unwrap();
+        }
+    }
+
 
     /// End the state of an on-going checkpoint.
     ///
@@ -839,24 +1626,73 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
     /// on the core server task's master channel.
     pub fn finalize_checkpoint(&mut self, checkpoint: Arc<ReadOnly<Checkpoint<S>>>) -> Result<()> where
         PL: MonolithicStateLog<S> {
-        match &self.current_checkpoint_state {
-            CheckpointState::None => {
-                Err!(StateTransferError::CheckpointNotInitiated)
-            }
-            CheckpointState::Complete(_) => {
-                Err!(StateTransferError::CheckpointAlreadyFinalized)
+        let verification = match &self.checkpoint_digest {
+            Some(checkpoint_digest) if self.current_checkpoint_state.is_partial() => {
+                let serialized = serialize_state(checkpoint.state())?;
+
+                verify_checkpoint_digest(&checkpoint, &serialized, checkpoint_digest.as_ref())
             }
-            CheckpointState::Partial { seq: _ } | CheckpointState::PartialWithEarlier { seq: _, .. } => {
-                let checkpoint_state = CheckpointState::Complete(checkpoint.clone());
-                println!("checkpoint {:?}", checkpoint_state.sequence_number());
+            _ => Ok(()),
+        };
+
+        if let Err(err) = self.current_checkpoint_state.complete(checkpoint.clone(), verification) {
+            error!("{:?} // Failed to finalize checkpoint {:?}: {:?}. Current checkpoint is {:?}",
+                self.node.id(), checkpoint.sequence_number(), err, self.current_checkpoint_state.sequence_number());
 
-                self.current_checkpoint_state = checkpoint_state;
-                self.persistent_log.write_checkpoint(OperationMode::NonBlockingSync(None), checkpoint)?;
+            return Err!(err);
+        }
+
+        println!("checkpoint {:?}", self.current_checkpoint_state.sequence_number());
 
-                metric_duration_end(CHECKPOINT_UPDATE_TIME_ID);
+        self.drift_detector.discard_older_than(self.current_checkpoint_state.sequence_number());
+        self.persistent_log.write_checkpoint(OperationMode::NonBlockingSync(None), checkpoint)?;
 
-                Ok(())
+        self.metrics.duration_end(CHECKPOINT_UPDATE_TIME_ID);
+
+        Ok(())
+    }
+
+    /// Announce the digest of our latest complete checkpoint to the rest of the quorum,
+    /// so that any divergence between the states of the replicas can be detected
+    pub fn announce_checkpoint_digest<V>(&mut self, view: V) where V: NetworkView {
+        let (seq, digest) = match &self.current_checkpoint_state {
+            CheckpointState::PartialWithEarlier { earlier, .. } => {
+                (earlier.sequence_number(), earlier.digest().clone())
             }
+            CheckpointState::Complete(checkpoint) => {
+                (checkpoint.sequence_number(), checkpoint.digest().clone())
+            }
+            _ => return,
+        };
+
+        if let Err(err) = self.drift_detector.register_digest(self.node.id(), seq, digest.clone(), view.quorum_members()) {
+            debug!("{:?} // Not tracking our own checkpoint digest: {:?}", self.node.id(), err);
+        }
+
+        let message = CstMessage::new(self.curr_seq, CstMessageKind::CheckpointDigest(seq, digest));
+
+        let targets = view.quorum_members().clone().into_iter().filter(|id| *id != self.node.id());
+
+        self.node.broadcast(message, targets);
+    }
+
+    /// The digest divergence detected for the checkpoint with the given sequence number, if any
+    pub fn checkpoint_divergence<V>(&self, view: V, seq: SeqNo) -> Option<DigestDivergence> where V: NetworkView {
+        self.drift_detector.check(seq, view.quorum())
+    }
+
+    fn process_checkpoint_digest<V>(&mut self, view: V, from: NodeId, seq: SeqNo, digest: Digest) where V: NetworkView {
+        debug!("{:?} // Received checkpoint digest {:?} for seq {:?} from {:?}", self.node.id(), digest, seq, from);
+
+        if let Err(err) = self.drift_detector.register_digest(from, seq, digest, view.quorum_members()) {
+            debug!("{:?} // Ignoring checkpoint digest from {:?}: {:?}", self.node.id(), from, err);
+
+            return;
+        }
+
+        if let Some(divergence) = self.drift_detector.check(seq, view.quorum()) {
+            error!("{:?} // Detected divergent checkpoint digests at seq {:?}. Majority digest {:?}, divergent replicas {:?}",
+                self.node.id(), seq, divergence.majority_digest(), divergence.divergent_replicas());
         }
     }
 
@@ -870,6 +1706,13 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
         self.curr_seq
     }
 
+    /// Register the timeout for the requests of the round we are currently in
+    fn register_round_timeout(&mut self, quorum: usize, cst_seq: SeqNo) {
+        if let Some(timeout) = self.round_timeouts.current(&self.phase) {
+            self.timeouts.timeout_cst_request(timeout, quorum as u32, cst_seq);
+        }
+    }
+
     /// Handle a timeout received from the timeouts layer.
     /// Returns a bool to signify if we must move to the Retrieving state
     /// If the timeout is no longer relevant, returns false (Can remain in current phase)
@@ -907,20 +1750,7 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
 
         self.next_seq();
 
-        match self.phase {
-            // retry requests if receiving state and we have timed out
-            ProtoPhase::ReceivingCid(_) => {
-                self.curr_timeout *= 2;
-                CstStatus::RequestStateCid
-            }
-            ProtoPhase::ReceivingState(_) => {
-                self.curr_timeout *= 2;
-                CstStatus::RequestState
-            }
-            // ignore timeouts if not receiving any kind
-            // of state from peer nodes
-            _ => CstStatus::Nil,
-        }
+        self.round_timeouts.timed_out(&self.phase)
     }
 
     /// Used by a recovering node to retrieve the latest sequence number
@@ -932,6 +1762,9 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
     {
         // Reset the map of received state ids
         self.received_state_ids.clear();
+        self.round_replies.clear();
+        self.agreed_checkpoint = None;
+        self.round_target = view.strong_quorum();
 
         self.next_seq();
 
@@ -939,12 +1772,10 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
 
         info!("{:?} // Requesting latest state seq no with seq {:?}", self.node.id(), cst_seq);
 
-        self.timeouts.timeout_cst_request(self.curr_timeout,
-                                          view.quorum() as u32,
-                                          cst_seq);
-
         self.phase = ProtoPhase::ReceivingCid(0);
 
+        self.register_round_timeout(view.quorum(), cst_seq);
+
         let message = CstMessage::new(
             cst_seq,
             CstMessageKind::RequestStateCid,
@@ -962,6 +1793,11 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
     {
         // reset hashmap of received states
         self.received_states.clear();
+        self.received_manifests.clear();
+        self.round_replies.clear();
+        self.busy_replicas.clear();
+        // Both the state and the manifest rounds complete with a weak quorum of matching replies
+        self.round_target = view.weak_quorum();
 
         self.next_seq();
 
@@ -969,25 +1805,130 @@ impl<S, NT, PL> CollabStateTransfer<S, NT, PL>
 
         info!("{:?} // Requesting latest state with cst msg seq {:?}", self.node.id(), cst_seq);
 
-        self.timeouts.timeout_cst_request(self.curr_timeout,
-                                          view.quorum() as u32,
-                                          cst_seq);
+        let targets = view.quorum_members().clone().into_iter().filter(|id| *id != self.node.id());
+
+        if self.state_chunk_size.is_some() {
+            // Only agree on the (cheap) manifest with the quorum, the state itself
+            // is then fetched in chunks from the replicas that agreed on it
+            self.phase = ProtoPhase::ReceivingManifest(0);
+
+            self.register_round_timeout(view.quorum(), cst_seq);
+
+            let message = CstMessage::new(cst_seq, CstMessageKind::RequestStateManifest);
+
+            self.node.broadcast(message, targets);
+
+            return;
+        }
 
         self.phase = ProtoPhase::ReceivingState(0);
 
+        self.register_round_timeout(view.quorum(), cst_seq);
+
         //TODO: Maybe attempt to use followers to rebuild state and avoid
         // Overloading the replicas
         let message = CstMessage::new(cst_seq, CstMessageKind::RequestState);
-        let targets = view.quorum_members().clone().into_iter().filter(|id| *id != self.node.id());
 
         self.node.broadcast(message, targets);
     }
 }
 
+/// Whether the given manifest describes the checkpoint agreed on in the CID round.
+/// When no checkpoint was agreed on (for example, when the quorum had no state) any manifest is accepted
+fn is_agreed_manifest(manifest: &StateManifest, agreed: Option<&(SeqNo, Digest)>) -> bool {
+    agreed.map_or(true, |(seq, digest)| manifest.sequence_number() == *seq && manifest.digest() == digest)
+}
+
+/// Recompute the digest of the given checkpoint from its serialized state, with the digest
+/// function of the execution layer, and check that it matches the digest the checkpoint carries
+fn verify_checkpoint_digest<S>(checkpoint: &ReadOnly<Checkpoint<S>>, serialized: &[u8],
+                               checkpoint_digest: &dyn CheckpointDigest) -> std::result::Result<(), StateTransferError> {
+    let computed = checkpoint_digest.digest(serialized);
+
+    if computed != *checkpoint.digest() {
+        return Err(StateTransferError::CheckpointDigestMismatch {
+            seq: checkpoint.sequence_number(),
+            expected: checkpoint.digest().clone(),
+            computed,
+        });
+    }
+
+    Ok(())
+}
+
+fn serialize_state<S>(state: &S) -> Result<Vec<u8>> where S: MonolithicState {
+    let mut serialized = Vec::new();
+
+    S::serialize_state(&mut serialized, state)?;
+
+    Ok(serialized)
+}
+
 impl<S, NT, PL> PersistableStateTransferProtocol for CollabStateTransfer<S, NT, PL>
     where S: MonolithicState + 'static {}
 
 
+impl<S> CheckpointState<S> {
+    /// The latest complete checkpoint, which we can serve to other replicas
+    fn serveable(&self) -> Option<Arc<ReadOnly<Checkpoint<S>>>> {
+        match self {
+            CheckpointState::PartialWithEarlier { earlier, .. } => Some(earlier.clone()),
+            CheckpointState::Complete(checkpoint) => Some(checkpoint.clone()),
+            _ => None,
+        }
+    }
+
+    /// Whether we are waiting for the application to deliver a checkpoint
+    fn is_partial(&self) -> bool {
+        matches!(self, CheckpointState::Partial { .. } | CheckpointState::PartialWithEarlier { .. })
+    }
+
+    /// Complete the checkpoint being generated with the one delivered by the application.
+    ///
+    /// When the delivered checkpoint failed `verification`, it is discarded and we fall back
+    /// to the earlier checkpoint, if we have one, so we can keep serving it
+    fn complete(&mut self, checkpoint: Arc<ReadOnly<Checkpoint<S>>>,
+                verification: std::result::Result<(), StateTransferError>) -> std::result::Result<(), StateTransferError> {
+        match self {
+            CheckpointState::None => {
+                return Err(StateTransferError::CheckpointNotInitiated);
+            }
+            CheckpointState::Complete(_) => {
+                return Err(StateTransferError::CheckpointAlreadyFinalized);
+            }
+            CheckpointState::Partial { .. } | CheckpointState::PartialWithEarlier { .. } => {}
+        }
+
+        let previous = std::mem::replace(self, CheckpointState::None);
+
+        if let Err(err) = verification {
+            *self = previous.roll_back();
+
+            return match self {
+                CheckpointState::Complete(earlier) => Err(StateTransferError::CheckpointRejected {
+                    rejected: checkpoint.sequence_number(),
+                    retained: earlier.sequence_number(),
+                }),
+                _ => Err(err),
+            };
+        }
+
+        *self = CheckpointState::Complete(checkpoint);
+
+        Ok(())
+    }
+
+    /// Abandon the checkpoint currently being generated, returning to the
+    /// earlier complete checkpoint, if there is one
+    fn roll_back(self) -> Self {
+        match self {
+            CheckpointState::PartialWithEarlier { earlier, .. } => CheckpointState::Complete(earlier),
+            CheckpointState::Complete(checkpoint) => CheckpointState::Complete(checkpoint),
+            CheckpointState::None | CheckpointState::Partial { .. } => CheckpointState::None,
+        }
+    }
+}
+
 impl<S> Orderable for CheckpointState<S> {
     fn sequence_number(&self) -> SeqNo {
         match self {
@@ -1007,10 +1948,402 @@ impl<S> Orderable for CheckpointState<S> {
     }
 }
 
+/// Install a state received through the state transfer protocol with `install`,
+/// emitting the state transfer metrics to the given sink.
+/// Returns how long the installation took
+fn install_state_measured<F>(metrics: &dyn MetricsSink, state_size: u64, install: F) -> Duration
+    where F: FnOnce() {
+    metrics.store_count(TOTAL_STATE_TRANSFERED_ID, 0);
+    metrics.duration_end(TOTAL_STATE_WAIT_ID);
+
+    metrics.store_count(TOTAL_STATE_INSTALLED_ID, 0);
+    metrics.increment(TOTAL_STATE_INSTALLED_ID, Some(state_size));
+
+    let start = Instant::now();
+
+    install();
+
+    let install_time = start.elapsed();
+
+    metrics.duration(STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID, install_time);
+    metrics.duration_end(STATE_TRANSFER_TIME_ID);
+
+    install_time
+}
+
 #[derive(Error, Debug)]
 pub enum StateTransferError {
     #[error("The checkpoint has already been finalized")]
     CheckpointAlreadyFinalized,
     #[error("No checkpoint has been initiated yet")]
-    CheckpointNotInitiated
-}
\ No newline at end of file
+    CheckpointNotInitiated,
+    #[error("The checkpoint for {seq:?} carries digest {expected:?}, but its state has digest {computed:?}")]
+    CheckpointDigestMismatch {
+        seq: SeqNo,
+        expected: Digest,
+        computed: Digest,
+    },
+    #[error("The checkpoint for {rejected:?} was rejected, the checkpoint for {retained:?} was kept instead")]
+    CheckpointRejected {
+        rejected: SeqNo,
+        retained: SeqNo,
+    },
+}
+
+#[cfg(test)]
+mod cst_tests {
+    use std::time::{Duration, Instant};
+
+    use atlas_common::crypto::hash::{Context, Digest};
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::{Orderable, SeqNo};
+    use atlas_core::state_transfer::Checkpoint;
+
+    use std::sync::Mutex;
+
+    use crate::chunks::{ChunkedStateReceiver, StateManifest};
+    use crate::metrics::{MetricsSink, STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID, TOTAL_STATE_INSTALLED_ID};
+
+    use super::{BusyReplicas, CheckpointState, CidRoundResult, CstStatus, install_state_measured, is_agreed_manifest, PhaseTimeout, ProtoPhase, ReceivedStateCids, ReceivedStates, RecoveryState, RoundReplies, RoundTimeouts, StateRoundResult, StateTransferError, verify_checkpoint_digest};
+
+    /// The digest function of our test execution layer, which does not simply hash the serialized state
+    fn execution_digest(serialized: &[u8]) -> Digest {
+        let mut ctx = Context::new();
+
+        ctx.update(b"checkpoint");
+        ctx.update(serialized);
+
+        ctx.finish()
+    }
+
+    /// A metrics sink which captures the durations and increments it receives
+    #[derive(Default)]
+    struct CapturingSink {
+        durations: Mutex<Vec<(usize, Duration)>>,
+        increments: Mutex<Vec<(usize, Option<u64>)>>,
+    }
+
+    impl MetricsSink for CapturingSink {
+        fn duration(&self, metric: usize, duration: Duration) {
+            self.durations.lock().unwrap().push((metric, duration));
+        }
+
+        fn duration_start(&self, _metric: usize) {}
+
+        fn duration_end(&self, _metric: usize) {}
+
+        fn increment(&self, metric: usize, amount: Option<u64>) {
+            self.increments.lock().unwrap().push((metric, amount));
+        }
+
+        fn store_count(&self, _metric: usize, _amount: usize) {}
+    }
+
+    #[test]
+    fn test_state_install_metrics_are_emitted_to_the_sink() {
+        let sink = CapturingSink::default();
+
+        let mut installed = false;
+
+        install_state_measured(&sink, 1024, || installed = true);
+
+        assert!(installed);
+
+        let durations = sink.durations.lock().unwrap();
+
+        assert_eq!(durations.len(), 1);
+        assert_eq!(durations[0].0, STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID);
+
+        assert_eq!(*sink.increments.lock().unwrap(), vec![(TOTAL_STATE_INSTALLED_ID, Some(1024))]);
+    }
+
+    #[test]
+    fn test_phase_timeouts_are_independent() {
+        let mut timeouts = RoundTimeouts::new(Duration::from_millis(500), Duration::from_secs(30), Duration::from_secs(600));
+
+        let serialized: Vec<u8> = (0..100u8).collect();
+        let manifest = StateManifest::new(SeqNo::ZERO.next(), Digest::from_bytes(&[1; Digest::LENGTH]).unwrap(), &serialized, 32);
+
+        let cid: ProtoPhase<u64> = ProtoPhase::ReceivingCid(0);
+
+        let state_phases: [ProtoPhase<u64>; 3] = [
+            ProtoPhase::ReceivingState(0),
+            ProtoPhase::ReceivingManifest(0),
+            ProtoPhase::ReceivingChunks(ChunkedStateReceiver::new(manifest, vec![NodeId(1), NodeId(2)])),
+        ];
+
+        // Every phase of the state round registers the state timeout, and backs it off when it times out
+        let mut expected = Duration::from_secs(30);
+
+        for phase in &state_phases {
+            assert_eq!(timeouts.current(phase), Some(expected));
+
+            assert!(matches!(timeouts.timed_out(phase), CstStatus::RequestState));
+
+            expected *= 2;
+
+            assert_eq!(timeouts.current(phase), Some(expected));
+            assert_eq!(timeouts.current(&cid), Some(Duration::from_millis(500)));
+        }
+
+        assert!(matches!(timeouts.timed_out(&cid), CstStatus::RequestStateCid));
+        assert_eq!(timeouts.current(&cid), Some(Duration::from_secs(1)));
+        assert_eq!(timeouts.current(&state_phases[0]), Some(Duration::from_secs(240)));
+
+        // Once the state round completes, only its timeout is reset
+        timeouts.state.reset();
+
+        for phase in &state_phases {
+            assert_eq!(timeouts.current(phase), Some(Duration::from_secs(30)));
+        }
+
+        assert_eq!(timeouts.current(&cid), Some(Duration::from_secs(1)));
+
+        // Phases which are not waiting for replies have no timeout
+        assert!(timeouts.current(&ProtoPhase::<u64>::Init).is_none());
+        assert!(matches!(timeouts.timed_out(&ProtoPhase::<u64>::Init), CstStatus::Nil));
+    }
+
+    #[test]
+    fn test_phase_timeout_is_capped() {
+        let mut timeout = PhaseTimeout::new(Duration::from_secs(1), Duration::from_secs(5));
+
+        for _ in 0..10 {
+            timeout.back_off();
+        }
+
+        assert_eq!(timeout.current(), Duration::from_secs(5));
+
+        timeout.reset();
+        assert_eq!(timeout.current(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_equivocating_replica_is_counted_once() {
+        let seq = SeqNo::ZERO.next();
+
+        let good_digest = Digest::from_bytes(&[1; Digest::LENGTH]).unwrap();
+        let bad_digest = Digest::from_bytes(&[2; Digest::LENGTH]).unwrap();
+
+        // n = 4, f = 1
+        let (weak_quorum, strong_quorum) = (2, 3);
+
+        // In the CID round, replica 3 is faulty and keeps sending conflicting checkpoints
+        let mut replies = RoundReplies::default();
+        let mut cids = ReceivedStateCids::new();
+
+        let (good, bad) = ((seq, good_digest.clone()), (seq, bad_digest.clone()));
+
+        let received = [
+            (NodeId(1), Some(&good)),
+            (NodeId(3), Some(&bad)),
+            (NodeId(3), Some(&good)),
+            (NodeId(3), None),
+            (NodeId(2), Some(&good)),
+        ];
+
+        let accepted = received.into_iter()
+            .filter(|(from, cid)| cids.receive(&mut replies, *from, *cid))
+            .count();
+
+        assert_eq!(accepted, 3);
+
+        // Only the first reply of replica 3 counted, so the good checkpoint is not vouched for by a strong quorum yet
+        assert_eq!(cids.conclude(accepted, strong_quorum), CidRoundResult::Running);
+
+        assert!(cids.receive(&mut replies, NodeId(0), Some(&good)));
+        assert_eq!(cids.conclude(accepted + 1, strong_quorum), CidRoundResult::Agreed(seq, good_digest.clone()));
+
+        // In the state round, replica 3 keeps sending conflicting states
+        replies.clear();
+
+        let mut states = ReceivedStates::new();
+
+        let state = |digest: &Digest| RecoveryState::new(Checkpoint::new(seq, 10u64, digest.clone()));
+
+        assert!(states.receive(&mut replies, NodeId(3), state(&bad_digest)));
+        assert!(!states.receive(&mut replies, NodeId(3), state(&bad_digest)));
+        assert!(!states.receive(&mut replies, NodeId(3), state(&good_digest)));
+
+        assert!(states.receive(&mut replies, NodeId(1), state(&good_digest)));
+
+        // Neither the bad state nor the good one got a weak quorum from the repeated replies
+        assert!(matches!(states.conclude(2, weak_quorum, strong_quorum), StateRoundResult::Running));
+
+        assert!(states.receive(&mut replies, NodeId(2), state(&good_digest)));
+
+        match states.conclude(3, weak_quorum, strong_quorum) {
+            StateRoundResult::Agreed(digest, _) => assert_eq!(digest, good_digest),
+            _ => panic!("The good state should have been accepted"),
+        }
+
+        // Once a new round starts, the replica can reply again
+        replies.clear();
+        assert!(replies.register(NodeId(3)));
+    }
+
+    #[test]
+    fn test_busy_replica_is_retried_after_its_hint() {
+        let mut busy = BusyReplicas::default();
+
+        let now = Instant::now();
+
+        busy.busy(NodeId(1), Duration::from_millis(100), now);
+        busy.busy(NodeId(2), Duration::from_secs(1), now);
+
+        // Nobody is asked again before the delay they asked for
+        assert!(busy.take_due(now).is_empty());
+
+        assert_eq!(busy.take_due(now + Duration::from_millis(100)), vec![NodeId(1)]);
+        // Each busy reply results in a single retry
+        assert!(busy.take_due(now + Duration::from_millis(100)).is_empty());
+
+        assert_eq!(busy.take_due(now + Duration::from_secs(1)), vec![NodeId(2)]);
+
+        // A new round forgets about the busy replicas of the previous one
+        busy.busy(NodeId(3), Duration::ZERO, now);
+        busy.clear();
+
+        assert!(busy.take_due(now).is_empty());
+    }
+
+    #[test]
+    fn test_manifest_must_match_agreed_checkpoint() {
+        let serialized: Vec<u8> = (0..100u8).collect();
+
+        let agreed_digest = Digest::from_bytes(&[1; Digest::LENGTH]).unwrap();
+        let other_digest = Digest::from_bytes(&[2; Digest::LENGTH]).unwrap();
+
+        let seq = SeqNo::ZERO.next();
+
+        let agreed = StateManifest::new(seq, agreed_digest.clone(), &serialized, 32);
+        let other_state = StateManifest::new(seq, other_digest, &serialized, 32);
+        let other_seq = StateManifest::new(seq.next(), agreed_digest.clone(), &serialized, 32);
+
+        let agreed_checkpoint = (seq, agreed_digest);
+
+        assert!(is_agreed_manifest(&agreed, Some(&agreed_checkpoint)));
+        assert!(!is_agreed_manifest(&other_state, Some(&agreed_checkpoint)));
+        assert!(!is_agreed_manifest(&other_seq, Some(&agreed_checkpoint)));
+
+        assert!(is_agreed_manifest(&other_state, None));
+    }
+
+    #[test]
+    fn test_corrupted_checkpoint_keeps_earlier() {
+        let earlier_seq = SeqNo::ZERO.next();
+        let seq = earlier_seq.next();
+
+        let earlier = Checkpoint::new(earlier_seq, 10u64, execution_digest(&10u64.to_le_bytes()));
+
+        // The next checkpoint is being generated when the application delivers a corrupted one
+        let mut checkpoint_state = CheckpointState::PartialWithEarlier {
+            seq,
+            earlier,
+        };
+
+        let corrupted = Checkpoint::new(seq, 11u64, execution_digest(&12u64.to_le_bytes()));
+
+        let verification = verify_checkpoint_digest(&corrupted, &11u64.to_le_bytes(), &execution_digest);
+
+        match checkpoint_state.complete(corrupted, verification) {
+            Err(StateTransferError::CheckpointRejected { rejected, retained }) => {
+                assert_eq!(rejected, seq);
+                assert_eq!(retained, earlier_seq);
+            }
+            _ => panic!("The corrupted checkpoint should have been rejected"),
+        }
+
+        // We keep serving the earlier checkpoint
+        let served = checkpoint_state.serveable().expect("The earlier checkpoint should have been retained");
+
+        assert_eq!(served.sequence_number(), earlier_seq);
+        assert_eq!(*served.state(), 10u64);
+
+        // Without an earlier checkpoint, there is nothing to fall back to
+        let mut checkpoint_state: CheckpointState<u64> = CheckpointState::Partial { seq: earlier_seq };
+
+        let corrupted = Checkpoint::new(earlier_seq, 11u64, execution_digest(&12u64.to_le_bytes()));
+
+        let verification = verify_checkpoint_digest(&corrupted, &11u64.to_le_bytes(), &execution_digest);
+
+        assert!(matches!(checkpoint_state.complete(corrupted, verification), Err(StateTransferError::CheckpointDigestMismatch { .. })));
+        assert!(checkpoint_state.serveable().is_none());
+    }
+
+    #[test]
+    fn test_finalize_checkpoint_verifies_with_the_execution_digest() {
+        let seq = SeqNo::ZERO.next();
+
+        let state = 10u64;
+        let serialized = state.to_le_bytes();
+
+        // A correct checkpoint carries the digest the execution layer computed for it
+        let correct = Checkpoint::new(seq, state, execution_digest(&serialized));
+
+        let mut checkpoint_state: CheckpointState<u64> = CheckpointState::Partial { seq };
+
+        let verification = verify_checkpoint_digest(&correct, &serialized, &execution_digest);
+
+        assert!(checkpoint_state.complete(correct, verification).is_ok());
+        assert!(matches!(&checkpoint_state, CheckpointState::Complete(checkpoint) if checkpoint.sequence_number() == seq));
+
+        // A corrupted checkpoint carries a digest which does not match its state
+        let corrupted_serialized = 11u64.to_le_bytes();
+        let corrupted = Checkpoint::new(seq.next(), 11u64, execution_digest(&serialized));
+
+        let mut checkpoint_state: CheckpointState<u64> = CheckpointState::Partial { seq: seq.next() };
+
+        let verification = verify_checkpoint_digest(&corrupted, &corrupted_serialized, &execution_digest);
+
+        assert!(matches!(verification, Err(StateTransferError::CheckpointDigestMismatch { .. })));
+        assert!(matches!(checkpoint_state.complete(corrupted, verification), Err(StateTransferError::CheckpointDigestMismatch { .. })));
+        assert!(matches!(checkpoint_state, CheckpointState::None));
+    }
+
+    #[test]
+    fn test_faulty_replicas_cannot_certify_a_state() {
+        let seq = SeqNo::ZERO.next();
+
+        let correct_digest = Digest::from_bytes(&[1; Digest::LENGTH]).unwrap();
+        let bogus_digest = Digest::from_bytes(&[2; Digest::LENGTH]).unwrap();
+
+        for f in 1..4 {
+            let (weak_quorum, strong_quorum) = (f + 1, 2 * f + 1);
+
+            let mut replies = RoundReplies::default();
+            let mut states = ReceivedStates::new();
+
+            let mut received = 0;
+
+            // All the faulty replicas reply first, with the same bogus state
+            for faulty in 0..f {
+                let bogus = RecoveryState::new(Checkpoint::new(seq, 0u64, bogus_digest.clone()));
+
+                assert!(states.receive(&mut replies, NodeId(faulty as u32), bogus));
+                received += 1;
+
+                assert!(matches!(states.conclude(received, weak_quorum, strong_quorum), StateRoundResult::Running));
+            }
+
+            // Followed by the correct replicas, which are a minority of the replies
+            // until a weak quorum of them vouches for their state
+            for correct in 0..weak_quorum {
+                let state = RecoveryState::new(Checkpoint::new(seq, 10u64, correct_digest.clone()));
+
+                assert!(states.receive(&mut replies, NodeId((f + correct) as u32), state));
+                received += 1;
+
+                match states.conclude(received, weak_quorum, strong_quorum) {
+                    StateRoundResult::Running => assert!(correct + 1 < weak_quorum),
+                    StateRoundResult::Agreed(digest, state) => {
+                        assert_eq!(correct + 1, weak_quorum);
+                        assert_eq!(digest, correct_digest);
+                        assert_eq!(*state.checkpoint().state(), 10u64);
+                    }
+                    StateRoundResult::NoQuorum => panic!("The bogus state must not prevent the correct one from being accepted"),
+                }
+            }
+        }
+    }
+}
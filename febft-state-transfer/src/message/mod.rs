@@ -1,4 +1,5 @@
 use std::fmt::{Debug, Formatter};
+use std::time::Duration;
 
 #[cfg(feature = "serialize_serde")]
 use serde::{Deserialize, Serialize};
@@ -6,6 +7,7 @@ use atlas_common::crypto::hash::Digest;
 
 use atlas_common::ordering::{Orderable, SeqNo};
 
+use crate::chunks::StateManifest;
 use crate::RecoveryState;
 
 pub mod serialize;
@@ -39,6 +41,28 @@ impl<S> Debug for CstMessage<S> {
                     write!(f, "Reply with state cid message None")
                 }
             }
+            CstMessageKind::CheckpointDigest(seq, digest) => {
+                write!(f, "Checkpoint digest message {:?} {:?}", seq, digest)
+            }
+            CstMessageKind::RequestStateManifest => {
+                write!(f, "Request state manifest message")
+            }
+            CstMessageKind::ReplyStateManifest(manifest) => {
+                if let Some(manifest) = manifest {
+                    write!(f, "Reply with state manifest message {:?} {:?}", manifest.sequence_number(), manifest.digest())
+                } else {
+                    write!(f, "Reply with state manifest message None")
+                }
+            }
+            CstMessageKind::RequestStateChunks { seq, chunks } => {
+                write!(f, "Request state chunks message {:?} {:?}", seq, chunks)
+            }
+            CstMessageKind::ReplyStateChunk { offset, len, .. } => {
+                write!(f, "Reply with state chunk message {} {}", offset, len)
+            }
+            CstMessageKind::ReplyStateBusy(retry_after) => {
+                write!(f, "Reply state busy, retry after {:?}", retry_after)
+            }
         }
     }
 }
@@ -50,6 +74,28 @@ pub enum CstMessageKind<S> {
     ReplyStateCid(Option<(SeqNo, Digest)>),
     RequestState,
     ReplyState(RecoveryState<S>),
+    /// Announce the digest of our latest checkpoint, so replicas can
+    /// detect if their states have diverged
+    CheckpointDigest(SeqNo, Digest),
+    /// Request the manifest of the latest checkpoint, describing the
+    /// chunks its state is split into
+    RequestStateManifest,
+    ReplyStateManifest(Option<StateManifest>),
+    /// Request chunks (offset and length) of the serialized state of the checkpoint at `seq`.
+    /// All the chunks requested from a replica are sent in a single request, so they
+    /// are served as a whole when the replica has a free serving slot
+    RequestStateChunks {
+        seq: SeqNo,
+        chunks: Vec<(u64, u64)>,
+    },
+    ReplyStateChunk {
+        offset: u64,
+        len: u64,
+        data: Vec<u8>,
+    },
+    /// The replica is serving too many states at the moment,
+    /// the request should be retried after the given duration
+    ReplyStateBusy(Duration),
 }
 
 impl<S> Orderable for CstMessage<S> {
@@ -1,5 +1,7 @@
+use std::time::Duration;
+
 use atlas_metrics::{MetricLevel, MetricRegistry};
-use atlas_metrics::metrics::MetricKind;
+use atlas_metrics::metrics::{metric_duration, metric_duration_end, metric_duration_start, metric_increment, metric_store_count, MetricKind};
 
 /// State transfer will take the
 /// 6XX metric ID range
@@ -24,6 +26,12 @@ pub const TOTAL_STATE_INSTALLED_ID : usize = 605;
 pub const TOTAL_STATE_WAIT : &str = "STATE_WAIT_TIME";
 pub const TOTAL_STATE_WAIT_ID : usize = 606;
 
+pub const STATE_REQUESTS_REJECTED : &str = "STATE_REQUESTS_REJECTED";
+pub const STATE_REQUESTS_REJECTED_ID : usize = 607;
+
+pub const CHECKPOINTS_OVERLAPPED : &str = "CHECKPOINTS_OVERLAPPED";
+pub const CHECKPOINTS_OVERLAPPED_ID : usize = 608;
+
 pub fn metrics() -> Vec<MetricRegistry> {
     vec![
         (STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID, STATE_TRANSFER_STATE_INSTALL_CLONE_TIME.to_string(), MetricKind::Duration, MetricLevel::Info).into(),
@@ -33,5 +41,54 @@ pub fn metrics() -> Vec<MetricRegistry> {
         (TOTAL_STATE_TRANSFERED_ID, TOTAL_STATE_TRANSFERED.to_string(), MetricKind::Counter, MetricLevel::Info).into(),
         (TOTAL_STATE_INSTALLED_ID, TOTAL_STATE_INSTALLED.to_string(), MetricKind::Counter, MetricLevel::Info).into(),
         (TOTAL_STATE_WAIT_ID, TOTAL_STATE_WAIT.to_string(), MetricKind::Duration, MetricLevel::Info).into(),
+        (STATE_REQUESTS_REJECTED_ID, STATE_REQUESTS_REJECTED.to_string(), MetricKind::Counter, MetricLevel::Info).into(),
+        (CHECKPOINTS_OVERLAPPED_ID, CHECKPOINTS_OVERLAPPED.to_string(), MetricKind::Counter, MetricLevel::Info).into(),
     ]
-}
\ No newline at end of file
+}
+
+/// The sink to which the state transfer protocol emits its metrics.
+/// By default, these are sent to `atlas_metrics`, but this can be replaced
+/// in order to use a different metrics backend.
+///
+/// The ids passed to the sink are the ones defined in this module.
+pub trait MetricsSink: Send + Sync {
+    /// Record the duration of an event
+    fn duration(&self, metric: usize, duration: Duration);
+
+    /// Start measuring the duration of an event
+    fn duration_start(&self, metric: usize);
+
+    /// Stop measuring the duration of an event, recording it
+    fn duration_end(&self, metric: usize);
+
+    /// Increment a counter
+    fn increment(&self, metric: usize, amount: Option<u64>);
+
+    /// Store the value of a counter
+    fn store_count(&self, metric: usize, amount: usize);
+}
+
+/// The default metrics sink, backed by `atlas_metrics`
+pub struct AtlasMetricsSink;
+
+impl MetricsSink for AtlasMetricsSink {
+    fn duration(&self, metric: usize, duration: Duration) {
+        metric_duration(metric, duration)
+    }
+
+    fn duration_start(&self, metric: usize) {
+        metric_duration_start(metric)
+    }
+
+    fn duration_end(&self, metric: usize) {
+        metric_duration_end(metric)
+    }
+
+    fn increment(&self, metric: usize, amount: Option<u64>) {
+        metric_increment(metric, amount)
+    }
+
+    fn store_count(&self, metric: usize, amount: usize) {
+        metric_store_count(metric, amount)
+    }
+}
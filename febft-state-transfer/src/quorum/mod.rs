@@ -0,0 +1,37 @@
+use atlas_core::ordering_protocol::networking::serialize::NetworkView;
+
+/// The reply thresholds used by the rounds of the CST protocol.
+///
+/// With `n >= 3f + 1` replicas, of which at most `f` are faulty:
+///
+/// - A *strong* quorum (`2f + 1` replies) is needed to learn the latest checkpoint
+///   sequence number, since the replies can legitimately differ (replicas may be at
+///   different points of execution). Any two strong quorums intersect in at least one
+///   correct replica, so the sequence number we settle on was seen by a correct replica
+///   and cannot be made up by the faulty ones.
+/// - A *weak* quorum (`f + 1` matching replies) is enough to accept a given state (or
+///   state manifest), since replies are only counted when they match exactly. At least
+///   one of them comes from a correct replica, which only sends states it has executed,
+///   so `f` faulty replicas can never get a state of their own accepted.
+pub trait CstQuorums {
+    /// The amount of replies needed to learn the latest checkpoint sequence number
+    fn strong_quorum(&self) -> usize;
+
+    /// The amount of matching replies needed to accept a state
+    fn weak_quorum(&self) -> usize;
+}
+
+impl<V> CstQuorums for V where V: NetworkView {
+    fn strong_quorum(&self) -> usize {
+        self.quorum()
+    }
+
+    fn weak_quorum(&self) -> usize {
+        weak_quorum(self.f())
+    }
+}
+
+fn weak_quorum(f: usize) -> usize {
+    f + 1
+}
+
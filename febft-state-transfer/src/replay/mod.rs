@@ -0,0 +1,118 @@
+use thiserror::Error;
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::Err;
+use atlas_common::error::*;
+use atlas_common::globals::ReadOnly;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_core::state_transfer::Checkpoint;
+
+/// Verify that the checkpoint `target` is consistent with applying the decided log
+/// on top of a base checkpoint.
+///
+/// `base_state` must be a fresh copy of the state of the base checkpoint, and `decided`
+/// the decisions taken from the base checkpoint up to (and including) the sequence
+/// number of `target`, in order. Each of them is re-executed with `execute` and the
+/// digest of the resulting state (calculated with `digest`) is compared with the one
+/// of `target`.
+///
+/// This is meant for offline auditing: a mismatch means that either the execution
+/// is not deterministic or the stored checkpoint is corrupted.
+pub fn verify_checkpoint_replay<S, B, E, H>(base_state: S,
+                                            decided: impl IntoIterator<Item=B>,
+                                            execute: E,
+                                            digest: H,
+                                            target: &ReadOnly<Checkpoint<S>>) -> Result<()>
+    where E: FnMut(&mut S, B),
+          H: FnOnce(&S) -> Result<Digest> {
+    verify_replay(base_state, decided, execute, digest, target.sequence_number(), target.digest())
+}
+
+/// Re-execute the `decided` batches on top of `state` and check that the resulting
+/// state digest matches the `expected` digest for the checkpoint at `seq`
+pub fn verify_replay<S, B, E, H>(mut state: S,
+                                 decided: impl IntoIterator<Item=B>,
+                                 mut execute: E,
+                                 digest: H,
+                                 seq: SeqNo,
+                                 expected: &Digest) -> Result<()>
+    where E: FnMut(&mut S, B),
+          H: FnOnce(&S) -> Result<Digest> {
+    for batch in decided {
+        execute(&mut state, batch);
+    }
+
+    let replayed = digest(&state)?;
+
+    if replayed != *expected {
+        return Err!(ReplayError::DigestMismatch {
+            seq,
+            expected: expected.clone(),
+            replayed,
+        });
+    }
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("Replaying the decided log up to {seq:?} resulted in digest {replayed:?}, but the checkpoint has digest {expected:?}")]
+    DigestMismatch {
+        seq: SeqNo,
+        expected: Digest,
+        replayed: Digest,
+    }
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use atlas_common::crypto::hash::{Context, Digest};
+    use atlas_common::error::*;
+    use atlas_common::ordering::SeqNo;
+
+    use super::verify_replay;
+
+    fn digest_of(state: &Vec<u64>) -> Result<Digest> {
+        let mut ctx = Context::new();
+
+        for value in state {
+            ctx.update(&value.to_le_bytes());
+        }
+
+        Ok(ctx.finish())
+    }
+
+    #[test]
+    fn test_nondeterministic_execution_is_detected() {
+        let decided: Vec<Vec<u64>> = vec![vec![1, 2], vec![3], vec![4, 5, 6]];
+
+        let execute = |state: &mut Vec<u64>, batch: Vec<u64>| state.extend(batch);
+
+        let mut expected_state = Vec::new();
+
+        for batch in decided.clone() {
+            execute(&mut expected_state, batch);
+        }
+
+        let checkpoint_digest = digest_of(&expected_state).unwrap();
+
+        let seq = SeqNo::ZERO.next();
+
+        assert!(verify_replay(Vec::new(), decided.clone(), execute, digest_of, seq, &checkpoint_digest).is_ok());
+
+        // An execution which does not produce the same state from the same decisions
+        let nondeterministic = |state: &mut Vec<u64>, batch: Vec<u64>| {
+            let len = state.len() as u64;
+
+            state.extend(batch.into_iter().map(|value| value + len));
+        };
+
+        assert!(verify_replay(Vec::new(), decided.clone(), nondeterministic, digest_of, seq, &checkpoint_digest).is_err());
+
+        // A corrupted checkpoint is detected as well
+        let corrupted_digest = Digest::from_bytes(&[0; Digest::LENGTH]).unwrap();
+
+        assert!(verify_replay(Vec::new(), decided, execute, digest_of, seq, &corrupted_digest).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "serialize_serde")]
+use serde::Deserialize;
+
+use atlas_common::node_id::NodeId;
+
+/// Configuration of how this replica serves its state to recovering replicas
+#[cfg_attr(feature = "serialize_serde", derive(Deserialize))]
+#[cfg_attr(feature = "serialize_serde", serde(default))]
+#[derive(Debug, Clone)]
+pub struct StateServingConfig {
+    /// The maximum amount of state replies that can be serialized and sent at the same time
+    pub max_concurrent_replies: usize,
+    /// How long a state reply is considered to be in flight.
+    /// Since the state is serialized and sent by the network layer, we do not know when
+    /// it actually finishes, so we hold the serving slot for this long
+    pub reply_slot_duration: Duration,
+    /// The maximum amount of state requests waiting for a free slot.
+    /// Requests beyond this are rejected with a retry hint
+    pub max_queued_requests: usize,
+    /// The largest serialized state (in bytes) we keep in memory to serve its chunks from.
+    /// Larger states are serialized again every time their chunks are served
+    pub max_cached_state_size: usize,
+}
+
+impl Default for StateServingConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_replies: 2,
+            reply_slot_duration: Duration::from_secs(1),
+            max_queued_requests: 16,
+            max_cached_state_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Limits the amount of state replies this replica serves at the same time,
+/// so that a wave of recovering replicas does not overwhelm it.
+///
+/// Only the state round is limited, since the CID round replies are cheap
+/// and are always served right away.
+pub struct StateServingLimiter<R> {
+    config: StateServingConfig,
+    // The instants at which the slots currently in use will be freed
+    in_flight: VecDeque<Instant>,
+    // The requests waiting for a free slot, at most one per replica
+    queued: VecDeque<(NodeId, R)>,
+}
+
+impl<R> StateServingLimiter<R> {
+    pub fn new(config: StateServingConfig) -> Self {
+        Self {
+            in_flight: VecDeque::with_capacity(config.max_concurrent_replies),
+            queued: VecDeque::with_capacity(config.max_queued_requests),
+            config,
+        }
+    }
+
+    /// Queue a state request to be served when a slot is available.
+    /// A replica only has one queued request, so a newer request from the same
+    /// replica replaces the older one.
+    /// When the queue is full, the request is returned along with how long the
+    /// requesting replica should wait before trying again
+    pub fn enqueue(&mut self, from: NodeId, request: R, now: Instant) -> std::result::Result<(), (R, Duration)> {
+        if let Some((_, queued)) = self.queued.iter_mut().find(|(node, _)| *node == from) {
+            *queued = request;
+
+            return Ok(());
+        }
+
+        if self.queued.len() >= self.config.max_queued_requests {
+            let retry_after = self.retry_hint(now);
+
+            return Err((request, retry_after));
+        }
+
+        self.queued.push_back((from, request));
+
+        Ok(())
+    }
+
+    /// Take the next queued request, if there is a free slot to serve it.
+    /// The slot is marked as in use until `reply_slot_duration` has elapsed
+    pub fn next_to_serve(&mut self, now: Instant) -> Option<(NodeId, R)> {
+        self.release_slots(now);
+
+        if self.in_flight.len() >= self.config.max_concurrent_replies {
+            return None;
+        }
+
+        let request = self.queued.pop_front()?;
+
+        self.in_flight.push_back(now + self.config.reply_slot_duration);
+
+        Some(request)
+    }
+
+    pub fn config(&self) -> &StateServingConfig {
+        &self.config
+    }
+
+    /// The amount of replies currently in flight
+    pub fn in_flight(&mut self, now: Instant) -> usize {
+        self.release_slots(now);
+
+        self.in_flight.len()
+    }
+
+    /// The amount of requests waiting for a free slot
+    pub fn queued(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// An estimate of how long it will take until a new request could be served
+    fn retry_hint(&self, now: Instant) -> Duration {
+        let first_free = self.in_flight.front()
+            .map(|free_at| free_at.saturating_duration_since(now))
+            .unwrap_or(Duration::ZERO);
+
+        let rounds = (self.queued.len() / self.config.max_concurrent_replies.max(1)) as u32;
+
+        first_free + self.config.reply_slot_duration * rounds
+    }
+
+    fn release_slots(&mut self, now: Instant) {
+        while let Some(free_at) = self.in_flight.front() {
+            if *free_at > now {
+                break;
+            }
+
+            self.in_flight.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod serving_tests {
+    use std::time::{Duration, Instant};
+
+    use atlas_common::node_id::NodeId;
+
+    use super::{StateServingConfig, StateServingLimiter};
+
+    #[test]
+    fn test_state_replies_respect_concurrency_cap() {
+        let config = StateServingConfig {
+            max_concurrent_replies: 2,
+            reply_slot_duration: Duration::from_secs(1),
+            max_queued_requests: 4,
+            max_cached_state_size: 0,
+        };
+
+        let mut limiter = StateServingLimiter::new(config);
+
+        let start = Instant::now();
+
+        for node in 0..4 {
+            assert!(limiter.enqueue(NodeId(node), node, start).is_ok());
+        }
+
+        // A newer request from an already queued replica replaces its older one
+        assert!(limiter.enqueue(NodeId(0), 0, start).is_ok());
+
+        // The queue is full, so further requests are rejected with a retry hint
+        let (rejected, retry_after) = limiter.enqueue(NodeId(4), 4, start).unwrap_err();
+
+        assert_eq!(rejected, 4);
+        assert!(retry_after > Duration::ZERO);
+
+        let mut served = Vec::new();
+
+        while let Some((node, _)) = limiter.next_to_serve(start) {
+            served.push(node);
+        }
+
+        assert_eq!(served, vec![NodeId(0), NodeId(1)]);
+        assert_eq!(limiter.in_flight(start), 2);
+        assert_eq!(limiter.queued(), 2);
+
+        // Once the slots are freed, the remaining requests are served
+        let later = start + Duration::from_secs(1);
+
+        while let Some((node, _)) = limiter.next_to_serve(later) {
+            served.push(node);
+        }
+
+        assert_eq!(served, vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)]);
+        assert_eq!(limiter.queued(), 0);
+    }
+}